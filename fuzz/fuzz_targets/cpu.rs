@@ -0,0 +1,65 @@
+//Runs arbitrary programs with arbitrary key presses for a few seconds of emulated time.
+//Input layout: a settings byte, a count n of key frames, n keypad bitmasks (u16 little endian,
+//bit k for key k, used in turn one per frame) and then the ROM.
+//Settings bit 0 selects COSMAC VIP timing, bit 1 records an undo log and rewinds it afterwards.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use chip8_emulator::cpu::CPU;
+use chip8_emulator::emulator::TICKS_PER_FRAME;
+use chip8_emulator::MEMORY_SIZE;
+
+const FRAMES: usize = 120;
+const STACK_SIZE: usize = 16;
+
+fn check_invariants(cpu: &CPU) {
+    let registers = cpu.registers();
+    assert!(registers.sp as usize <= STACK_SIZE, "SP {} outside the stack", registers.sp);
+    assert!((registers.pc as usize) < MEMORY_SIZE, "PC {:X} outside memory", registers.pc);
+    assert!((registers.i as usize) < MEMORY_SIZE, "I {:X} outside the address space", registers.i);
+}
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 2 {
+        return;
+    }
+    let (settings, key_frames) = (data[0], data[1] as usize);
+    let rom_start = (2 + key_frames * 2).min(data.len());
+    let keys: Vec<[bool; 16]> = data[2..rom_start].chunks_exact(2).map(|pair| {
+        let mask = u16::from_le_bytes([pair[0], pair[1]]);
+        std::array::from_fn(|key| mask & (1 << key) != 0)
+    }).collect();
+    let vip_timing = settings & 1 != 0;
+    let rewind = settings & 2 != 0;
+
+    let mut cpu = CPU::new();
+    cpu.load(&data[rom_start..]);
+    if rewind {
+        cpu.set_history_limit(usize::MAX);
+    }
+    let initial_registers = cpu.registers();
+    let initial_memory = cpu.memory().to_vec();
+
+    for frame in 0..FRAMES {
+        let keypad = keys.get(frame % keys.len().max(1)).copied().unwrap_or([false; 16]);
+        if vip_timing {
+            cpu.run_frame(&keypad);
+        } else {
+            for _ in 0..TICKS_PER_FRAME {
+                cpu.tick(&keypad);
+            }
+        }
+        cpu.end_frame();
+        check_invariants(&cpu);
+    }
+
+    if rewind {
+        while cpu.step_back() {
+            check_invariants(&cpu);
+        }
+        assert_eq!(cpu.registers(), initial_registers);
+        assert!(cpu.memory() == initial_memory.as_slice(), "memory differs after rewinding");
+        assert!(cpu.vram().iter().flatten().all(|&pixel| pixel == 0), "display not clear after rewinding");
+    }
+});
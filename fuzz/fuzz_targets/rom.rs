@@ -0,0 +1,33 @@
+//Loads arbitrary files as ROMs and checks they are accepted or rejected as their size says,
+//and end up in memory as read. Archives and cartridges only have to load or fail cleanly
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use chip8_emulator::cpu::CPU;
+use chip8_emulator::drivers::{Platform, RomError, ROM};
+
+fuzz_target!(|data: &[u8]| {
+    let path = std::env::temp_dir().join(format!("chip8_fuzz_{}.rom", std::process::id()));
+    std::fs::write(&path, data).unwrap();
+    let limit = Platform::Chip8.max_rom_size();
+    let packed = data.starts_with(b"PK\x03\x04") || data.starts_with(&[0x1F, 0x8B]) || data.starts_with(b"GIF8");
+    if packed {
+        if let Ok(rom) = ROM::from(path.to_str().unwrap(), Platform::Chip8) {
+            assert!(!rom.data.is_empty() && rom.data.len() <= limit);
+        }
+        return;
+    }
+    let rom = match ROM::from(path.to_str().unwrap(), Platform::Chip8) {
+        Ok(rom) => rom,
+        Err(RomError::Empty) => return assert!(data.is_empty()),
+        Err(RomError::TooLarge{size, limit: reported}) => return assert!(size == data.len() && reported == limit && size > limit),
+        Err(error) => panic!("{:?}", error),
+    };
+    assert_eq!(rom.data, data);
+    let _ = rom.warning();
+
+    let mut cpu = CPU::new();
+    cpu.load(&rom.data);
+    assert_eq!(cpu.memory()[0x200..0x200 + rom.data.len()], rom.data[..]);
+});
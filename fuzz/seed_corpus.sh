@@ -0,0 +1,14 @@
+#!/bin/sh
+#Seeds the fuzzing corpora with the bundled ROMs, then run a target with
+#  cargo +nightly fuzz run cpu -- -close_fd_mask=1
+#The CPU prints invalid opcodes to stdout, -close_fd_mask=1 keeps that out of the way.
+set -e
+cd "$(dirname "$0")"
+mkdir -p corpus/cpu corpus/rom
+for rom in ../*.rom ../*.ch8 ../*.c8; do
+    name=$(basename "$rom")
+    cp "$rom" "corpus/rom/$name"
+    #No key frames, once with instruction timing and once with COSMAC VIP timing
+    { printf '\000\000'; cat "$rom"; } > "corpus/cpu/$name"
+    { printf '\001\000'; cat "$rom"; } > "corpus/cpu/vip-$name"
+done
@@ -0,0 +1,301 @@
+//Cheats for finding and pinning down values in memory, like the lives counter of a game.
+//A RAM search starts from every address and narrows them down by how their values moved
+//between searches. Found addresses become named cheats that either freeze a value, writing it
+//every frame, or poke it once. Cheats are kept in one file per ROM, named after its SHA-1:
+//  # name address kind value [off]
+//  lives 1F4 freeze 05
+use std::fs;
+use std::io::{self, BufRead, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use sha1_smol::Sha1;
+
+use crate::cpu::CPU;
+use crate::MEMORY_SIZE;
+
+const EXTENSION: &str = "cht";
+//Candidates listed after each search
+const LISTED_CANDIDATES: usize = 16;
+
+const HELP: &str = "search                         start a new search over all of memory
+eq NN | inc | dec | changed | same   keep addresses whose value matches since the last search
+add name address freeze|poke NN      add a cheat
+toggle name | remove name            switch a cheat off and on, or delete it
+list | save                          show the cheats, or write them to the cheat file
+Addresses and values are hex";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Comparison {
+    Equal(u8),
+    Increased,
+    Decreased,
+    Changed,
+    Unchanged,
+}
+
+//Addresses still in the running, and memory as it was at the last search
+pub struct RamSearch {
+    candidates: Vec<usize>,
+    previous: Vec<u8>,
+}
+
+impl RamSearch {
+    pub fn new(memory: &[u8]) -> Self {
+        RamSearch{candidates: (0..memory.len()).collect(), previous: memory.to_vec()}
+    }
+
+    pub fn filter(&mut self, memory: &[u8], comparison: Comparison) {
+        let previous = &self.previous;
+        self.candidates.retain(|&address| {
+            let (before, now) = (previous[address], memory[address]);
+            match comparison {
+                Comparison::Equal(value) => now == value,
+                Comparison::Increased => now > before,
+                Comparison::Decreased => now < before,
+                Comparison::Changed => now != before,
+                Comparison::Unchanged => now == before,
+            }
+        });
+        self.previous = memory.to_vec();
+    }
+
+    pub fn candidates(&self) -> &[usize] {
+        &self.candidates
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CheatKind {
+    //Written before every frame
+    Freeze,
+    //Written once, when the cheat is loaded or switched on
+    Poke,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cheat {
+    pub name: String,
+    pub address: usize,
+    pub value: u8,
+    pub kind: CheatKind,
+    pub enabled: bool,
+    applied: bool,
+}
+
+impl Cheat {
+    pub fn new(name: &str, address: usize, kind: CheatKind, value: u8) -> Self {
+        Cheat{name: name.to_string(), address, value, kind, enabled: true, applied: false}
+    }
+}
+
+pub struct Cheats {
+    //Where save writes to
+    path: Option<PathBuf>,
+    cheats: Vec<Cheat>,
+    search: Option<RamSearch>,
+}
+
+impl Cheats {
+    pub fn new() -> Self {
+        Cheats{path: None, cheats: Vec::new(), search: None}
+    }
+
+    //The cheat file for a ROM in the given directory
+    pub fn path_for(directory: &str, rom: &[u8]) -> PathBuf {
+        Path::new(directory).join(format!("{}.{}", Sha1::from(rom).digest(), EXTENSION))
+    }
+
+    //A missing file is an empty list, created on the first save
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let cheats = match fs::read_to_string(path) {
+            Ok(contents) => parse(&contents).map_err(|line| io::Error::new(ErrorKind::InvalidData, format!("Invalid cheat: {}", line)))?,
+            Err(error) if error.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(error) => return Err(error),
+        };
+        Ok(Cheats{path: Some(path.to_path_buf()), cheats, search: None})
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Err(io::Error::new(ErrorKind::NotFound, "No cheat file for this ROM"));
+        };
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let mut file = fs::File::create(path)?;
+        self.write(&mut file)?;
+        file.flush()
+    }
+
+    pub fn write(&self, writer: &mut dyn Write) -> io::Result<()> {
+        writeln!(writer, "# name address kind value [off]")?;
+        for cheat in self.cheats.iter() {
+            let kind = match cheat.kind {
+                CheatKind::Freeze => "freeze",
+                CheatKind::Poke => "poke",
+            };
+            writeln!(writer, "{} {:03X} {} {:02X}{}", cheat.name, cheat.address, kind, cheat.value, if cheat.enabled { "" } else { " off" })?;
+        }
+        Ok(())
+    }
+
+    pub fn cheats(&self) -> &[Cheat] {
+        &self.cheats
+    }
+
+    pub fn len(&self) -> usize {
+        self.cheats.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cheats.is_empty()
+    }
+
+    //Called before each frame
+    pub fn apply(&mut self, cpu: &mut CPU) {
+        for cheat in self.cheats.iter_mut().filter(|cheat| cheat.enabled) {
+            if cheat.kind == CheatKind::Freeze || !cheat.applied {
+                cpu.write_memory(cheat.address, &[cheat.value]);
+                cheat.applied = true;
+            }
+        }
+    }
+
+    //Runs a line typed into the cheat console and returns what to print
+    pub fn command(&mut self, line: &str, cpu: &mut CPU) -> String {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let comparison = match words[..] {
+            ["eq", value] => match u8::from_str_radix(value, 16) {
+                Ok(value) => Some(Comparison::Equal(value)),
+                Err(_) => return format!("Invalid value {}", value),
+            },
+            ["inc"] => Some(Comparison::Increased),
+            ["dec"] => Some(Comparison::Decreased),
+            ["changed"] => Some(Comparison::Changed),
+            ["same"] => Some(Comparison::Unchanged),
+            _ => None,
+        };
+        if let Some(comparison) = comparison {
+            let search = self.search.get_or_insert_with(|| RamSearch::new(cpu.memory()));
+            search.filter(cpu.memory(), comparison);
+            return candidates(search, cpu.memory());
+        }
+
+        match words[..] {
+            [] => String::new(),
+            ["search"] => {
+                self.search = Some(RamSearch::new(cpu.memory()));
+                format!("Searching {} addresses", MEMORY_SIZE)
+            }
+            ["add", name, address, kind, value] => {
+                let address = usize::from_str_radix(address, 16).ok().filter(|&address| address < MEMORY_SIZE);
+                let kind = parse_kind(kind);
+                let value = u8::from_str_radix(value, 16).ok();
+                match (address, kind, value) {
+                    (Some(address), Some(kind), Some(value)) => {
+                        self.cheats.retain(|cheat| cheat.name != name);
+                        self.cheats.push(Cheat::new(name, address, kind, value));
+                        format!("Added {}", name)
+                    }
+                    _ => String::from("Usage: add name address freeze|poke value"),
+                }
+            }
+            ["toggle", name] => match self.cheats.iter_mut().find(|cheat| cheat.name == name) {
+                Some(cheat) => {
+                    cheat.enabled = !cheat.enabled;
+                    cheat.applied = false;
+                    format!("{} {}", name, if cheat.enabled { "on" } else { "off" })
+                }
+                None => format!("No cheat named {}", name),
+            },
+            ["remove", name] => {
+                let count = self.cheats.len();
+                self.cheats.retain(|cheat| cheat.name != name);
+                if self.cheats.len() < count { format!("Removed {}", name) } else { format!("No cheat named {}", name) }
+            }
+            ["list"] => {
+                let mut listing = Vec::new();
+                let _ = self.write(&mut listing);
+                String::from_utf8_lossy(&listing).trim_end().to_string()
+            }
+            ["save"] => match self.save() {
+                Ok(()) => format!("Saved {} cheats", self.cheats.len()),
+                Err(error) => format!("ERROR {:?}", error),
+            },
+            ["help"] => HELP.to_string(),
+            _ => String::from("Unknown command, try help"),
+        }
+    }
+}
+
+impl Default for Cheats {
+    fn default() -> Self {
+        Cheats::new()
+    }
+}
+
+fn candidates(search: &RamSearch, memory: &[u8]) -> String {
+    let found = search.candidates();
+    let listed: Vec<String> = found.iter().take(LISTED_CANDIDATES).map(|&address| format!("{:03X}={:02X}", address, memory[address])).collect();
+    let more = if found.len() > LISTED_CANDIDATES { " ..." } else { "" };
+    format!("{} candidates {}{}", found.len(), listed.join(" "), more).trim_end().to_string()
+}
+
+fn parse_kind(kind: &str) -> Option<CheatKind> {
+    match kind {
+        "freeze" => Some(CheatKind::Freeze),
+        "poke" => Some(CheatKind::Poke),
+        _ => None,
+    }
+}
+
+//Returns the first line that is not a cheat, comment or blank
+fn parse(contents: &str) -> Result<Vec<Cheat>, String> {
+    let mut cheats = Vec::new();
+    for line in contents.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (name, address, kind, value, enabled) = match words[..] {
+            [name, address, kind, value] => (name, address, kind, value, true),
+            [name, address, kind, value, "off"] => (name, address, kind, value, false),
+            _ => return Err(line.to_string()),
+        };
+        let address = usize::from_str_radix(address, 16).ok().filter(|&address| address < MEMORY_SIZE);
+        match (address, parse_kind(kind), u8::from_str_radix(value, 16)) {
+            (Some(address), Some(kind), Ok(value)) => cheats.push(Cheat{enabled, ..Cheat::new(name, address, kind, value)}),
+            _ => return Err(line.to_string()),
+        }
+    }
+    Ok(cheats)
+}
+
+//Reads commands from stdin on a thread of its own, so the emulator can keep running.
+//Clones share the thread, so the launcher starts one console for all the ROMs it runs.
+#[derive(Clone)]
+pub struct CheatConsole {
+    lines: Rc<Receiver<String>>,
+}
+
+impl CheatConsole {
+    pub fn start() -> Self {
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        CheatConsole{lines: Rc::new(lines)}
+    }
+
+    pub fn poll(&self) -> Option<String> {
+        self.lines.try_recv().ok()
+    }
+}
+
+#[cfg(test)]
+#[path = "./cheats_test.rs"]
+mod cheats_test;
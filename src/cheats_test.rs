@@ -0,0 +1,83 @@
+use std::env;
+use std::fs;
+
+use crate::cpu::CPU;
+use crate::MEMORY_SIZE;
+use super::{parse, Cheat, CheatKind, Cheats, Comparison, RamSearch};
+
+#[test]
+fn test_ram_search() {
+    let mut memory = vec![0; MEMORY_SIZE];
+    memory[0x300] = 3;
+    memory[0x301] = 3;
+    let mut search = RamSearch::new(&memory);
+    search.filter(&memory, Comparison::Equal(3));
+    assert_eq!(search.candidates(), &[0x300, 0x301]);
+    //A life is lost
+    memory[0x300] = 2;
+    memory[0x400] = 9;
+    search.filter(&memory, Comparison::Decreased);
+    assert_eq!(search.candidates(), &[0x300]);
+    search.filter(&memory, Comparison::Unchanged);
+    assert_eq!(search.candidates(), &[0x300]);
+    memory[0x300] = 5;
+    search.filter(&memory, Comparison::Increased);
+    assert_eq!(search.candidates(), &[0x300]);
+    search.filter(&memory, Comparison::Changed);
+    assert!(search.candidates().is_empty());
+}
+
+#[test]
+fn test_freeze_and_poke() {
+    let mut cpu = CPU::new();
+    let mut cheats = Cheats::new();
+    cheats.command("add lives 300 freeze 05", &mut cpu);
+    cheats.command("add level 301 poke 07", &mut cpu);
+    cheats.apply(&mut cpu);
+    assert_eq!(&cpu.memory()[0x300..0x302], &[5, 7]);
+    cpu.write_memory(0x300, &[0, 0]);
+    cheats.apply(&mut cpu);
+    //Pokes are only written once
+    assert_eq!(&cpu.memory()[0x300..0x302], &[5, 0]);
+    cheats.command("toggle lives", &mut cpu);
+    cheats.command("toggle level", &mut cpu);
+    cheats.command("toggle level", &mut cpu);
+    cpu.write_memory(0x300, &[1]);
+    cheats.apply(&mut cpu);
+    assert_eq!(&cpu.memory()[0x300..0x302], &[1, 7]);
+}
+
+#[test]
+fn test_console_search() {
+    let mut cpu = CPU::new();
+    let mut cheats = Cheats::new();
+    cpu.write_memory(0x300, &[0xAB]);
+    assert_eq!(cheats.command("eq ab", &mut cpu), "1 candidates 300=AB");
+    cpu.write_memory(0x300, &[0xAA]);
+    assert_eq!(cheats.command("dec", &mut cpu), "1 candidates 300=AA");
+    assert!(cheats.command("search", &mut cpu).starts_with("Searching"));
+    assert!(cheats.command("same", &mut cpu).ends_with("..."));
+    assert_eq!(cheats.command("eq zz", &mut cpu), "Invalid value zz");
+    assert_eq!(cheats.command("add lives 1000 freeze 05", &mut cpu), "Usage: add name address freeze|poke value");
+    assert_eq!(cheats.command("remove lives", &mut cpu), "No cheat named lives");
+    assert_eq!(cheats.command("jump", &mut cpu), "Unknown command, try help");
+}
+
+#[test]
+fn test_cheat_file() {
+    let cheats = parse("# breakout\nlives 1F4 freeze 05\n\nlevel 1F5 poke 02 off\n").unwrap();
+    assert_eq!(cheats, vec![Cheat::new("lives", 0x1F4, CheatKind::Freeze, 5), Cheat{enabled: false, ..Cheat::new("level", 0x1F5, CheatKind::Poke, 2)}]);
+    assert_eq!(parse("lives 1F4 melt 05"), Err(String::from("lives 1F4 melt 05")));
+    assert_eq!(parse("lives 1F4 freeze"), Err(String::from("lives 1F4 freeze")));
+
+    let directory = env::temp_dir().join("chip8_emulator_cheats_test");
+    let path = Cheats::path_for(directory.to_str().unwrap(), b"abc");
+    assert!(path.ends_with("a9993e364706816aba3e25717850c26c9cd0d89d.cht"));
+    let mut cheats = Cheats::load(&path).unwrap();
+    assert_eq!(cheats.len(), 0);
+    cheats.command("add lives 1F4 freeze 05", &mut CPU::new());
+    cheats.save().unwrap();
+    let loaded = Cheats::load(&path).unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+    assert_eq!(loaded.cheats(), cheats.cheats());
+}
@@ -0,0 +1,440 @@
+use std::fs;
+use std::path::Path;
+
+use crate::coverage::CoverageFormat;
+use crate::drivers::{Glyphs, Platform, Scaling};
+use crate::pacing::FastForward;
+use crate::palette::Palette;
+use crate::persistence::AntiFlicker;
+use crate::recording::{self, RecordFormat};
+use crate::timing::Timing;
+use crate::tone::{ToneSettings, Waveform};
+use crate::trace::{TraceFilter, TraceFormat};
+
+//Read by the binary from the working directory when no --config is given
+pub const DEFAULT_CONFIG_FILE: &str = "chip8.cfg";
+//Options that take no value, set in a config file with "option = true"
+const FLAGS: [&str; 6] = ["debugger", "cheat-console", "memory-viewer", "watch", "watch-keep-settings", "launcher"];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Frontend {
+    Sdl,
+    Terminal,
+    //Headless, nothing is shown or played
+    Null,
+}
+
+pub struct Config {
+    pub rom_path: String,
+    //The platform ROMs are loaded for, or None to go by the file extension
+    pub platform: Option<Platform>,
+    pub scale: usize,
+    pub scaling: Scaling,
+    pub anti_flicker: AntiFlicker,
+    //Built in themes followed by the ones from the config file
+    pub palettes: Vec<Palette>,
+    pub palette: usize,
+    pub screenshot_scale: usize,
+    //Record from the start into this .gif or .y4m file
+    pub record_path: Option<String>,
+    pub record_format: RecordFormat,
+    pub record_scale: usize,
+    //Capture the buzzer from the start into this .wav file
+    pub wav_path: Option<String>,
+    pub tone: ToneSettings,
+    pub timing: Timing,
+    pub fast_forward: FastForward,
+    //Slow motion runs at 1/slow_motion speed
+    pub slow_motion: u32,
+    //Run the ROM in the terminal debugger instead of a frontend
+    pub debugger: bool,
+    //Instructions the debuggers can step back through
+    pub history: usize,
+    //Trace every instruction to this file, or stdout for -
+    pub trace_path: Option<String>,
+    pub trace_format: TraceFormat,
+    pub trace_filter: TraceFilter,
+    //Print this binary trace as text instead of running a ROM
+    pub decode_trace: Option<String>,
+    //Hot spot report written on exit, or to stdout for -
+    pub profile_path: Option<String>,
+    //Call stacks in the folded format of flamegraph tools, written on exit
+    pub profile_folded: Option<String>,
+    //Coverage report written on exit, or to stdout for -
+    pub coverage_path: Option<String>,
+    pub coverage_format: CoverageFormat,
+    //Cheat files are looked up here by the SHA-1 of the ROM
+    pub cheats_dir: String,
+    //Read cheat and RAM search commands from stdin while running
+    pub cheat_console: bool,
+    //Serve gdb on this localhost port
+    pub gdb_port: Option<u16>,
+    pub frontend: Frontend,
+    //Open the memory viewer window next to the display, SDL only
+    pub memory_viewer: bool,
+    //Reload the ROM, or reassemble the .8o source, whenever the file is saved
+    pub watch: bool,
+    //Keep breakpoints, watchpoints and the tickrate across reloads
+    pub watch_keep_settings: bool,
+    //Pick the ROM from the library in the SDL window instead of running rom_path
+    pub launcher: bool,
+    //Directories the launcher lists ROMs from
+    pub library_dirs: Vec<String>,
+    //Titles for the launcher by SHA-1, one "sha1 title" per line
+    pub rom_database: String,
+    pub glyphs: Glyphs,
+    //Stop after this many frames, unthrottled, instead of running until closed
+    pub frames: Option<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    UnknownOption(String),
+    MissingValue(String),
+    InvalidValue(String, String),
+    UnreadableFile(String),
+    InvalidLine(String),
+    //Two options that cannot be used together
+    Conflict(String, String),
+}
+
+impl Config {
+    //chip8_emulator [rom] [scale] [--config file]
+    //  [--scaling integer|fit] [--anti-flicker off|phosphor|frame-or|vblank]
+    //  [--palette name] [--theme name=#rrggbb #rrggbb...] [--screenshot-scale n]
+    //  [--record file.gif|file.y4m] [--record-format gif|y4m] [--record-scale 1-1023] [--wav file.wav]
+    //  [--waveform square|sine|triangle|sawtooth|noise] [--tone-frequency hz]
+    //  [--volume 0-1] [--duty-cycle 0-1] [--sample-rate hz]
+    //  [--platform chip8|xo-chip] [--timing instruction|vip] [--fast-forward uncapped|n] [--slow-motion n]
+    //  [--debugger] [--gdb port] [--history n]
+    //  [--trace file|-] [--trace-format text|binary] [--trace-range 200-2ff] [--trace-opcodes d,f]
+    //  [--decode-trace file] [--profile file|-] [--profile-folded file]
+    //  [--coverage file|-] [--coverage-format annotated|lcov] [--cheats-dir dir] [--cheat-console]
+    //  [--frontend sdl|tty|null] [--memory-viewer] [--frames n]
+    //  [--launcher] [--library dir]... [--rom-database file] [--watch] [--watch-keep-settings]
+    //  [--tty-glyphs half-block|braille]
+    //Options from the config file come first, so the command line overrides them.
+    pub fn from_args(args: &[String]) -> Result<Self, ConfigError> {
+        Config::from_args_or_file(args, None)
+    }
+
+    //Like from_args, but reads default_file when no --config is given and it exists
+    pub fn from_args_or_file(args: &[String], default_file: Option<&str>) -> Result<Self, ConfigError> {
+        let config_file = args.iter().position(|arg| arg == "--config").map(|i| next_value("--config", &mut args[i + 1..].iter()));
+        let config_file = match config_file {
+            Some(path) => Some(path?),
+            None => default_file.filter(|path| Path::new(path).exists()),
+        };
+        let mut all_args = match config_file {
+            Some(path) => file_args(&fs::read_to_string(path).map_err(|_| ConfigError::UnreadableFile(path.to_string()))?)?,
+            None => Vec::new(),
+        };
+        all_args.extend(args.iter().cloned());
+        Config::parse(&all_args)
+    }
+
+    fn parse(args: &[String]) -> Result<Self, ConfigError> {
+        let mut palette = None;
+        let mut library_dirs = Vec::new();
+        let mut record_format = None;
+        let mut config = Config {
+            rom_path: String::from("tetris.rom"),
+            platform: None,
+            scale: 5,
+            scaling: Scaling::Integer,
+            anti_flicker: AntiFlicker::Off,
+            palettes: Palette::builtin(),
+            palette: 0,
+            screenshot_scale: 1,
+            record_path: None,
+            record_format: RecordFormat::Gif,
+            record_scale: 4,
+            wav_path: None,
+            tone: ToneSettings::new(),
+            timing: Timing::Instruction,
+            fast_forward: FastForward::Multiplier(4),
+            slow_motion: 4,
+            debugger: false,
+            history: 100_000,
+            trace_path: None,
+            trace_format: TraceFormat::Text,
+            trace_filter: TraceFilter::new(),
+            decode_trace: None,
+            profile_path: None,
+            profile_folded: None,
+            coverage_path: None,
+            coverage_format: CoverageFormat::Annotated,
+            cheats_dir: String::from("cheats"),
+            cheat_console: false,
+            gdb_port: None,
+            frontend: Frontend::Sdl,
+            memory_viewer: false,
+            watch: false,
+            watch_keep_settings: false,
+            launcher: false,
+            library_dirs: vec![String::from(".")],
+            rom_database: String::from("roms.txt"),
+            glyphs: Glyphs::HalfBlock,
+            frames: None,
+        };
+        let mut positional = 0;
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--scaling" => {
+                    config.scaling = match next_value(arg, &mut args)? {
+                        "integer" => Scaling::Integer,
+                        "fit" => Scaling::Fit,
+                        value => return Err(ConfigError::InvalidValue(arg.clone(), value.to_string())),
+                    };
+                }
+                "--anti-flicker" => {
+                    config.anti_flicker = match next_value(arg, &mut args)? {
+                        "off" => AntiFlicker::Off,
+                        "phosphor" => AntiFlicker::Phosphor,
+                        "frame-or" => AntiFlicker::FrameOr,
+                        "vblank" => AntiFlicker::Vblank,
+                        value => return Err(ConfigError::InvalidValue(arg.clone(), value.to_string())),
+                    };
+                }
+                "--palette" => palette = Some(next_value(arg, &mut args)?),
+                "--theme" => {
+                    let value = next_value(arg, &mut args)?;
+                    let theme = Palette::parse(value).ok_or_else(|| ConfigError::InvalidValue(arg.clone(), value.to_string()))?;
+                    config.palettes.retain(|palette| palette.name != theme.name);
+                    config.palettes.push(theme);
+                }
+                "--screenshot-scale" => {
+                    let value = next_value(arg, &mut args)?;
+                    config.screenshot_scale = scale(arg, value)?;
+                }
+                "--record" => {
+                    let value = next_value(arg, &mut args)?;
+                    if RecordFormat::from_path(Path::new(value)).is_none() {
+                        return Err(ConfigError::InvalidValue(arg.clone(), value.to_string()));
+                    }
+                    config.record_path = Some(value.to_string());
+                }
+                "--record-format" => {
+                    record_format = Some(match next_value(arg, &mut args)? {
+                        "gif" => RecordFormat::Gif,
+                        "y4m" => RecordFormat::Y4m,
+                        value => return Err(ConfigError::InvalidValue(arg.clone(), value.to_string())),
+                    });
+                }
+                "--record-scale" => {
+                    let value = next_value(arg, &mut args)?;
+                    config.record_scale = value.parse().ok().filter(|scale| (1..=recording::MAX_SCALE).contains(scale))
+                        .ok_or_else(|| ConfigError::InvalidValue(arg.clone(), value.to_string()))?;
+                }
+                "--wav" => config.wav_path = Some(next_value(arg, &mut args)?.to_string()),
+                "--waveform" => {
+                    config.tone.waveform = match next_value(arg, &mut args)? {
+                        "square" => Waveform::Square,
+                        "sine" => Waveform::Sine,
+                        "triangle" => Waveform::Triangle,
+                        "sawtooth" => Waveform::Sawtooth,
+                        "noise" => Waveform::Noise,
+                        value => return Err(ConfigError::InvalidValue(arg.clone(), value.to_string())),
+                    };
+                }
+                "--tone-frequency" => {
+                    let value = next_value(arg, &mut args)?;
+                    config.tone.frequency = value.parse().ok().filter(|&frequency: &f32| frequency > 0.0)
+                        .ok_or_else(|| ConfigError::InvalidValue(arg.clone(), value.to_string()))?;
+                }
+                "--volume" => config.tone.volume = fraction(arg, next_value(arg, &mut args)?)?,
+                "--duty-cycle" => config.tone.duty_cycle = fraction(arg, next_value(arg, &mut args)?)?,
+                "--sample-rate" => {
+                    let value = next_value(arg, &mut args)?;
+                    config.tone.sample_rate = value.parse().ok().filter(|&rate| rate > 0)
+                        .ok_or_else(|| ConfigError::InvalidValue(arg.clone(), value.to_string()))?;
+                }
+                "--config" => {
+                    next_value(arg, &mut args)?;
+                }
+                "--platform" => {
+                    config.platform = match next_value(arg, &mut args)? {
+                        "chip8" => Some(Platform::Chip8),
+                        "xo-chip" => Some(Platform::XoChip),
+                        value => return Err(ConfigError::InvalidValue(arg.clone(), value.to_string())),
+                    };
+                }
+                "--timing" => {
+                    config.timing = match next_value(arg, &mut args)? {
+                        "instruction" => Timing::Instruction,
+                        "vip" => Timing::CosmacVip,
+                        value => return Err(ConfigError::InvalidValue(arg.clone(), value.to_string())),
+                    };
+                }
+                "--fast-forward" => {
+                    config.fast_forward = match next_value(arg, &mut args)? {
+                        "uncapped" => FastForward::Uncapped,
+                        value => FastForward::Multiplier(value.parse().ok().filter(|&multiplier| multiplier > 1)
+                            .ok_or_else(|| ConfigError::InvalidValue(arg.clone(), value.to_string()))?),
+                    };
+                }
+                "--slow-motion" => {
+                    let value = next_value(arg, &mut args)?;
+                    config.slow_motion = value.parse().ok().filter(|&divisor| divisor > 1)
+                        .ok_or_else(|| ConfigError::InvalidValue(arg.clone(), value.to_string()))?;
+                }
+                "--debugger" => config.debugger = true,
+                "--trace" => config.trace_path = Some(next_value(arg, &mut args)?.to_string()),
+                "--trace-format" => {
+                    config.trace_format = match next_value(arg, &mut args)? {
+                        "text" => TraceFormat::Text,
+                        "binary" => TraceFormat::Binary,
+                        value => return Err(ConfigError::InvalidValue(arg.clone(), value.to_string())),
+                    };
+                }
+                "--trace-range" => {
+                    let value = next_value(arg, &mut args)?;
+                    let range = value.split_once('-').and_then(|(first, last)| {
+                        Some((usize::from_str_radix(first, 16).ok()?, usize::from_str_radix(last, 16).ok()?))
+                    }).filter(|(first, last)| first <= last);
+                    config.trace_filter.addresses = Some(range.ok_or_else(|| ConfigError::InvalidValue(arg.clone(), value.to_string()))?);
+                }
+                "--trace-opcodes" => {
+                    let value = next_value(arg, &mut args)?;
+                    config.trace_filter.classes = value.split(',').map(|class| u8::from_str_radix(class, 16).ok().filter(|&class| class < 16))
+                        .collect::<Option<Vec<u8>>>().ok_or_else(|| ConfigError::InvalidValue(arg.clone(), value.to_string()))?;
+                }
+                "--decode-trace" => config.decode_trace = Some(next_value(arg, &mut args)?.to_string()),
+                "--profile" => config.profile_path = Some(next_value(arg, &mut args)?.to_string()),
+                "--profile-folded" => config.profile_folded = Some(next_value(arg, &mut args)?.to_string()),
+                "--coverage" => config.coverage_path = Some(next_value(arg, &mut args)?.to_string()),
+                "--coverage-format" => {
+                    config.coverage_format = match next_value(arg, &mut args)? {
+                        "annotated" => CoverageFormat::Annotated,
+                        "lcov" => CoverageFormat::Lcov,
+                        value => return Err(ConfigError::InvalidValue(arg.clone(), value.to_string())),
+                    };
+                }
+                "--cheats-dir" => config.cheats_dir = next_value(arg, &mut args)?.to_string(),
+                "--cheat-console" => config.cheat_console = true,
+                "--history" => {
+                    let value = next_value(arg, &mut args)?;
+                    config.history = value.parse().map_err(|_| ConfigError::InvalidValue(arg.clone(), value.to_string()))?;
+                }
+                "--gdb" => {
+                    let value = next_value(arg, &mut args)?;
+                    config.gdb_port = Some(value.parse().map_err(|_| ConfigError::InvalidValue(arg.clone(), value.to_string()))?);
+                }
+                "--frontend" => {
+                    config.frontend = match next_value(arg, &mut args)? {
+                        "sdl" => Frontend::Sdl,
+                        "tty" => Frontend::Terminal,
+                        "null" => Frontend::Null,
+                        value => return Err(ConfigError::InvalidValue(arg.clone(), value.to_string())),
+                    };
+                }
+                "--memory-viewer" => config.memory_viewer = true,
+                "--watch" => config.watch = true,
+                "--watch-keep-settings" => config.watch_keep_settings = true,
+                "--launcher" => config.launcher = true,
+                "--library" => library_dirs.push(next_value(arg, &mut args)?.to_string()),
+                "--rom-database" => config.rom_database = next_value(arg, &mut args)?.to_string(),
+                "--tty-glyphs" => {
+                    config.glyphs = match next_value(arg, &mut args)? {
+                        "half-block" => Glyphs::HalfBlock,
+                        "braille" => Glyphs::Braille,
+                        value => return Err(ConfigError::InvalidValue(arg.clone(), value.to_string())),
+                    };
+                }
+                "--frames" => {
+                    let value = next_value(arg, &mut args)?;
+                    config.frames = Some(value.parse().map_err(|_| ConfigError::InvalidValue(arg.clone(), value.to_string()))?);
+                }
+                option if option.starts_with("--") => return Err(ConfigError::UnknownOption(arg.clone())),
+                _ => {
+                    match positional {
+                        0 => config.rom_path = arg.clone(),
+                        1 => config.scale = scale("scale", arg)?,
+                        _ => return Err(ConfigError::UnknownOption(arg.clone())),
+                    }
+                    positional += 1;
+                }
+            }
+        }
+        if !library_dirs.is_empty() {
+            config.library_dirs = library_dirs;
+        }
+        //Recordings from the hotkey use the same format as --record
+        let record_path_format = config.record_path.as_deref().and_then(|path| RecordFormat::from_path(Path::new(path)));
+        match (record_path_format, record_format) {
+            (Some(path_format), Some(format)) if path_format != format => {
+                return Err(ConfigError::Conflict(String::from("--record-format"), String::from("--record")));
+            }
+            (Some(format), _) | (None, Some(format)) => config.record_format = format,
+            (None, None) => {}
+        }
+        if let Some(name) = palette {
+            config.palette = config.palettes.iter().position(|palette| palette.name == name)
+                .ok_or_else(|| ConfigError::InvalidValue(String::from("--palette"), name.to_string()))?;
+        }
+        //The terminal frontend reads keys from stdin too, and would lose them to the console
+        if config.cheat_console && config.frontend == Frontend::Terminal {
+            return Err(ConfigError::Conflict(String::from("--cheat-console"), String::from("--frontend tty")));
+        }
+        //Each ROM run from the launcher would write over the last one's file
+        if config.launcher {
+            let outputs = [
+                ("--record", config.record_path.is_some()),
+                ("--wav", config.wav_path.is_some()),
+                ("--trace", config.trace_path.is_some()),
+                ("--profile", config.profile_path.is_some()),
+                ("--profile-folded", config.profile_folded.is_some()),
+                ("--coverage", config.coverage_path.is_some()),
+            ];
+            if let Some((option, _)) = outputs.iter().find(|(_, set)| *set) {
+                return Err(ConfigError::Conflict(String::from("--launcher"), option.to_string()));
+            }
+        }
+        Ok(config)
+    }
+}
+
+//A config file holds "option = value" lines standing for "--option value", flags are "option = true" or "false",
+//themes are written "theme.name = #rrggbb #rrggbb..." and # starts a comment line
+fn file_args(contents: &str) -> Result<Vec<String>, ConfigError> {
+    let mut args = Vec::new();
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (option, value) = line.split_once('=').ok_or_else(|| ConfigError::InvalidLine(line.to_string()))?;
+        let (option, value) = (option.trim(), value.trim());
+        match option.strip_prefix("theme.") {
+            Some(name) => args.extend([String::from("--theme"), format!("{}={}", name, value)]),
+            None if FLAGS.contains(&option) => match value {
+                "true" => args.push(format!("--{}", option)),
+                "false" => {}
+                _ => return Err(ConfigError::InvalidValue(format!("--{}", option), value.to_string())),
+            },
+            None => args.extend([format!("--{}", option), value.to_string()]),
+        }
+    }
+    Ok(args)
+}
+
+//A whole multiple of the display size, at least 1
+fn scale(option: &str, value: &str) -> Result<usize, ConfigError> {
+    value.parse().ok().filter(|&scale| scale > 0)
+        .ok_or_else(|| ConfigError::InvalidValue(option.to_string(), value.to_string()))
+}
+
+//A value from 0 to 1
+fn fraction(option: &str, value: &str) -> Result<f32, ConfigError> {
+    value.parse().ok().filter(|fraction| (0.0..=1.0).contains(fraction))
+        .ok_or_else(|| ConfigError::InvalidValue(option.to_string(), value.to_string()))
+}
+
+fn next_value<'a>(option: &str, args: &mut impl Iterator<Item = &'a String>) -> Result<&'a str, ConfigError> {
+    args.next().map(|value| value.as_str()).ok_or_else(|| ConfigError::MissingValue(option.to_string()))
+}
+
+#[cfg(test)]
+#[path = "./config_test.rs"]
+mod config_test;
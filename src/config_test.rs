@@ -0,0 +1,220 @@
+use std::env;
+use std::fs;
+
+use crate::coverage::CoverageFormat;
+use crate::drivers::{Glyphs, Platform, Scaling};
+use crate::pacing::FastForward;
+use crate::palette::Palette;
+use crate::persistence::AntiFlicker;
+use crate::recording::RecordFormat;
+use crate::timing::Timing;
+use crate::tone::{ToneSettings, Waveform};
+use crate::trace::TraceFormat;
+use super::{file_args, Config, ConfigError, Frontend};
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn test_defaults() {
+    let config = Config::from_args(&[]).unwrap();
+    assert_eq!(config.rom_path, "tetris.rom");
+    assert_eq!(config.scale, 5);
+    assert_eq!(config.timing, Timing::Instruction);
+    assert_eq!(config.frontend, Frontend::Sdl);
+}
+
+#[test]
+fn test_positional_and_options() {
+    let config = Config::from_args(&args(&["breakout.rom", "10", "--timing", "vip", "--scaling", "fit"])).unwrap();
+    assert_eq!(config.rom_path, "breakout.rom");
+    assert_eq!(config.scale, 10);
+    assert_eq!(config.scaling, Scaling::Fit);
+    assert_eq!(config.timing, Timing::CosmacVip);
+    assert_eq!(Config::from_args(&args(&["breakout.rom", "0"])).err(),
+        Some(ConfigError::InvalidValue(String::from("scale"), String::from("0"))));
+    assert_eq!(Config::from_args(&args(&["--screenshot-scale", "0"])).err(),
+        Some(ConfigError::InvalidValue(String::from("--screenshot-scale"), String::from("0"))));
+}
+
+#[test]
+fn test_platform() {
+    assert_eq!(Config::from_args(&[]).unwrap().platform, None);
+    assert_eq!(Config::from_args(&args(&["--platform", "xo-chip"])).unwrap().platform, Some(Platform::XoChip));
+    assert_eq!(Config::from_args(&args(&["--platform", "chip8"])).unwrap().platform, Some(Platform::Chip8));
+    assert!(Config::from_args(&args(&["--platform", "schip"])).is_err());
+}
+
+#[test]
+fn test_anti_flicker() {
+    let config = Config::from_args(&args(&["--anti-flicker", "phosphor"])).unwrap();
+    assert_eq!(config.anti_flicker, AntiFlicker::Phosphor);
+    assert_eq!(Config::from_args(&[]).unwrap().anti_flicker, AntiFlicker::Off);
+}
+
+#[test]
+fn test_errors() {
+    assert_eq!(Config::from_args(&args(&["--timing"])).err(), Some(ConfigError::MissingValue(String::from("--timing"))));
+    assert_eq!(Config::from_args(&args(&["--timing", "fast"])).err(), Some(ConfigError::InvalidValue(String::from("--timing"), String::from("fast"))));
+    assert_eq!(Config::from_args(&args(&["--turbo"])).err(), Some(ConfigError::UnknownOption(String::from("--turbo"))));
+}
+
+#[test]
+fn test_terminal_frontend() {
+    let config = Config::from_args(&args(&["--frontend", "tty", "--tty-glyphs", "braille"])).unwrap();
+    assert_eq!(config.frontend, Frontend::Terminal);
+    assert_eq!(config.glyphs, Glyphs::Braille);
+    assert!(Config::from_args(&args(&["--frontend", "x11"])).is_err());
+}
+
+#[test]
+fn test_memory_viewer() {
+    assert!(Config::from_args(&args(&["--memory-viewer"])).unwrap().memory_viewer);
+    assert!(!Config::from_args(&[]).unwrap().memory_viewer);
+}
+
+#[test]
+fn test_watch_options() {
+    let config = Config::from_args(&args(&["game.8o", "--watch", "--watch-keep-settings"])).unwrap();
+    assert!(config.watch && config.watch_keep_settings);
+    assert!(!Config::from_args(&[]).unwrap().watch);
+}
+
+#[test]
+fn test_launcher_options() {
+    let config = Config::from_args(&args(&["--launcher", "--library", "roms", "--library", "games", "--rom-database", "titles.txt"])).unwrap();
+    assert!(config.launcher);
+    assert_eq!(config.library_dirs, vec!["roms", "games"]);
+    assert_eq!(config.rom_database, "titles.txt");
+    assert_eq!(Config::from_args(&[]).unwrap().library_dirs, vec!["."]);
+    assert_eq!(Config::from_args(&args(&["--launcher", "--record", "game.gif"])).err(),
+        Some(ConfigError::Conflict(String::from("--launcher"), String::from("--record"))));
+    assert_eq!(Config::from_args(&args(&["--coverage", "-", "--launcher"])).err(),
+        Some(ConfigError::Conflict(String::from("--launcher"), String::from("--coverage"))));
+}
+
+#[test]
+fn test_palettes_and_themes() {
+    let config = Config::from_args(&args(&["--palette", "amber"])).unwrap();
+    assert_eq!(config.palettes[config.palette].name, "amber");
+    let config = Config::from_args(&args(&["--theme", "mine=#000000 #FFFFFF", "--palette", "mine"])).unwrap();
+    assert_eq!(config.palettes[config.palette].name, "mine");
+    assert_eq!(config.palettes.len(), Palette::builtin().len() + 1);
+    assert!(Config::from_args(&args(&["--palette", "missing"])).is_err());
+    assert!(Config::from_args(&args(&["--theme", "=#000000 #FFFFFF"])).is_err());
+}
+
+#[test]
+fn test_config_file_lines() {
+    let contents = "# my settings\nanti-flicker = frame-or\n\ntheme.mine = #000000 #FFFFFF\n";
+    assert_eq!(file_args(contents).unwrap(), args(&["--anti-flicker", "frame-or", "--theme", "mine=#000000 #FFFFFF"]));
+    assert_eq!(file_args("timing vip").err(), Some(ConfigError::InvalidLine(String::from("timing vip"))));
+    assert_eq!(file_args("watch = true\ndebugger = false\nrom-database = db.txt").unwrap(), args(&["--watch", "--rom-database", "db.txt"]));
+    assert_eq!(file_args("watch = yes").err(), Some(ConfigError::InvalidValue(String::from("--watch"), String::from("yes"))));
+    assert_eq!(Config::from_args(&args(&["--config", "missing.cfg"])).err(), Some(ConfigError::UnreadableFile(String::from("missing.cfg"))));
+}
+
+#[test]
+fn test_default_config_file() {
+    let path = env::temp_dir().join("chip8_config_test.cfg");
+    fs::write(&path, "anti-flicker = frame-or\nwatch = true\nlauncher = false\n").unwrap();
+    let path = path.to_str().unwrap();
+    let config = Config::from_args_or_file(&args(&["game.ch8"]), Some(path)).unwrap();
+    assert_eq!(config.anti_flicker, AntiFlicker::FrameOr);
+    assert!(config.watch && !config.launcher);
+    assert_eq!(config.rom_path, "game.ch8");
+    assert_eq!(Config::from_args_or_file(&args(&["--config", "missing.cfg"]), Some(path)).err(),
+        Some(ConfigError::UnreadableFile(String::from("missing.cfg"))));
+    assert_eq!(Config::from_args_or_file(&args(&[]), Some("missing.cfg")).unwrap().anti_flicker, Config::from_args(&args(&[])).unwrap().anti_flicker);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_recording_options() {
+    let config = Config::from_args(&args(&["--record", "clip.y4m", "--record-format", "y4m", "--record-scale", "2"])).unwrap();
+    assert_eq!(config.record_path, Some(String::from("clip.y4m")));
+    assert_eq!(config.record_format, RecordFormat::Y4m);
+    assert_eq!(config.record_scale, 2);
+    assert_eq!(Config::from_args(&args(&["--wav", "beep.wav"])).unwrap().wav_path, Some(String::from("beep.wav")));
+    assert!(Config::from_args(&args(&["--record", "clip.mp4"])).is_err());
+    assert_eq!(Config::from_args(&args(&["--record", "clip.y4m"])).unwrap().record_format, RecordFormat::Y4m);
+    assert_eq!(Config::from_args(&args(&["--record", "clip.gif", "--record-format", "y4m"])).err(),
+        Some(ConfigError::Conflict(String::from("--record-format"), String::from("--record"))));
+    assert_eq!(Config::from_args(&args(&["--record-scale", "1023"])).unwrap().record_scale, 1023);
+    assert!(Config::from_args(&args(&["--record-scale", "1024"])).is_err());
+    assert!(Config::from_args(&args(&["--record-scale", "0"])).is_err());
+}
+
+#[test]
+fn test_tone_options() {
+    let config = Config::from_args(&args(&["--waveform", "triangle", "--tone-frequency", "440", "--volume", "0.5", "--duty-cycle", "0.125", "--sample-rate", "48000"])).unwrap();
+    assert_eq!(config.tone, ToneSettings{waveform: Waveform::Triangle, frequency: 440.0, volume: 0.5, duty_cycle: 0.125, sample_rate: 48000});
+    assert!(Config::from_args(&args(&["--volume", "2"])).is_err());
+    assert!(Config::from_args(&args(&["--sample-rate", "0"])).is_err());
+    assert!(Config::from_args(&args(&["--waveform", "pulse"])).is_err());
+}
+
+#[test]
+fn test_speed_options() {
+    let config = Config::from_args(&args(&["--fast-forward", "uncapped", "--slow-motion", "2"])).unwrap();
+    assert_eq!(config.fast_forward, FastForward::Uncapped);
+    assert_eq!(config.slow_motion, 2);
+    assert_eq!(Config::from_args(&args(&["--fast-forward", "8"])).unwrap().fast_forward, FastForward::Multiplier(8));
+    assert!(Config::from_args(&args(&["--fast-forward", "1"])).is_err());
+    assert!(Config::from_args(&args(&["--slow-motion", "half"])).is_err());
+}
+
+#[test]
+fn test_debugging_options() {
+    assert_eq!(Config::from_args(&args(&["--gdb", "1234"])).unwrap().gdb_port, Some(1234));
+    assert!(Config::from_args(&args(&["--debugger"])).unwrap().debugger);
+    assert_eq!(Config::from_args(&args(&["--history", "500"])).unwrap().history, 500);
+    assert!(Config::from_args(&args(&["--gdb", "70000"])).is_err());
+}
+
+#[test]
+fn test_trace_options() {
+    let config = Config::from_args(&args(&["--trace", "-", "--trace-format", "binary", "--trace-range", "200-2FF", "--trace-opcodes", "d,f"])).unwrap();
+    assert_eq!(config.trace_path, Some(String::from("-")));
+    assert_eq!(config.trace_format, TraceFormat::Binary);
+    assert_eq!(config.trace_filter.addresses, Some((0x200, 0x2FF)));
+    assert_eq!(config.trace_filter.classes, vec![0xD, 0xF]);
+    assert!(Config::from_args(&args(&["--trace-range", "200"])).is_err());
+    assert_eq!(Config::from_args(&args(&["--trace-range", "2FF-200"])).err(),
+        Some(ConfigError::InvalidValue(String::from("--trace-range"), String::from("2FF-200"))));
+    assert!(Config::from_args(&args(&["--trace-opcodes", "10"])).is_err());
+}
+
+#[test]
+fn test_profile_options() {
+    let config = Config::from_args(&args(&["--profile", "-", "--profile-folded", "tetris.folded"])).unwrap();
+    assert_eq!(config.profile_path, Some(String::from("-")));
+    assert_eq!(config.profile_folded, Some(String::from("tetris.folded")));
+    assert!(Config::from_args(&args(&["--profile"])).is_err());
+}
+
+#[test]
+fn test_coverage_options() {
+    let config = Config::from_args(&args(&[])).unwrap();
+    assert_eq!(config.coverage_format, CoverageFormat::Annotated);
+    let config = Config::from_args(&args(&["--coverage", "tetris.info", "--coverage-format", "lcov"])).unwrap();
+    assert_eq!(config.coverage_path, Some(String::from("tetris.info")));
+    assert_eq!(config.coverage_format, CoverageFormat::Lcov);
+    assert!(Config::from_args(&args(&["--coverage-format", "html"])).is_err());
+}
+
+#[test]
+fn test_cheat_options() {
+    let config = Config::from_args(&args(&["--cheats-dir", "mine", "--cheat-console"])).unwrap();
+    assert_eq!(config.cheats_dir, "mine");
+    assert!(config.cheat_console);
+    assert_eq!(Config::from_args(&[]).unwrap().cheats_dir, "cheats");
+}
+
+#[test]
+fn test_cheat_console_needs_stdin() {
+    assert!(Config::from_args(&args(&["--cheat-console"])).unwrap().cheat_console);
+    assert_eq!(Config::from_args(&args(&["--cheat-console", "--frontend", "tty"])).err(),
+        Some(ConfigError::Conflict(String::from("--cheat-console"), String::from("--frontend tty"))));
+}
@@ -0,0 +1,151 @@
+//Records which bytes of memory were executed, read as data by FX65 and DXYN, or written by FX33 and FX55.
+//Bytes that are both written and executed are reported as self-modifying code.
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use crate::disassembler::disassemble;
+use crate::MEMORY_SIZE;
+
+const ROM_START: usize = 0x200;
+
+const EXECUTED: u8 = 1;
+const READ: u8 = 2;
+const WRITTEN: u8 = 4;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CoverageFormat {
+    //The ROM disassembled, each line marked with how it was used
+    Annotated,
+    //lcov tracefile with memory addresses as line numbers, for genhtml and coverage services
+    Lcov,
+}
+
+pub struct Coverage {
+    //The ROM as loaded, so listings show the program rather than what it changed itself into
+    rom: Vec<u8>,
+    flags: Vec<u8>,
+    //Times an instruction started at each address
+    executions: Vec<u64>,
+    self_modified: BTreeSet<usize>,
+}
+
+impl Coverage {
+    pub fn new(rom: &[u8]) -> Self {
+        Coverage {
+            rom: rom[..rom.len().min(MEMORY_SIZE - ROM_START)].to_vec(),
+            flags: vec![0; MEMORY_SIZE],
+            executions: vec![0; MEMORY_SIZE],
+            self_modified: BTreeSet::new(),
+        }
+    }
+
+    pub fn executed(&mut self, pc: usize) {
+        self.executions[pc] += 1;
+        for address in [pc, (pc + 1) % MEMORY_SIZE] {
+            self.mark(address, EXECUTED);
+        }
+    }
+
+    pub fn read(&mut self, address: usize) {
+        self.mark(address, READ);
+    }
+
+    pub fn written(&mut self, address: usize) {
+        self.mark(address, WRITTEN);
+    }
+
+    fn mark(&mut self, address: usize, flag: u8) {
+        self.flags[address] |= flag;
+        if self.flags[address] & (EXECUTED | WRITTEN) == EXECUTED | WRITTEN {
+            self.self_modified.insert(address);
+        }
+    }
+
+    fn count(&self, flag: u8) -> usize {
+        (ROM_START..ROM_START + self.rom.len()).filter(|&address| self.flags[address] & flag != 0).count()
+    }
+
+    //Instructions are listed where they were executed, everything else byte by byte.
+    //Memory outside the ROM only shows up in the summary.
+    pub fn write_annotated(&self, writer: &mut dyn Write) -> io::Result<()> {
+        let size = self.rom.len().max(1);
+        let percent = |count: usize| count as f64 * 100.0 / size as f64;
+        let (executed, read, written) = (self.count(EXECUTED), self.count(READ), self.count(WRITTEN));
+        writeln!(writer, "Executed {} of {} ROM bytes ({:.1}%)", executed, self.rom.len(), percent(executed))?;
+        writeln!(writer, "Read as data {} ({:.1}%), written {} ({:.1}%)", read, percent(read), written, percent(written))?;
+        let outside = (0..MEMORY_SIZE).filter(|&address| !(ROM_START..ROM_START + self.rom.len()).contains(&address))
+            .filter(|&address| self.flags[address] & EXECUTED != 0).count();
+        if outside > 0 {
+            writeln!(writer, "Executed {} bytes outside the ROM", outside)?;
+        }
+        if !self.self_modified.is_empty() {
+            let addresses: Vec<String> = self.self_modified.iter().map(|address| format!("{:03X}", address)).collect();
+            writeln!(writer, "Self-modifying code at {}", addresses.join(" "))?;
+        }
+        writeln!(writer)?;
+        writeln!(writer, "X executed, R read as data, W written, ! written and executed")?;
+
+        let mut offset = 0;
+        while offset < self.rom.len() {
+            let address = ROM_START + offset;
+            let flags = self.flags[address] | if offset + 1 < self.rom.len() { self.flags[address + 1] } else { 0 };
+            let modified = if self.self_modified.contains(&address) { '!' } else { ' ' };
+            if self.executions[address] > 0 && offset + 1 < self.rom.len() {
+                let opcode = u16::from_be_bytes([self.rom[offset], self.rom[offset + 1]]);
+                writeln!(writer, "{}{} {:03X}  {:04X}  {:<16} {:>10}", markers(flags), modified, address, opcode, disassemble(opcode), self.executions[address])?;
+                offset += 2;
+            } else {
+                let flags = self.flags[address];
+                writeln!(writer, "{}{} {:03X}  {:02X}    DB 0x{:02X}", markers(flags), modified, address, self.rom[offset], self.rom[offset])?;
+                offset += 1;
+            }
+        }
+        Ok(())
+    }
+
+    //Every executed address is a line, and so is each even ROM address that was never used,
+    //since it could have been an instruction. Bytes only read as data are left out.
+    pub fn write_lcov(&self, writer: &mut dyn Write, rom_path: &str) -> io::Result<()> {
+        writeln!(writer, "TN:")?;
+        writeln!(writer, "SF:{}", rom_path)?;
+        let mut lines = 0;
+        let mut hit = 0;
+        for address in 0..MEMORY_SIZE {
+            let in_rom = (ROM_START..ROM_START + self.rom.len()).contains(&address);
+            let unused = in_rom && (address - ROM_START).is_multiple_of(2) && self.flags[address] == 0
+                && self.flags.get(address + 1).is_none_or(|&flags| flags & EXECUTED == 0);
+            if self.executions[address] > 0 || unused {
+                writeln!(writer, "DA:{},{}", address, self.executions[address])?;
+                lines += 1;
+                hit += (self.executions[address] > 0) as usize;
+            }
+        }
+        writeln!(writer, "LF:{}", lines)?;
+        writeln!(writer, "LH:{}", hit)?;
+        writeln!(writer, "end_of_record")
+    }
+
+    //"-" writes to stdout
+    pub fn save(&self, path: &str, format: CoverageFormat, rom_path: &str) -> io::Result<()> {
+        let mut writer: BufWriter<Box<dyn Write>> = BufWriter::new(match path {
+            "-" => Box::new(io::stdout()),
+            path => Box::new(File::create(path)?),
+        });
+        match format {
+            CoverageFormat::Annotated => self.write_annotated(&mut writer)?,
+            CoverageFormat::Lcov => self.write_lcov(&mut writer, rom_path)?,
+        }
+        writer.flush()
+    }
+}
+
+fn markers(flags: u8) -> String {
+    [(EXECUTED, 'X'), (READ, 'R'), (WRITTEN, 'W')].iter()
+        .map(|&(flag, marker)| if flags & flag != 0 { marker } else { '-' })
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "./coverage_test.rs"]
+mod coverage_test;
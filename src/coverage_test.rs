@@ -0,0 +1,54 @@
+use crate::cpu::CPU;
+use super::Coverage;
+
+//Draws a sprite from 20E, stores V0 over the jump at 210 and then spins on it
+const ROM: [u8; 18] = [
+    0x60, 0x12, 0xA2, 0x0E, 0xD0, 0x01, 0xA2, 0x10, 0xF0, 0x55, 0x12, 0x10,
+    0x00, 0x00, 0x80, 0x00, 0x12, 0x10,
+];
+
+fn run() -> Coverage {
+    let mut cpu = CPU::new();
+    cpu.load(&ROM);
+    cpu.set_coverage(Some(Coverage::new(&ROM)));
+    for _ in 0..8 {
+        cpu.step_instruction(&[false; 16]);
+    }
+    cpu.take_coverage().unwrap()
+}
+
+#[test]
+fn test_annotated() {
+    let mut report = Vec::new();
+    run().write_annotated(&mut report).unwrap();
+    let report = String::from_utf8(report).unwrap();
+    let lines: Vec<&str> = report.lines().map(str::trim_end).collect();
+    assert_eq!(lines[..4], [
+        "Executed 14 of 18 ROM bytes (77.8%)",
+        "Read as data 1 (5.6%), written 1 (5.6%)",
+        "Self-modifying code at 210",
+        "",
+    ]);
+    assert_eq!(lines[5..], [
+        "X--  200  6012  LD V0, 0x12               1",
+        "X--  202  A20E  LD I, 0x20E               1",
+        "X--  204  D001  DRW V0, V0, 1             1",
+        "X--  206  A210  LD I, 0x210               1",
+        "X--  208  F055  LD [I], V0                1",
+        "X--  20A  1210  JP 0x210                  1",
+        "---  20C  00    DB 0x00",
+        "---  20D  00    DB 0x00",
+        "-R-  20E  80    DB 0x80",
+        "---  20F  00    DB 0x00",
+        "X-W! 210  1210  JP 0x210                  2",
+    ]);
+}
+
+#[test]
+fn test_lcov() {
+    let mut report = Vec::new();
+    run().write_lcov(&mut report, "test.ch8").unwrap();
+    assert_eq!(String::from_utf8(report).unwrap(), "TN:\nSF:test.ch8\n\
+        DA:512,1\nDA:514,1\nDA:516,1\nDA:518,1\nDA:520,1\nDA:522,1\nDA:524,0\nDA:528,2\n\
+        LF:8\nLH:7\nend_of_record\n");
+}
@@ -1,5 +1,10 @@
 use rand::prelude::*;
-use crate::{DISPLAY_WIDTH, DISPLAY_HEIGHT, MEMORY_SIZE, VRAM};
+use crate::{DISPLAY_WIDTH, DISPLAY_HEIGHT, MEMORY_SIZE, VRAM, DirtyRows, ALL_ROWS};
+use crate::coverage::Coverage;
+use crate::history::{History, UndoEntry};
+use crate::profiler::Profiler;
+use crate::timing;
+use crate::trace::Tracer;
 
 const REGISTER_AMOUNT: usize = 16;
 const STACK_SIZE: usize = 16;
@@ -31,15 +36,36 @@ impl IndexRegister {
         let value = self.value + rhs;
         (IndexRegister{value: value & 0xFFF}, value > 0xFFF)
     }
+
+    //Addresses past I wrap around to the start of memory
+    pub fn offset(self, offset: usize) -> usize {
+        (self.value + offset) & 0xFFF
+    }
 }
 
-pub struct OutputState<'a> {
-    pub vram: &'a VRAM,
+pub struct OutputState {
     pub vram_changed: bool,
+    //Bit n is set when row n of vram changed
+    pub dirty_rows: DirtyRows,
     pub beep: bool,
+    //Instructions executed by the call
+    pub instructions: u32,
+    //Execution stopped before the instruction at a breakpoint, or after one wrote to a watchpoint
+    pub breakpoint: bool,
 }
 
-pub(crate) struct CPU {
+//Register file as seen by debuggers
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Registers {
+    pub v: [u8; REGISTER_AMOUNT],
+    pub i: u16,
+    pub pc: u16,
+    pub sp: u8,
+    pub dt: u8,
+    pub st: u8,
+}
+
+pub struct CPU {
     pc: PC,
     sp: SP,
     dt: Register,
@@ -50,8 +76,17 @@ pub(crate) struct CPU {
     memory: [u8; MEMORY_SIZE],
     vram: VRAM,
     vram_changed: bool,
+    dirty_rows: DirtyRows,
     waiting_for_key_press: Option<usize>,
     keypad: [bool; 16],
+    cycle_overrun: u32,
+    breakpoints: Vec<PC>,
+    watchpoints: Vec<usize>,
+    watch_hit: bool,
+    history: Option<History>,
+    tracer: Option<Tracer>,
+    profiler: Option<Profiler>,
+    coverage: Option<Coverage>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -81,8 +116,17 @@ impl CPU {
             memory: [0; MEMORY_SIZE],
             vram: [[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
             vram_changed: false,
+            dirty_rows: 0,
             waiting_for_key_press: None,
             keypad: [false; 16],
+            cycle_overrun: 0,
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            watch_hit: false,
+            history: None,
+            tracer: None,
+            profiler: None,
+            coverage: None,
         }
     }
 
@@ -97,45 +141,359 @@ impl CPU {
         }
     }
 
+    //Starts over from power on with a new program. The tracer, profiler and coverage stay attached and
+    //breakpoints and watchpoints are kept or cleared as asked, while the undo history is emptied.
+    pub fn reload(&mut self, data: &[u8], keep_breakpoints: bool) {
+        let mut cpu = CPU::new();
+        cpu.load(data);
+        if keep_breakpoints {
+            cpu.breakpoints = std::mem::take(&mut self.breakpoints);
+            cpu.watchpoints = std::mem::take(&mut self.watchpoints);
+        }
+        cpu.history = self.history.take().map(|mut history| {
+            history.clear();
+            history
+        });
+        cpu.tracer = self.tracer.take();
+        cpu.profiler = self.profiler.take();
+        cpu.coverage = self.coverage.take();
+        *self = cpu;
+    }
+
+    pub fn vram(&self) -> &VRAM {
+        &self.vram
+    }
+
     fn read_opcode(&self) -> OpCode {
-        (self.memory[self.pc] as OpCode) << 8 | (self.memory[self.pc + 1] as OpCode)
+        (self.memory[self.pc] as OpCode) << 8 | (self.memory[(self.pc + 1) % MEMORY_SIZE] as OpCode)
     }
 
     pub fn tick(&mut self, keypad: &[bool; 16]) -> OutputState {
         self.keypad = *keypad;
         self.vram_changed = false;
+        self.dirty_rows = 0;
 
-        if self.waiting_for_key_press.is_some() {
-            for i in 0..keypad.len() {
-                if keypad[i] {
-                    self.registers[self.waiting_for_key_press.unwrap()] = i as u8;
-                    self.waiting_for_key_press = None;
-                }
+        let mut instructions = 0;
+        let breakpoint = self.at_breakpoint();
+        if !breakpoint && !self.wait_for_key_press(keypad) {
+            self.step();
+            instructions = 1;
+        }
+        OutputState {
+            vram_changed: self.vram_changed,
+            dirty_rows: self.dirty_rows,
+            beep: self.st > 0,
+            instructions,
+            breakpoint: breakpoint || self.watch_hit,
+        }
+    }
+
+    //Runs one 60 Hz frame worth of instructions as the COSMAC VIP would,
+    //stopping early when a sprite draw waits for the display interrupt. The timers are left to end_frame.
+    pub fn run_frame(&mut self, keypad: &[bool; 16]) -> OutputState {
+        self.keypad = *keypad;
+        self.vram_changed = false;
+        self.dirty_rows = 0;
+
+        let budget = timing::VIP_CYCLES_AVAILABLE_PER_FRAME;
+        let mut cycles = self.cycle_overrun;
+        let mut instructions = 0;
+        let mut breakpoint = false;
+        while cycles < budget && !self.wait_for_key_press(keypad) {
+            if self.at_breakpoint() {
+                breakpoint = true;
+                break;
             }
-        } else {
-            if self.dt > 0 {
-                self.dt -= 1;
+            let waits_for_vblank = timing::waits_for_vblank(self.read_opcode());
+            cycles += self.step();
+            instructions += 1;
+            if self.watch_hit {
+                breakpoint = true;
+                break;
             }
-            if self.dt > 0 {
-                self.dt -= 1;
+            if waits_for_vblank {
+                cycles = cycles.max(budget);
             }
-            let pc_change = self.run_opcode(self.read_opcode());
-
-            match pc_change {
-                Ok(PcChange::Increment) => self.pc += OPCODE_SIZE,
-                Ok(PcChange::Skip) => self.pc += OPCODE_SIZE * 2,
-                Ok(PcChange::Jump(address)) => self.pc = address,
-                Err(Error::InvalidOpcode(opcode)) => println!("Invalid Opcode {:?}", opcode),
-                Err(error) => println!("ERROR {:?}", error),
-            };
         }
+        //A breakpoint part way through leaves the frame's cycles so far to the next call, which finishes it
+        self.cycle_overrun = if breakpoint { cycles } else { cycles.saturating_sub(budget) };
         OutputState {
-            vram: &self.vram,
             vram_changed: self.vram_changed,
-            beep: self.dt > 0,
+            dirty_rows: self.dirty_rows,
+            beep: self.st > 0,
+            instructions,
+            breakpoint,
+        }
+    }
+
+    //Executes a single instruction for debuggers, ignoring breakpoints and leaving the timers alone
+    pub fn step_instruction(&mut self, keypad: &[bool; 16]) -> OutputState {
+        self.keypad = *keypad;
+        self.vram_changed = false;
+        self.dirty_rows = 0;
+
+        let mut instructions = 0;
+        if !self.wait_for_key_press(keypad) {
+            self.step();
+            instructions = 1;
+        }
+        OutputState {
+            vram_changed: self.vram_changed,
+            dirty_rows: self.dirty_rows,
+            beep: self.st > 0,
+            instructions,
+            breakpoint: false,
+        }
+    }
+
+    pub fn registers(&self) -> Registers {
+        Registers{v: self.registers, i: self.i.value as u16, pc: self.pc as u16, sp: self.sp as u8, dt: self.dt, st: self.st}
+    }
+
+    pub fn set_registers(&mut self, registers: &Registers) {
+        self.registers = registers.v;
+        self.i = IndexRegister::from(registers.i as usize);
+        //PC has to leave room for a whole opcode
+        self.pc = (registers.pc as usize).min(MEMORY_SIZE - OPCODE_SIZE);
+        self.sp = (registers.sp as usize).min(STACK_SIZE);
+        self.dt = registers.dt;
+        self.st = registers.st;
+    }
+
+    //Return addresses, the most recent call last
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.sp.min(STACK_SIZE)]
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    //Returns false, changing nothing, when the bytes do not all fit in memory
+    pub fn write_memory(&mut self, address: usize, data: &[u8]) -> bool {
+        match self.memory.get_mut(address..address + data.len()) {
+            Some(memory) => {
+                memory.copy_from_slice(data);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, address: usize) {
+        if !self.breakpoints.contains(&address) {
+            self.breakpoints.push(address);
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, address: usize) {
+        self.breakpoints.retain(|&breakpoint| breakpoint != address);
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    //Execution stops after an instruction writes to a watched address
+    pub fn add_watchpoint(&mut self, address: usize) {
+        if !self.watchpoints.contains(&address) {
+            self.watchpoints.push(address);
         }
     }
 
+    pub fn remove_watchpoint(&mut self, address: usize) {
+        self.watchpoints.retain(|&watchpoint| watchpoint != address);
+    }
+
+    pub fn has_watchpoint(&self, address: usize) -> bool {
+        self.watchpoints.contains(&address)
+    }
+
+    //Every instruction executed is written to the tracer
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) {
+        if let Some(Err(error)) = self.tracer.as_mut().map(Tracer::flush) {
+            println!("ERROR {:?}", error);
+        }
+        self.tracer = tracer;
+    }
+
+    //Counts where execution time goes until the profiler is taken back
+    pub fn set_profiler(&mut self, profiler: Option<Profiler>) {
+        self.profiler = profiler;
+    }
+
+    pub fn take_profiler(&mut self) -> Option<Profiler> {
+        self.profiler.take()
+    }
+
+    //Records how memory is used until the coverage is taken back
+    pub fn set_coverage(&mut self, coverage: Option<Coverage>) {
+        self.coverage = coverage;
+    }
+
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+
+    //Marks the end of a 60 Hz frame: the timers count down once, whichever timing ran the instructions,
+    //and the profiler counts the frame for its cycles per frame
+    pub fn end_frame(&mut self) {
+        self.dt = self.dt.saturating_sub(1);
+        self.st = self.st.saturating_sub(1);
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.count_frame();
+        }
+    }
+
+    //Keeps an undo log of the last instructions executed, or none with a limit of 0
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history = if limit == 0 { None } else { Some(History::new(limit)) };
+    }
+
+    pub fn history_len(&self) -> usize {
+        self.history.as_ref().map_or(0, History::len)
+    }
+
+    //Undoes the last instruction executed, returns false when there is nothing to undo.
+    //Random numbers are not replayed, running forward again draws new ones.
+    pub fn step_back(&mut self) -> bool {
+        self.undo().is_some()
+    }
+
+    //Steps back until a breakpoint or an instruction that wrote to a watchpoint,
+    //returns false when the history ran out first
+    pub fn reverse_continue(&mut self) -> bool {
+        while let Some(watch_hit) = self.undo() {
+            if watch_hit || self.at_breakpoint() {
+                return true;
+            }
+        }
+        false
+    }
+
+    //Returns whether the undone instruction hit a watchpoint
+    fn undo(&mut self) -> Option<bool> {
+        let entry = self.history.as_mut()?.pop()?;
+        self.set_registers(&entry.registers);
+        self.stack = entry.stack;
+        self.waiting_for_key_press = entry.waiting_for_key_press;
+        self.cycle_overrun = entry.cycle_overrun;
+        for &(address, value) in entry.memory.iter().rev() {
+            self.memory[address] = value;
+        }
+        for &(y, row) in entry.rows.iter().rev() {
+            self.vram[y] = row;
+        }
+        Some(entry.watch_hit)
+    }
+
+    //Memory writes by instructions go through here, for the undo log and watchpoints
+    fn write_byte(&mut self, address: usize, value: u8) {
+        let previous = self.memory[address];
+        let watched = self.watchpoints.contains(&address);
+        self.watch_hit |= watched;
+        if let Some(entry) = self.history.as_mut().and_then(History::current) {
+            entry.memory.push((address, previous));
+            entry.watch_hit |= watched;
+        }
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.written(address);
+        }
+        self.memory[address] = value;
+    }
+
+    //Data reads by instructions go through here, for coverage
+    fn read_byte(&mut self, address: usize) -> u8 {
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.read(address);
+        }
+        self.memory[address]
+    }
+
+    //Called before an instruction changes a VRAM row
+    fn save_row(&mut self, y: usize) {
+        let row = self.vram[y];
+        if let Some(entry) = self.history.as_mut().and_then(History::current) {
+            if !entry.rows.iter().any(|&(saved, _)| saved == y) {
+                entry.rows.push((y, row));
+            }
+        }
+    }
+
+    pub fn has_breakpoint(&self, address: usize) -> bool {
+        self.breakpoints.contains(&address)
+    }
+
+    pub fn at_breakpoint(&self) -> bool {
+        self.has_breakpoint(self.pc)
+    }
+
+    //Returns true while FX0A is still blocking execution
+    fn wait_for_key_press(&mut self, keypad: &[bool; 16]) -> bool {
+        match self.waiting_for_key_press {
+            Some(x) => {
+                for (i, &pressed) in keypad.iter().enumerate() {
+                    if pressed {
+                        self.registers[x] = i as u8;
+                        self.waiting_for_key_press = None;
+                    }
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    //Executes the instruction at PC and returns its cost in COSMAC VIP machine cycles
+    fn step(&mut self) -> u32 {
+        self.watch_hit = false;
+        if self.history.is_some() {
+            let entry = UndoEntry {
+                registers: self.registers(),
+                stack: self.stack,
+                waiting_for_key_press: self.waiting_for_key_press,
+                cycle_overrun: self.cycle_overrun,
+                memory: Vec::new(),
+                rows: Vec::new(),
+                watch_hit: false,
+            };
+            if let Some(history) = self.history.as_mut() {
+                history.push(entry);
+            }
+        }
+        let traced = self.tracer.as_ref().map(|_| self.registers());
+        let pc = self.pc;
+        let opcode = self.read_opcode();
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.executed(pc);
+        }
+        let x = ((opcode & 0x0F00) >> 8) as RegisterIndex;
+        let mut cycles = timing::cosmac_vip_cycles(opcode, self.registers[x], self.registers[0], self.i.value);
+
+        match self.run_opcode(opcode) {
+            Ok(PcChange::Increment) => self.pc = (self.pc + OPCODE_SIZE) % MEMORY_SIZE,
+            Ok(PcChange::Skip) => {
+                self.pc = (self.pc + OPCODE_SIZE * 2) % MEMORY_SIZE;
+                cycles += timing::SKIP_CYCLES;
+            }
+            //BNNN can reach past the end of memory, which wraps like the address bus
+            Ok(PcChange::Jump(address)) => self.pc = address % MEMORY_SIZE,
+            Err(Error::InvalidOpcode(opcode)) => println!("Invalid Opcode {:?}", opcode),
+            Err(error) => println!("ERROR {:?}", error),
+        };
+        if let Some(before) = traced {
+            let after = self.registers();
+            if let Some(Err(error)) = self.tracer.as_mut().map(|tracer| tracer.record(opcode, cycles, &before, &after)) {
+                println!("ERROR {:?}", error);
+                self.tracer = None;
+            }
+        }
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.record(pc, opcode, cycles, self.pc, self.sp);
+        }
+        cycles
+    }
+
     fn run_opcode(&mut self, opcode: OpCode) -> Result<PcChange, Error>{
         let c = ((opcode & 0xF000) >> 12) as u8;
         let x = ((opcode & 0x0F00) >> 8) as u8;
@@ -188,22 +546,22 @@ impl CPU {
 
     fn set_registers_to_memory_at_i(&mut self, x: RegisterIndex) -> Result<PcChange, Error> {
         for i in 0..x + 1 {
-            self.registers[i] = self.memory[self.i.value + i];
+            self.registers[i] = self.read_byte(self.i.offset(i));
         }
         Ok(PcChange::Increment)
     }
 
     fn set_memory_at_i_to_registers(&mut self, x: RegisterIndex) -> Result<PcChange, Error> {
         for i in 0..x + 1 {
-            self.memory[self.i.value + i] = self.registers[i];
+            self.write_byte(self.i.offset(i), self.registers[i]);
         }
         Ok(PcChange::Increment)
     }
 
     fn set_memory_at_i_to_decimal_value_of_register_x(&mut self, x: RegisterIndex) -> Result<PcChange, Error> {
-        self.memory[self.i.value] = self.registers[x] / 100;
-        self.memory[self.i.value + 1] = (self.registers[x] % 100) / 10;
-        self.memory[self.i.value + 2] = self.registers[x] %10;
+        self.write_byte(self.i.offset(0), self.registers[x] / 100);
+        self.write_byte(self.i.offset(1), (self.registers[x] % 100) / 10);
+        self.write_byte(self.i.offset(2), self.registers[x] %10);
         Ok(PcChange::Increment)
     }
 
@@ -235,7 +593,7 @@ impl CPU {
     }
 
     fn set_register_x_to_next_pressed_key(&mut self, x: RegisterIndex) -> Result<PcChange, Error> {
-        self.waiting_for_key_press = Some(x as usize);
+        self.waiting_for_key_press = Some(x);
         Ok(PcChange::Increment)
     }
 
@@ -244,8 +602,9 @@ impl CPU {
         Ok(PcChange::Increment)
     }
 
+    //Only the low nibble of Vx selects the key
     fn skip_next_op_if_reg_x_key_is_not_pressed(&self, x: RegisterIndex) -> Result<PcChange, Error> {
-        if self.keypad[self.registers[x] as usize] {
+        if self.keypad[(self.registers[x] & 0xF) as usize] {
             Ok(PcChange::Increment)
         } else {
             Ok(PcChange::Skip)
@@ -253,7 +612,7 @@ impl CPU {
     }
 
     fn skip_next_op_if_reg_x_key_is_pressed(&self, x: RegisterIndex) -> Result<PcChange, Error> {
-        if self.keypad[self.registers[x] as usize] {
+        if self.keypad[(self.registers[x] & 0xF) as usize] {
             Ok(PcChange::Skip)
         } else {
             Ok(PcChange::Increment)
@@ -261,12 +620,17 @@ impl CPU {
     }
 
     fn display_sprite(&mut self, x: RegisterIndex, y: RegisterIndex, n: SpriteSize) -> Result<PcChange, Error> {
+        //Coordinates are read before the collision flag, which may be one of them
+        let (left, top) = (self.registers[x] as usize, self.registers[y] as usize);
         self.registers[FLAG_REGISTER] = 0x00;
         for byte in 0..n as usize{
-            let y = (self.registers[y] as usize + byte) % DISPLAY_HEIGHT;
+            let y = (top + byte) % DISPLAY_HEIGHT;
+            let sprite = self.read_byte(self.i.offset(byte));
+            self.save_row(y);
+            self.dirty_rows |= 1 << y;
             for bit in 0..8 {
-                let x = (self.registers[x] as usize + bit) % DISPLAY_WIDTH;
-                let color = (self.memory[self.i.value + byte] >> (7 - bit)) & 1;
+                let x = (left + bit) % DISPLAY_WIDTH;
+                let color = (sprite >> (7 - bit)) & 1;
                 self.registers[FLAG_REGISTER] |= color & self.vram[y][x];
                 self.vram[y][x] ^= color;
             }
@@ -294,45 +658,31 @@ impl CPU {
     }
 
     fn shift_register_x_left(&mut self, x:RegisterIndex) -> Result<PcChange, Error> {
-        if self.registers[x] & 0x80 != 0 {
-            self.registers[FLAG_REGISTER] = 1;
-        } else {
-            self.registers[FLAG_REGISTER] = 0;
-        }
+        let shifted_out = self.registers[x] >> 7;
         self.registers[x] <<= 1;
+        self.registers[FLAG_REGISTER] = shifted_out;
         Ok(PcChange::Increment)
     }
 
-    //Set VF NOT borrow
+    //Set VF NOT borrow. The flag is written last so it survives when VF is an operand
     fn set_register_x_to_register_y_sub_register_x(&mut self, x: RegisterIndex, y: RegisterIndex) -> Result<PcChange, Error> {
-        if self.registers[y] > self.registers[x] {
-            self.registers[FLAG_REGISTER] = 1;
-        }
-        else {
-            self.registers[FLAG_REGISTER] = 0;
-        }
+        let not_borrow = self.registers[y] > self.registers[x];
         self.registers[x] = self.registers[y].wrapping_sub(self.registers[x]);
+        self.registers[FLAG_REGISTER] = not_borrow as Register;
         Ok(PcChange::Increment)
     }
 
     fn shift_register_x_right(&mut self, x:RegisterIndex) -> Result<PcChange, Error> {
-        if self.registers[x] & 0x1 != 0 {
-            self.registers[FLAG_REGISTER] = 1;
-        } else {
-            self.registers[FLAG_REGISTER] = 0;
-        }
+        let shifted_out = self.registers[x] & 0x1;
         self.registers[x] >>= 1;
+        self.registers[FLAG_REGISTER] = shifted_out;
         Ok(PcChange::Increment)
     }
 
     fn set_register_x_to_register_x_sub_register_y(&mut self, x: RegisterIndex, y: RegisterIndex) -> Result<PcChange, Error> {
-        if self.registers[x] > self.registers[y] {
-            self.registers[FLAG_REGISTER] = 1;
-        }
-        else {
-            self.registers[FLAG_REGISTER] = 0;
-        }
+        let not_borrow = self.registers[x] > self.registers[y];
         self.registers[x] = self.registers[x].wrapping_sub(self.registers[y]);
+        self.registers[FLAG_REGISTER] = not_borrow as Register;
         Ok(PcChange::Increment)
     }
 
@@ -380,12 +730,16 @@ impl CPU {
     }
 
     fn clear_screen(&mut self) -> Result<PcChange, Error> {
+        for y in 0..DISPLAY_HEIGHT {
+            self.save_row(y);
+        }
         for row in self.vram.iter_mut() {
             for pixel in row.iter_mut() {
                 *pixel = 0;
             }
         }
         self.vram_changed = true;
+        self.dirty_rows = ALL_ROWS;
         Ok(PcChange::Increment)
     }
 
@@ -402,7 +756,7 @@ impl CPU {
     }
 
     fn execute_subroutine_at_address_nnn(&mut self, address: NNN) -> Result<PcChange, Error> {
-        if self.sp > self.stack.len() {
+        if self.sp >= self.stack.len() {
             return Err(Error::StackOverflow);
         }
         self.stack[self.sp] = (self.pc + OPCODE_SIZE) as Address;
@@ -429,6 +783,20 @@ impl CPU {
     }
 }
 
+impl Default for CPU {
+    fn default() -> Self {
+        CPU::new()
+    }
+}
+
 #[cfg(test)]
 #[path = "./cpu_test.rs"]
-mod cpu_test;
\ No newline at end of file
+mod cpu_test;
+
+#[cfg(test)]
+#[path = "./reference_test.rs"]
+mod reference_test;
+
+#[cfg(test)]
+#[path = "./differential_test.rs"]
+mod differential_test;
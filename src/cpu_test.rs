@@ -1,4 +1,4 @@
-use crate::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use crate::{DISPLAY_HEIGHT, DISPLAY_WIDTH, ALL_ROWS};
 use crate::cpu::{Error, FLAG_REGISTER, PcChange};
 use super::CPU;
 
@@ -195,6 +195,21 @@ fn test_set_register_x_to_register_x_sub_register_y() {
     assert_eq!(cpu.registers[FLAG_REGISTER], 0x00);
 }
 
+#[test]
+fn test_flag_register_operands() {
+    let mut cpu = CPU::new();
+    cpu.registers[9] = 0x00;
+    cpu.registers[FLAG_REGISTER] = 0x0D;
+    let change = cpu.run_opcode(0x89F5);
+    assert!(change.is_ok());
+    assert_eq!(cpu.registers[9], 0xF3);
+    assert_eq!(cpu.registers[FLAG_REGISTER], 0x00);
+    cpu.registers[FLAG_REGISTER] = 0x81;
+    let change = cpu.run_opcode(0x8F0E);
+    assert!(change.is_ok());
+    assert_eq!(cpu.registers[FLAG_REGISTER], 0x01);
+}
+
 #[test]
 fn test_shift_register_x_right() {
     let mut cpu = CPU::new();
@@ -267,6 +282,7 @@ fn test_set_register_i_to_nnn() {
     assert_eq!(change.unwrap(), PcChange::Increment);
     assert_eq!(cpu.i.value, 0x230);
     let change = cpu.run_opcode(0xA130);
+    assert!(change.is_ok());
     assert_eq!(cpu.i.value, 0x130);
 }
 
@@ -302,6 +318,32 @@ fn test_display_sprite() {
     assert!(cpu.vram_changed);
 }
 
+#[test]
+fn test_display_sprite_at_flag_register() {
+    let mut cpu = CPU::new();
+    cpu.i.value = 0;
+    cpu.memory[0] = 0b10000000;
+    cpu.registers[FLAG_REGISTER] = 3;
+    cpu.registers[1] = 2;
+    let change = cpu.run_opcode(0xDF11);
+    assert!(change.is_ok());
+    assert_eq!(cpu.vram[2][3], 1);
+    assert_eq!(cpu.registers[FLAG_REGISTER], 0);
+}
+
+#[test]
+fn test_dirty_rows() {
+    let mut cpu = CPU::new();
+    cpu.registers[0] = 0;
+    cpu.registers[1] = 30;
+    let change = cpu.run_opcode(0xD013);
+    assert!(change.is_ok());
+    assert_eq!(cpu.dirty_rows, 0b11 << 30 | 0b1);
+    let change = cpu.run_opcode(0x00E0);
+    assert!(change.is_ok());
+    assert_eq!(cpu.dirty_rows, ALL_ROWS);
+}
+
 #[test]
 fn test_set_register_x_to_timer_register() {
     let mut cpu = CPU::new();
@@ -401,3 +443,207 @@ fn test_set_registers_to_memory_at_i() {
     }
     assert_eq!(change.unwrap(), PcChange::Increment);
 }
+
+#[test]
+fn test_run_frame_stops_at_sprite_draw() {
+    let mut cpu = CPU::new();
+    cpu.load(&[0x60, 0x01, 0xD0, 0x01, 0x60, 0x02]);
+    cpu.st = 2;
+    let output = cpu.run_frame(&[false; 16]);
+    assert!(output.vram_changed);
+    assert!(output.beep);
+    assert_eq!(cpu.pc, 0x204);
+    assert_eq!(cpu.registers[0], 0x01);
+    cpu.end_frame();
+    assert_eq!(cpu.st, 1);
+    cpu.end_frame();
+    let output = cpu.run_frame(&[false; 16]);
+    assert!(!output.vram_changed);
+    assert!(!output.beep);
+    assert_eq!(cpu.registers[0], 0x02);
+}
+
+#[test]
+fn test_run_frame_spends_cycle_budget() {
+    let mut cpu = CPU::new();
+    //1200: jump to self, 23 machine cycles per iteration
+    cpu.load(&[0x12, 0x00]);
+    cpu.dt = 5;
+    cpu.run_frame(&[false; 16]);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.dt, 5);
+    assert!(cpu.cycle_overrun < 23);
+}
+
+#[test]
+fn test_run_frame_resumes_after_breakpoint() {
+    let mut cpu = CPU::new();
+    //1202: jump to the next instruction, 1200: jump back
+    cpu.load(&[0x12, 0x02, 0x12, 0x00]);
+    cpu.add_breakpoint(0x202);
+    assert!(cpu.run_frame(&[false; 16]).breakpoint);
+    cpu.remove_breakpoint(0x202);
+    //The instruction run before the breakpoint still counts towards the frame
+    let resumed = cpu.run_frame(&[false; 16]);
+    assert!(!resumed.breakpoint);
+    let mut full = CPU::new();
+    full.load(&[0x12, 0x02, 0x12, 0x00]);
+    assert_eq!(resumed.instructions + 1, full.run_frame(&[false; 16]).instructions);
+}
+
+#[test]
+fn test_timers_count_down_once_per_frame() {
+    let mut cpu = CPU::new();
+    //1200: jump to self
+    cpu.load(&[0x12, 0x00]);
+    (cpu.dt, cpu.st) = (10, 1);
+    for _ in 0..8 {
+        cpu.tick(&[false; 16]);
+    }
+    assert_eq!((cpu.dt, cpu.st), (10, 1));
+    cpu.end_frame();
+    assert_eq!((cpu.dt, cpu.st), (9, 0));
+    cpu.end_frame();
+    assert_eq!((cpu.dt, cpu.st), (8, 0));
+}
+
+#[test]
+fn test_tick_beeps_while_sound_timer_runs() {
+    let mut cpu = CPU::new();
+    //F015: DT = V0, then jump to self
+    cpu.load(&[0x60, 0x05, 0xF0, 0x15, 0x12, 0x04]);
+    cpu.st = 1;
+    assert!(cpu.tick(&[false; 16]).beep);
+    assert!(cpu.tick(&[false; 16]).beep);
+    cpu.end_frame();
+    assert!(!cpu.tick(&[false; 16]).beep);
+    assert_eq!(cpu.dt, 4);
+}
+
+#[test]
+fn test_breakpoint_stops_execution() {
+    let mut cpu = CPU::new();
+    cpu.load(&[0x60, 0x01, 0x61, 0x02, 0x12, 0x04]);
+    cpu.add_breakpoint(0x202);
+    let output = cpu.run_frame(&[false; 16]);
+    assert!(output.breakpoint);
+    assert_eq!(output.instructions, 1);
+    assert_eq!(cpu.pc, 0x202);
+    assert!(cpu.tick(&[false; 16]).breakpoint);
+    assert_eq!(cpu.pc, 0x202);
+    cpu.step_instruction(&[false; 16]);
+    assert_eq!(cpu.registers[1], 0x02);
+    cpu.remove_breakpoint(0x202);
+    assert!(!cpu.at_breakpoint());
+}
+
+#[test]
+fn test_step_back() {
+    let mut cpu = CPU::new();
+    //LD V0, 123; LD I, 0x300; LD B, V0; CLS; DRW V0, V0, 1; CALL 0x200
+    cpu.load(&[0x60, 0x7B, 0xA3, 0x00, 0xF0, 0x33, 0x00, 0xE0, 0xD0, 0x01, 0x22, 0x00]);
+    cpu.set_history_limit(100);
+    cpu.vram[0][0] = 1;
+    for _ in 0..6 {
+        cpu.step_instruction(&[false; 16]);
+    }
+    assert_eq!(cpu.stack(), &[0x20C]);
+    assert_eq!(&cpu.memory[0x300..0x303], &[1, 2, 3]);
+    assert_eq!(cpu.history_len(), 6);
+
+    assert!(cpu.step_back());
+    assert_eq!(cpu.stack(), &[] as &[u16]);
+    assert_eq!(cpu.pc, 0x20A);
+    assert!(cpu.step_back());
+    assert!(cpu.step_back());
+    assert_eq!(cpu.vram[0][0], 1);
+    assert!(cpu.step_back());
+    assert_eq!(&cpu.memory[0x300..0x303], &[0, 0, 0]);
+    assert!(cpu.step_back());
+    assert!(cpu.step_back());
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.registers[0], 0);
+    assert!(!cpu.step_back());
+}
+
+#[test]
+fn test_reverse_continue_stops_at_watchpoint() {
+    let mut cpu = CPU::new();
+    //LD I, 0x300; LD [I], V0; ADD V0, 1; LD V1, 2; JP 0x202
+    cpu.load(&[0xA3, 0x00, 0xF0, 0x55, 0x70, 0x01, 0x61, 0x02, 0x12, 0x02]);
+    cpu.set_history_limit(100);
+    cpu.add_watchpoint(0x300);
+    let output = cpu.run_frame(&[false; 16]);
+    //Forward execution stops right after the write
+    assert!(output.breakpoint);
+    assert_eq!(cpu.pc, 0x204);
+    for _ in 0..3 {
+        cpu.step_instruction(&[false; 16]);
+    }
+    assert!(cpu.reverse_continue());
+    assert_eq!(cpu.pc, 0x202);
+    assert!(!cpu.reverse_continue());
+    assert_eq!(cpu.pc, 0x200);
+}
+
+#[test]
+fn test_memory_accesses_wrap_around() {
+    let mut cpu = CPU::new();
+    cpu.i.value = 0xFFE;
+    cpu.registers[0] = 123;
+    assert!(cpu.run_opcode(0xF033).is_ok());
+    assert_eq!(cpu.memory[0xFFE..], [1, 2]);
+    assert_eq!(cpu.memory[0], 3);
+    assert!(cpu.run_opcode(0xF265).is_ok());
+    assert_eq!(cpu.registers[..3], [1, 2, 3]);
+    assert!(cpu.run_opcode(0xD00F).is_ok());
+
+    cpu.pc = 0xFFF;
+    cpu.memory[0xFFF] = 0x12;
+    cpu.memory[0] = 0x34;
+    assert_eq!(cpu.read_opcode(), 0x1234);
+    cpu.registers[0] = 0xFF;
+    cpu.memory[0] = 0xBF;
+    cpu.memory[1] = 0xFF;
+    cpu.pc = 0;
+    cpu.step();
+    assert_eq!(cpu.pc, 0x0FE);
+}
+
+#[test]
+fn test_stack_overflow() {
+    let mut cpu = CPU::new();
+    cpu.sp = 16;
+    let change = cpu.run_opcode(0x2234);
+    assert_eq!(change.unwrap_err(), Error::StackOverflow);
+    assert_eq!(cpu.sp, 16);
+}
+
+#[test]
+fn test_key_uses_low_nibble() {
+    let mut cpu = CPU::new();
+    cpu.keypad[0x3] = true;
+    cpu.registers[1] = 0xF3;
+    assert_eq!(cpu.run_opcode(0xE19E).unwrap(), PcChange::Skip);
+    assert_eq!(cpu.run_opcode(0xE1A1).unwrap(), PcChange::Increment);
+}
+
+#[test]
+fn test_reload() {
+    let mut cpu = CPU::new();
+    cpu.load(&[0x60, 0x05, 0x12, 0x02]);
+    cpu.set_history_limit(10);
+    cpu.add_breakpoint(0x202);
+    cpu.add_watchpoint(0x300);
+    cpu.step();
+    cpu.vram[0][0] = 1;
+    cpu.reload(&[0x00, 0xE0], true);
+    assert_eq!(cpu.registers, [0; 16]);
+    assert_eq!(cpu.vram[0][0], 0);
+    assert_eq!(&cpu.memory[0x200..0x204], &[0x00, 0xE0, 0x00, 0x00]);
+    assert!(cpu.has_breakpoint(0x202) && cpu.has_watchpoint(0x300));
+    assert_eq!(cpu.history_len(), 0);
+    assert!(cpu.history.is_some());
+    cpu.reload(&[0x00, 0xE0], false);
+    assert!(!cpu.has_breakpoint(0x202) && !cpu.has_watchpoint(0x300));
+}
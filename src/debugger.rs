@@ -0,0 +1,333 @@
+//Full screen terminal debugger: disassembly, registers, stack, memory and a preview of the display.
+//While it runs, the keyboard belongs to the debugger. CHIP-8 keys are toggled with k and a hex digit,
+//and the ROM sees them held until they are toggled again or K lets go of them all.
+//Shifted step and continue run backwards through the CPU's undo log.
+use std::io::{self, Stdout};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::{cursor, execute, terminal};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::Terminal;
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::Frame;
+
+use crate::cpu::{CPU, Registers};
+use crate::disassembler::disassemble;
+use crate::drivers::{render_lines, Glyphs};
+use crate::emulator::TICKS_PER_FRAME;
+use crate::timing::Timing;
+use crate::MEMORY_SIZE;
+
+const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+const OPCODE_SIZE: usize = 2;
+const MEMORY_ROWS: usize = 8;
+const BYTES_PER_ROW: usize = 16;
+
+pub struct Debugger {
+    cpu: CPU,
+    timing: Timing,
+    //Instructions per 60 Hz frame with instruction timing
+    ticks_per_frame: u32,
+    //Instructions run so far in a frame a breakpoint stopped part way through
+    frame_ticks: u32,
+    //CHIP-8 keys held down for the ROM
+    keypad: [bool; 16],
+    //k was pressed and the next hex digit toggles a key
+    choosing_key: bool,
+    //Registers before the last step or run, to highlight what changed
+    previous: Registers,
+    cursor: usize,
+    running: bool,
+    //Breakpoint set by run to cursor, removed once the CPU stops
+    run_to: Option<usize>,
+    quit: bool,
+}
+
+impl Debugger {
+    pub fn new(cpu: CPU, timing: Timing) -> Self {
+        let registers = cpu.registers();
+        Debugger{cpu, timing, ticks_per_frame: TICKS_PER_FRAME, frame_ticks: 0, keypad: [false; 16], choosing_key: false, previous: registers,
+            cursor: registers.pc as usize, running: false, run_to: None, quit: false}
+    }
+
+    //As an Octo cartridge sets with its tickrate
+    pub fn set_ticks_per_frame(&mut self, ticks: u32) {
+        self.ticks_per_frame = ticks;
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) {
+        if std::mem::take(&mut self.choosing_key) {
+            let digit = match key {
+                KeyCode::Char(c) => c.to_digit(16),
+                _ => None,
+            };
+            if let Some(digit) = digit {
+                self.keypad[digit as usize] = !self.keypad[digit as usize];
+            }
+            return;
+        }
+        match key {
+            KeyCode::Char('k') => self.choosing_key = true,
+            KeyCode::Char('K') => self.keypad = [false; 16],
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Char('c') if self.running => self.stop(),
+            KeyCode::Char('c') => self.resume(),
+            KeyCode::Char('s') if !self.running => {
+                self.previous = self.cpu.registers();
+                self.cpu.step_instruction(&self.keypad);
+                self.cursor = self.pc();
+            }
+            KeyCode::Char('r') if !self.running => {
+                if !self.cpu.has_breakpoint(self.cursor) {
+                    self.cpu.add_breakpoint(self.cursor);
+                    self.run_to = Some(self.cursor);
+                }
+                self.resume();
+            }
+            KeyCode::Char('S') if !self.running => {
+                self.previous = self.cpu.registers();
+                self.cpu.step_back();
+                self.cursor = self.pc();
+            }
+            KeyCode::Char('C') if !self.running => {
+                self.previous = self.cpu.registers();
+                self.cpu.reverse_continue();
+                self.cursor = self.pc();
+            }
+            KeyCode::Char('w') => {
+                let i = self.cpu.registers().i as usize;
+                if self.cpu.has_watchpoint(i) {
+                    self.cpu.remove_watchpoint(i);
+                } else {
+                    self.cpu.add_watchpoint(i);
+                }
+            }
+            KeyCode::Char('b') => {
+                if self.cpu.has_breakpoint(self.cursor) {
+                    self.cpu.remove_breakpoint(self.cursor);
+                } else {
+                    self.cpu.add_breakpoint(self.cursor);
+                }
+            }
+            KeyCode::Char('p') => self.cursor = self.pc(),
+            KeyCode::Up => self.cursor = self.cursor.saturating_sub(OPCODE_SIZE),
+            KeyCode::Down => self.cursor = (self.cursor + OPCODE_SIZE).min(MEMORY_SIZE - OPCODE_SIZE),
+            KeyCode::PageUp => self.cursor = self.cursor.saturating_sub(OPCODE_SIZE * 16),
+            KeyCode::PageDown => self.cursor = (self.cursor + OPCODE_SIZE * 16).min(MEMORY_SIZE - OPCODE_SIZE),
+            _ => {}
+        }
+    }
+
+    //Runs one 60 Hz frame worth of instructions, stopping at breakpoints
+    pub fn run_frame(&mut self) {
+        if !self.running {
+            return;
+        }
+        //A breakpoint leaves the frame unfinished, so its timers and the rest of its instructions wait for the next run
+        match self.timing {
+            Timing::Instruction => {
+                while self.frame_ticks < self.ticks_per_frame {
+                    if self.cpu.tick(&self.keypad).breakpoint {
+                        return self.stop();
+                    }
+                    self.frame_ticks += 1;
+                }
+                self.frame_ticks = 0;
+            }
+            Timing::CosmacVip => {
+                if self.cpu.run_frame(&self.keypad).breakpoint {
+                    return self.stop();
+                }
+            }
+        }
+        self.cpu.end_frame();
+    }
+
+    fn resume(&mut self) {
+        self.previous = self.cpu.registers();
+        //Step off the breakpoint the CPU is stopped at, or it would stop there again
+        if self.cpu.at_breakpoint() {
+            self.cpu.step_instruction(&self.keypad);
+        }
+        self.running = true;
+    }
+
+    fn stop(&mut self) {
+        self.running = false;
+        if let Some(address) = self.run_to.take() {
+            self.cpu.remove_breakpoint(address);
+        }
+        self.cursor = self.pc();
+    }
+
+    fn pc(&self) -> usize {
+        self.cpu.registers().pc as usize
+    }
+}
+
+//Puts the terminal back as it was when dropped, even when the debugger fails or panics
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn new() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let guard = TerminalGuard;
+        execute!(io::stdout(), terminal::EnterAlternateScreen)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), terminal::LeaveAlternateScreen, cursor::Show);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+//Hands the CPU back when the debugger quits, so the reports collected while it ran can be saved
+pub fn run(cpu: CPU, timing: Timing, ticks_per_frame: u32) -> io::Result<CPU> {
+    let _guard = TerminalGuard::new()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    let mut debugger = Debugger::new(cpu, timing);
+    debugger.set_ticks_per_frame(ticks_per_frame);
+    run_loop(&mut terminal, &mut debugger)?;
+    Ok(debugger.cpu)
+}
+
+fn run_loop(terminal: &mut Terminal<CrosstermBackend<Stdout>>, debugger: &mut Debugger) -> io::Result<()> {
+    let mut next_frame = Instant::now();
+    while !debugger.quit {
+        terminal.draw(|frame| draw(frame, debugger))?;
+        next_frame += FRAME_DURATION;
+        while event::poll(next_frame.saturating_duration_since(Instant::now()))? {
+            if let Event::Key(key_event) = event::read()? {
+                if key_event.kind != KeyEventKind::Release {
+                    debugger.handle_key(key_event.code);
+                }
+            }
+        }
+        debugger.run_frame();
+        let now = Instant::now();
+        if next_frame > now {
+            thread::sleep(next_frame - now);
+        } else {
+            next_frame = now;
+        }
+    }
+    Ok(())
+}
+
+fn draw(frame: &mut Frame, debugger: &Debugger) {
+    let rows = Layout::default().direction(Direction::Vertical)
+        .constraints([Constraint::Min(12), Constraint::Length(MEMORY_ROWS as u16 + 2), Constraint::Length(1)])
+        .split(frame.size());
+    let top = Layout::default().direction(Direction::Horizontal)
+        .constraints([Constraint::Min(36), Constraint::Length(26), Constraint::Length(12)])
+        .split(rows[0]);
+    let bottom = Layout::default().direction(Direction::Horizontal)
+        .constraints([Constraint::Min(72), Constraint::Length(34)])
+        .split(rows[1]);
+
+    frame.render_widget(Paragraph::new(disassembly_lines(debugger, top[0].height.saturating_sub(2) as usize))
+        .block(Block::default().borders(Borders::ALL).title("Disassembly")), top[0]);
+    frame.render_widget(Paragraph::new(register_lines(debugger))
+        .block(Block::default().borders(Borders::ALL).title("Registers")), top[1]);
+    frame.render_widget(Paragraph::new(stack_lines(debugger))
+        .block(Block::default().borders(Borders::ALL).title("Stack")), top[2]);
+    frame.render_widget(Paragraph::new(memory_lines(debugger))
+        .block(Block::default().borders(Borders::ALL).title("Memory at I")), bottom[0]);
+    let display: Vec<Line> = render_lines(debugger.cpu.vram(), Glyphs::Braille).into_iter().map(Line::from).collect();
+    frame.render_widget(Paragraph::new(display).block(Block::default().borders(Borders::ALL).title("Display")), bottom[1]);
+    frame.render_widget(Paragraph::new(status_line(debugger)), rows[2]);
+}
+
+fn disassembly_lines(debugger: &Debugger, height: usize) -> Vec<Line<'static>> {
+    let memory = debugger.cpu.memory();
+    let pc = debugger.pc();
+    let start = debugger.cursor.saturating_sub(OPCODE_SIZE * (height / 2));
+    (0..height).map(|line| start + line * OPCODE_SIZE).take_while(|&address| address + 1 < memory.len()).map(|address| {
+        let opcode = (memory[address] as u16) << 8 | memory[address + 1] as u16;
+        let breakpoint = if debugger.cpu.has_breakpoint(address) { "●" } else { " " };
+        let marker = if address == pc { "▶" } else { " " };
+        let text = format!("{}{} {:03X}  {:04X}  {}", breakpoint, marker, address, opcode, disassemble(opcode));
+        let mut style = Style::default();
+        if address == pc {
+            style = style.fg(Color::Yellow);
+        }
+        if debugger.cpu.has_breakpoint(address) {
+            style = style.fg(Color::Red);
+        }
+        if address == debugger.cursor {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        Line::styled(text, style)
+    }).collect()
+}
+
+fn register_lines(debugger: &Debugger) -> Vec<Line<'static>> {
+    let registers = debugger.cpu.registers();
+    let previous = debugger.previous;
+    let value = |name: String, value: String, changed: bool| {
+        let style = if changed { Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD) } else { Style::default() };
+        vec![Span::raw(name), Span::styled(value, style), Span::raw("  ")]
+    };
+    let mut lines: Vec<Line> = (0..8).map(|row| {
+        let mut spans = value(format!("V{:X} ", row), format!("{:02X}", registers.v[row]), registers.v[row] != previous.v[row]);
+        spans.extend(value(format!("V{:X} ", row + 8), format!("{:02X}", registers.v[row + 8]), registers.v[row + 8] != previous.v[row + 8]));
+        Line::from(spans)
+    }).collect();
+    lines.push(Line::from(""));
+    lines.push(Line::from(value(String::from("I  "), format!("{:03X}", registers.i), registers.i != previous.i)));
+    lines.push(Line::from(value(String::from("PC "), format!("{:03X}", registers.pc), registers.pc != previous.pc)));
+    lines.push(Line::from(value(String::from("SP "), format!("{:X}", registers.sp), registers.sp != previous.sp)));
+    let mut timers = value(String::from("DT "), format!("{:02X}", registers.dt), registers.dt != previous.dt);
+    timers.extend(value(String::from("ST "), format!("{:02X}", registers.st), registers.st != previous.st));
+    lines.push(Line::from(timers));
+    let held: Vec<String> = (0..16).filter(|&key| debugger.keypad[key]).map(|key| format!("{:X}", key)).collect();
+    let keys = if held.is_empty() { String::from("-") } else { held.join(" ") };
+    let prompt = if debugger.choosing_key { " k_" } else { "" };
+    lines.push(Line::from(format!("Keys {}{}", keys, prompt)));
+    lines
+}
+
+fn stack_lines(debugger: &Debugger) -> Vec<Line<'static>> {
+    debugger.cpu.stack().iter().rev().map(|&address| Line::from(format!("{:03X}", address))).collect()
+}
+
+//The rows around I, so sprite and BCD writes can be watched
+fn memory_lines(debugger: &Debugger) -> Vec<Line<'static>> {
+    let memory = debugger.cpu.memory();
+    let i = debugger.cpu.registers().i as usize;
+    let first_row = (i / BYTES_PER_ROW).min(memory.len() / BYTES_PER_ROW - MEMORY_ROWS);
+    (first_row..first_row + MEMORY_ROWS).map(|row| {
+        let address = row * BYTES_PER_ROW;
+        let mut spans = vec![Span::raw(format!("{:03X} ", address))];
+        for (offset, &byte) in memory[address..address + BYTES_PER_ROW].iter().enumerate() {
+            let mut style = Style::default();
+            if debugger.cpu.has_watchpoint(address + offset) {
+                style = style.fg(Color::Red);
+            }
+            if address + offset == i {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            spans.push(Span::raw(" "));
+            spans.push(Span::styled(format!("{:02X}", byte), style));
+        }
+        Line::from(spans)
+    }).collect()
+}
+
+fn status_line(debugger: &Debugger) -> Line<'static> {
+    let state = if debugger.running { String::from("Running") } else { format!("Stopped at {:03X}", debugger.pc()) };
+    Line::from(format!("{} | {} back | s/S step  c/C continue  r run to cursor  b breakpoint  w watch I  k0-F/K keys  up/down  p PC  q quit",
+        state, debugger.cpu.history_len()))
+}
+
+#[cfg(test)]
+#[path = "./debugger_test.rs"]
+mod debugger_test;
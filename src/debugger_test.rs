@@ -0,0 +1,148 @@
+use crossterm::event::KeyCode;
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+
+use crate::cpu::CPU;
+use crate::timing::Timing;
+use super::{draw, memory_lines, Debugger};
+
+fn debugger() -> Debugger {
+    let mut cpu = CPU::new();
+    //V0 += 1 forever
+    cpu.load(&[0x70, 0x01, 0x61, 0x05, 0x12, 0x00]);
+    Debugger::new(cpu, Timing::Instruction)
+}
+
+#[test]
+fn test_step_highlights_changes() {
+    let mut debugger = debugger();
+    debugger.handle_key(KeyCode::Char('s'));
+    assert_eq!(debugger.pc(), 0x202);
+    assert_eq!(debugger.cursor, 0x202);
+    assert_eq!(debugger.previous.v[0], 0);
+    assert_eq!(debugger.cpu.registers().v[0], 1);
+}
+
+#[test]
+fn test_breakpoint_stops_run() {
+    let mut debugger = debugger();
+    debugger.handle_key(KeyCode::Down);
+    debugger.handle_key(KeyCode::Down);
+    debugger.handle_key(KeyCode::Char('b'));
+    assert!(debugger.cpu.has_breakpoint(0x204));
+    debugger.handle_key(KeyCode::Char('c'));
+    assert!(debugger.running);
+    debugger.run_frame();
+    assert!(!debugger.running);
+    assert_eq!(debugger.pc(), 0x204);
+
+    //Continuing steps off the breakpoint and stops on it again after one loop
+    debugger.handle_key(KeyCode::Char('c'));
+    debugger.run_frame();
+    assert_eq!(debugger.pc(), 0x204);
+    assert_eq!(debugger.cpu.registers().v[0], 2);
+}
+
+#[test]
+fn test_breakpoint_leaves_frame_unfinished() {
+    let mut cpu = CPU::new();
+    //DT := 5, then loop forever
+    cpu.load(&[0x60, 0x05, 0xF0, 0x15, 0x12, 0x04]);
+    cpu.add_breakpoint(0x204);
+    let mut debugger = Debugger::new(cpu, Timing::Instruction);
+    debugger.handle_key(KeyCode::Char('c'));
+    debugger.run_frame();
+    assert!(!debugger.running);
+    assert_eq!(debugger.cpu.registers().dt, 5);
+    assert_eq!(debugger.frame_ticks, 2);
+
+    debugger.cpu.remove_breakpoint(0x204);
+    debugger.handle_key(KeyCode::Char('c'));
+    debugger.run_frame();
+    assert_eq!(debugger.cpu.registers().dt, 4);
+    assert_eq!(debugger.frame_ticks, 0);
+}
+
+#[test]
+fn test_keys_reach_the_rom() {
+    let mut cpu = CPU::new();
+    //V0 := key, then loop forever
+    cpu.load(&[0xF0, 0x0A, 0x12, 0x02]);
+    let mut debugger = Debugger::new(cpu, Timing::Instruction);
+    debugger.handle_key(KeyCode::Char('c'));
+    debugger.run_frame();
+    assert_eq!(debugger.pc(), 0x202);
+    debugger.handle_key(KeyCode::Char('k'));
+    debugger.handle_key(KeyCode::Char('b'));
+    assert!(debugger.keypad[0xB]);
+    //The key was not taken as a breakpoint
+    assert!(!debugger.cpu.has_breakpoint(debugger.cursor));
+    debugger.run_frame();
+    assert_eq!(debugger.cpu.registers().v[0], 0xB);
+    assert_eq!(debugger.pc(), 0x202);
+    debugger.handle_key(KeyCode::Char('K'));
+    assert_eq!(debugger.keypad, [false; 16]);
+}
+
+#[test]
+fn test_run_to_cursor() {
+    let mut debugger = debugger();
+    debugger.handle_key(KeyCode::Down);
+    debugger.handle_key(KeyCode::Char('r'));
+    debugger.run_frame();
+    assert!(!debugger.running);
+    assert_eq!(debugger.pc(), 0x202);
+    //The temporary breakpoint is gone
+    assert!(!debugger.cpu.has_breakpoint(0x202));
+}
+
+#[test]
+fn test_memory_follows_i() {
+    let mut cpu = CPU::new();
+    cpu.load(&[0xA3, 0x45]);
+    let mut debugger = Debugger::new(cpu, Timing::Instruction);
+    debugger.handle_key(KeyCode::Char('s'));
+    let lines = memory_lines(&debugger);
+    assert_eq!(lines[0].spans[0].content, "340 ");
+    let mut debugger = Debugger::new(CPU::new(), Timing::Instruction);
+    debugger.cpu.set_registers(&crate::cpu::Registers{i: 0xFFF, ..debugger.cpu.registers()});
+    assert_eq!(memory_lines(&debugger)[7].spans[0].content, "FF0 ");
+}
+
+#[test]
+fn test_draw() {
+    let mut debugger = debugger();
+    debugger.cpu.add_breakpoint(0x202);
+    for (width, height) in [(120, 40), (20, 10)] {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| draw(frame, &debugger)).unwrap();
+    }
+    let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+    terminal.draw(|frame| draw(frame, &debugger)).unwrap();
+    let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+    assert!(screen.contains("▶ 200  7001  ADD V0, 0x01"));
+    assert!(screen.contains("●  202  6105  LD V1, 0x05"));
+    assert!(screen.contains("Stopped at 200"));
+}
+
+#[test]
+fn test_step_back_and_reverse_continue() {
+    let mut cpu = CPU::new();
+    cpu.load(&[0x70, 0x01, 0x61, 0x05, 0x12, 0x00]);
+    cpu.set_history_limit(100);
+    let mut debugger = Debugger::new(cpu, Timing::Instruction);
+    debugger.handle_key(KeyCode::Char('s'));
+    debugger.handle_key(KeyCode::Char('S'));
+    assert_eq!(debugger.pc(), 0x200);
+    assert_eq!(debugger.cpu.registers().v[0], 0);
+
+    debugger.cpu.add_breakpoint(0x202);
+    for _ in 0..4 {
+        debugger.handle_key(KeyCode::Char('s'));
+    }
+    assert_eq!(debugger.pc(), 0x202);
+    assert_eq!(debugger.cpu.registers().v[0], 2);
+    debugger.handle_key(KeyCode::Char('C'));
+    assert_eq!(debugger.pc(), 0x202);
+    assert_eq!(debugger.cpu.registers().v[0], 1);
+}
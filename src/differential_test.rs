@@ -0,0 +1,287 @@
+//Runs random programs from random starting states on CPU and on the reference interpreter,
+//comparing the whole machine after every instruction. A divergence is shrunk to a small
+//reproducer before it is reported.
+use std::fmt;
+
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+use crate::cpu::{Registers, CPU};
+use crate::disassembler::disassemble;
+use super::reference_test::{Reference, HEIGHT, MEMORY_SIZE, WIDTH};
+
+const CASES: u64 = 500;
+const STEPS: usize = 64;
+const PROGRAM_START: usize = 0x200;
+
+//A program and the state it starts from
+#[derive(Clone)]
+struct Case {
+    v: [u8; 16],
+    i: u16,
+    stack: Vec<u16>,
+    dt: u8,
+    st: u8,
+    keypad: [bool; 16],
+    memory: Vec<u8>,
+    display: [[bool; WIDTH]; HEIGHT],
+    program: Vec<u16>,
+}
+
+impl Case {
+    fn generate(rng: &mut StdRng) -> Self {
+        let length = rng.gen_range(8..=32);
+        let program = (0..length).map(|_| random_instruction(rng, length)).collect();
+        let mut keypad = [false; 16];
+        if rng.gen() {
+            keypad[rng.gen_range(0..16)] = true;
+        }
+        let mut display = [[false; WIDTH]; HEIGHT];
+        for pixel in display.iter_mut().flatten() {
+            *pixel = rng.gen_bool(0.25);
+        }
+        Case {
+            //Small values too, so key instructions and font lookups get exercised
+            v: std::array::from_fn(|_| if rng.gen() { rng.gen_range(0..16) } else { rng.gen() }),
+            i: rng.gen_range(0..0x1000),
+            stack: (0..rng.gen_range(0..4)).map(|_| program_address(rng, length)).collect(),
+            dt: rng.gen(),
+            st: rng.gen(),
+            keypad,
+            memory: (0..MEMORY_SIZE).map(|_| rng.gen()).collect(),
+            display,
+            program,
+        }
+    }
+
+    fn memory(&self) -> Vec<u8> {
+        let mut memory = self.memory.clone();
+        for (n, opcode) in self.program.iter().enumerate() {
+            memory[PROGRAM_START + n * 2..PROGRAM_START + n * 2 + 2].copy_from_slice(&opcode.to_be_bytes());
+        }
+        memory
+    }
+
+    fn cpu(&self) -> CPU {
+        let mut cpu = CPU::new();
+        cpu.write_memory(0, &self.memory());
+        cpu.set_registers(&Registers{v: self.v, i: self.i, pc: PROGRAM_START as u16, sp: self.stack.len() as u8, dt: self.dt, st: self.st});
+        cpu.stack[..self.stack.len()].copy_from_slice(&self.stack);
+        for (row, pixels) in cpu.vram.iter_mut().zip(self.display.iter()) {
+            for (pixel, &lit) in row.iter_mut().zip(pixels.iter()) {
+                *pixel = lit as u8;
+            }
+        }
+        cpu
+    }
+
+    fn reference(&self) -> Reference {
+        Reference {
+            v: self.v,
+            i: self.i,
+            pc: PROGRAM_START as u16,
+            stack: self.stack.clone(),
+            dt: self.dt,
+            st: self.st,
+            memory: self.memory(),
+            display: self.display,
+            keypad: self.keypad,
+        }
+    }
+}
+
+impl fmt::Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let registers: Vec<String> = self.v.iter().map(|value| format!("{:02X}", value)).collect();
+        writeln!(f, "  V0-VF {}", registers.join(" "))?;
+        writeln!(f, "  I={:03X} DT={:02X} ST={:02X} stack {:03X?}", self.i, self.dt, self.st, self.stack)?;
+        let keys: Vec<usize> = (0..16).filter(|&key| self.keypad[key]).collect();
+        writeln!(f, "  keys held {:X?}", keys)?;
+        writeln!(f, "  memory {}", if self.memory.iter().all(|&byte| byte == 0) { "zeroed" } else { "random" })?;
+        writeln!(f, "  display {}", if self.display.iter().flatten().any(|&lit| lit) { "random" } else { "clear" })?;
+        for (n, &opcode) in self.program.iter().enumerate() {
+            writeln!(f, "  {:03X}  {:04X}  {}", PROGRAM_START + n * 2, opcode, disassemble(opcode))?;
+        }
+        Ok(())
+    }
+}
+
+fn program_address(rng: &mut StdRng, length: usize) -> u16 {
+    (PROGRAM_START + rng.gen_range(0..length) * 2) as u16
+}
+
+//Only opcodes the reference defines, jumps stay inside the program
+fn random_instruction(rng: &mut StdRng, length: usize) -> u16 {
+    let x = rng.gen_range(0..16u16) << 8;
+    let y = rng.gen_range(0..16u16) << 4;
+    let kk = rng.gen::<u8>() as u16;
+    match rng.gen_range(0..34) {
+        0 => 0x00E0,
+        1 => 0x00EE,
+        2 => 0x1000 | program_address(rng, length),
+        3 => 0x2000 | program_address(rng, length),
+        4 => 0x3000 | x | kk,
+        5 => 0x4000 | x | kk,
+        6 => 0x5000 | x | y,
+        7 => 0x6000 | x | kk,
+        8 => 0x7000 | x | kk,
+        9..=17 => 0x8000 | x | y | [0x0, 0x1, 0x2, 0x3, 0x4, 0x5, 0x6, 0x7, 0xE][rng.gen_range(0..9)],
+        18 => 0x9000 | x | y,
+        19 => 0xA000 | rng.gen_range(0..0x1000),
+        20 => 0xB000 | program_address(rng, length),
+        21 => 0xC000 | x | kk,
+        22 | 23 => 0xD000 | x | y | rng.gen_range(0..16),
+        24 => 0xE09E | x,
+        25 => 0xE0A1 | x,
+        _ => 0xF000 | x | [0x07, 0x0A, 0x15, 0x18, 0x1E, 0x29, 0x33, 0x55, 0x65][rng.gen_range(0..9)],
+    }
+}
+
+struct Divergence {
+    step: usize,
+    pc: u16,
+    opcode: u16,
+    differences: Vec<String>,
+}
+
+//Runs a case until the reference stops, returning the first instruction after which the machines differ
+fn run(case: &Case) -> Result<usize, Divergence> {
+    let mut cpu = case.cpu();
+    let mut reference = case.reference();
+    for step in 0..STEPS {
+        let opcode = match reference.next() {
+            Ok(opcode) => opcode,
+            Err(_) => return Ok(step),
+        };
+        let pc = reference.pc;
+        cpu.step_instruction(&case.keypad);
+        //FX0A blocks until the next call sees the key held
+        if cpu.waiting_for_key_press.is_some() {
+            cpu.step_instruction(&case.keypad);
+        }
+        reference.execute(opcode, cpu.registers[(opcode >> 8 & 0xF) as usize]);
+        let differences = differences(&cpu, &reference);
+        if !differences.is_empty() {
+            return Err(Divergence{step, pc, opcode, differences});
+        }
+    }
+    Ok(STEPS)
+}
+
+fn differences(cpu: &CPU, reference: &Reference) -> Vec<String> {
+    let mut differences = Vec::new();
+    let mut compare = |name: String, expected: String, actual: String| {
+        if expected != actual {
+            differences.push(format!("{}: expected {}, got {}", name, expected, actual));
+        }
+    };
+    let registers = cpu.registers();
+    for x in 0..16 {
+        compare(format!("V{:X}", x), format!("{:02X}", reference.v[x]), format!("{:02X}", registers.v[x]));
+    }
+    compare(String::from("I"), format!("{:03X}", reference.i), format!("{:03X}", registers.i));
+    compare(String::from("PC"), format!("{:03X}", reference.pc), format!("{:03X}", registers.pc));
+    compare(String::from("stack"), format!("{:03X?}", reference.stack), format!("{:03X?}", cpu.stack()));
+    compare(String::from("DT"), format!("{:02X}", reference.dt), format!("{:02X}", registers.dt));
+    compare(String::from("ST"), format!("{:02X}", reference.st), format!("{:02X}", registers.st));
+    for (address, (expected, actual)) in reference.memory.iter().zip(cpu.memory().iter()).enumerate() {
+        if expected != actual {
+            compare(format!("memory {:03X}", address), format!("{:02X}", expected), format!("{:02X}", actual));
+        }
+    }
+    for (y, (expected, actual)) in reference.display.iter().zip(cpu.vram().iter()).enumerate() {
+        if expected.iter().zip(actual.iter()).any(|(&lit, &pixel)| lit != (pixel != 0)) {
+            let expected: String = expected.iter().map(|&lit| if lit { '#' } else { '.' }).collect();
+            let actual: String = actual.iter().map(|&pixel| if pixel != 0 { '#' } else { '.' }).collect();
+            compare(format!("display row {}", y), expected, actual);
+        }
+    }
+    differences
+}
+
+//Greedily drops instructions and simplifies the starting state while the case keeps failing
+fn minimize(mut case: Case, fails: impl Fn(&Case) -> bool) -> Case {
+    loop {
+        let mut candidates = Vec::new();
+        for n in (0..case.program.len()).rev() {
+            let mut candidate = case.clone();
+            candidate.program.remove(n);
+            candidates.push(candidate);
+        }
+        let mut candidate = case.clone();
+        candidate.memory = vec![0; MEMORY_SIZE];
+        candidates.push(candidate);
+        let mut candidate = case.clone();
+        candidate.display = [[false; WIDTH]; HEIGHT];
+        candidates.push(candidate);
+        let mut candidate = case.clone();
+        candidate.stack.clear();
+        candidates.push(candidate);
+        let mut candidate = case.clone();
+        candidate.keypad = [false; 16];
+        candidates.push(candidate);
+        for x in 0..16 {
+            let mut candidate = case.clone();
+            candidate.v[x] = 0;
+            candidates.push(candidate);
+        }
+        let mut candidate = case.clone();
+        candidate.i = 0;
+        candidates.push(candidate);
+        let mut candidate = case.clone();
+        (candidate.dt, candidate.st) = (0, 0);
+        candidates.push(candidate);
+
+        //Only simpler cases count, so this always ends
+        let simpler = candidates.into_iter().find(|candidate| complexity(candidate) < complexity(&case) && fails(candidate));
+        match simpler {
+            Some(candidate) => case = candidate,
+            None => return case,
+        }
+    }
+}
+
+fn complexity(case: &Case) -> usize {
+    case.program.len() + case.stack.len()
+        + case.v.iter().filter(|&&value| value != 0).count()
+        + (case.i != 0) as usize + (case.dt != 0) as usize + (case.st != 0) as usize
+        + case.keypad.contains(&true) as usize
+        + case.memory.iter().any(|&byte| byte != 0) as usize
+        + case.display.iter().flatten().any(|&lit| lit) as usize
+}
+
+fn report(seed: u64, case: &Case) -> String {
+    let case = minimize(case.clone(), |candidate| run(candidate).is_err());
+    let divergence = match run(&case) {
+        Err(divergence) => divergence,
+        Ok(_) => unreachable!(),
+    };
+    format!("CPU diverged from the reference on seed {} at step {}, {:03X}  {:04X}  {}\n{}\nReproducer:\n{}",
+        seed, divergence.step, divergence.pc, divergence.opcode, disassemble(divergence.opcode),
+        divergence.differences.iter().map(|difference| format!("  {}", difference)).collect::<Vec<String>>().join("\n"),
+        case)
+}
+
+#[test]
+fn test_cpu_matches_reference() {
+    let mut executed = 0;
+    for seed in 0..CASES {
+        let case = Case::generate(&mut StdRng::seed_from_u64(seed));
+        match run(&case) {
+            Ok(steps) => executed += steps,
+            Err(_) => panic!("{}", report(seed, &case)),
+        }
+    }
+    //Most programs should get somewhere before the reference stops them
+    assert!(executed > CASES as usize * 8, "only {} instructions executed", executed);
+}
+
+#[test]
+fn test_minimize() {
+    let case = Case::generate(&mut StdRng::seed_from_u64(1));
+    let mut failing = case.clone();
+    failing.program.insert(3, 0x8125);
+    let minimized = minimize(failing, |candidate| candidate.program.contains(&0x8125));
+    assert_eq!(minimized.program, vec![0x8125]);
+    assert_eq!(complexity(&minimized), 1);
+}
@@ -0,0 +1,69 @@
+//Turns opcodes into the mnemonics of Cowgod's CHIP-8 technical reference
+
+type OpCode = u16;
+
+pub fn disassemble(opcode: OpCode) -> String {
+    let x = (opcode & 0x0F00) >> 8;
+    let y = (opcode & 0x00F0) >> 4;
+    let n = opcode & 0x000F;
+    let nnn = opcode & 0x0FFF;
+    let kk = opcode & 0x00FF;
+
+    match ((opcode & 0xF000) >> 12, x, y, n) {
+        (0x0, 0x0, 0xE, 0x0) => String::from("CLS"),
+        (0x0, 0x0, 0xE, 0xE) => String::from("RET"),
+        (0x0, _, _, _) => format!("SYS 0x{:03X}", nnn),
+        (0x1, _, _, _) => format!("JP 0x{:03X}", nnn),
+        (0x2, _, _, _) => format!("CALL 0x{:03X}", nnn),
+        (0x3, _, _, _) => format!("SE V{:X}, 0x{:02X}", x, kk),
+        (0x4, _, _, _) => format!("SNE V{:X}, 0x{:02X}", x, kk),
+        (0x5, _, _, 0x0) => format!("SE V{:X}, V{:X}", x, y),
+        (0x6, _, _, _) => format!("LD V{:X}, 0x{:02X}", x, kk),
+        (0x7, _, _, _) => format!("ADD V{:X}, 0x{:02X}", x, kk),
+        (0x8, _, _, 0x0) => format!("LD V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x1) => format!("OR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x2) => format!("AND V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x3) => format!("XOR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x4) => format!("ADD V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x5) => format!("SUB V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x6) => format!("SHR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x7) => format!("SUBN V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0xE) => format!("SHL V{:X}, V{:X}", x, y),
+        (0x9, _, _, 0x0) => format!("SNE V{:X}, V{:X}", x, y),
+        (0xA, _, _, _) => format!("LD I, 0x{:03X}", nnn),
+        (0xB, _, _, _) => format!("JP V0, 0x{:03X}", nnn),
+        (0xC, _, _, _) => format!("RND V{:X}, 0x{:02X}", x, kk),
+        (0xD, _, _, _) => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        (0xE, _, 0x9, 0xE) => format!("SKP V{:X}", x),
+        (0xE, _, 0xA, 0x1) => format!("SKNP V{:X}", x),
+        (0xF, _, 0x0, 0x7) => format!("LD V{:X}, DT", x),
+        (0xF, _, 0x0, 0xA) => format!("LD V{:X}, K", x),
+        (0xF, _, 0x1, 0x5) => format!("LD DT, V{:X}", x),
+        (0xF, _, 0x1, 0x8) => format!("LD ST, V{:X}", x),
+        (0xF, _, 0x1, 0xE) => format!("ADD I, V{:X}", x),
+        (0xF, _, 0x2, 0x9) => format!("LD F, V{:X}", x),
+        (0xF, _, 0x3, 0x3) => format!("LD B, V{:X}", x),
+        (0xF, _, 0x5, 0x5) => format!("LD [I], V{:X}", x),
+        (0xF, _, 0x6, 0x5) => format!("LD V{:X}, [I]", x),
+        //Not an instruction, most likely sprite data
+        _ => format!("DW 0x{:04X}", opcode),
+    }
+}
+
+//Whether CPU can execute the opcode, which leaves out SYS calls into machine code
+pub fn is_instruction(opcode: OpCode) -> bool {
+    let n = opcode & 0x000F;
+    let kk = opcode & 0x00FF;
+    match (opcode & 0xF000) >> 12 {
+        0x0 => opcode == 0x00E0 || opcode == 0x00EE,
+        0x5 | 0x9 => n == 0,
+        0x8 => matches!(n, 0x0..=0x7 | 0xE),
+        0xE => matches!(kk, 0x9E | 0xA1),
+        0xF => matches!(kk, 0x07 | 0x0A | 0x15 | 0x18 | 0x1E | 0x29 | 0x33 | 0x55 | 0x65),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+#[path = "./disassembler_test.rs"]
+mod disassembler_test;
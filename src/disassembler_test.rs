@@ -0,0 +1,38 @@
+use super::{disassemble, is_instruction};
+
+#[test]
+fn test_disassemble() {
+    assert_eq!(disassemble(0x00E0), "CLS");
+    assert_eq!(disassemble(0x00EE), "RET");
+    assert_eq!(disassemble(0x1234), "JP 0x234");
+    assert_eq!(disassemble(0x2ABC), "CALL 0xABC");
+    assert_eq!(disassemble(0x3A2F), "SE VA, 0x2F");
+    assert_eq!(disassemble(0x8124), "ADD V1, V2");
+    assert_eq!(disassemble(0x812E), "SHL V1, V2");
+    assert_eq!(disassemble(0xA2F0), "LD I, 0x2F0");
+    assert_eq!(disassemble(0xB300), "JP V0, 0x300");
+    assert_eq!(disassemble(0xD015), "DRW V0, V1, 5");
+    assert_eq!(disassemble(0xE59E), "SKP V5");
+    assert_eq!(disassemble(0xF30A), "LD V3, K");
+    assert_eq!(disassemble(0xF255), "LD [I], V2");
+    assert_eq!(disassemble(0xF265), "LD V2, [I]");
+}
+
+#[test]
+fn test_data_words() {
+    assert_eq!(disassemble(0x5121), "DW 0x5121");
+    assert_eq!(disassemble(0xE0FF), "DW 0xE0FF");
+    assert_eq!(disassemble(0xF0FF), "DW 0xF0FF");
+}
+
+#[test]
+fn test_is_instruction() {
+    assert!(is_instruction(0x00E0));
+    assert!(is_instruction(0x6A02));
+    assert!(is_instruction(0x812E));
+    assert!(!is_instruction(0x0123));
+    assert!(!is_instruction(0x5121));
+    assert!(!is_instruction(0x8128));
+    assert!(!is_instruction(0xE1A2));
+    assert!(!is_instruction(0xF1FF));
+}
@@ -0,0 +1,119 @@
+//Unpacks ROMs shipped in zip or gzip files. The zip reader only handles what ROM packs use:
+//stored and deflated entries, found through the central directory.
+use std::io::Read;
+
+use flate2::read::{DeflateDecoder, GzDecoder};
+use flate2::Crc;
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+const END_OF_DIRECTORY: u32 = 0x06054B50;
+const DIRECTORY_ENTRY: u32 = 0x02014B50;
+const LOCAL_HEADER: u32 = 0x04034B50;
+const END_OF_DIRECTORY_SIZE: usize = 22;
+const DIRECTORY_ENTRY_SIZE: usize = 46;
+const LOCAL_HEADER_SIZE: usize = 30;
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+pub fn is_zip(data: &[u8]) -> bool {
+    data.starts_with(ZIP_MAGIC)
+}
+
+pub fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(GZIP_MAGIC)
+}
+
+//Stops one byte past limit so an oversized file is caught without inflating all of it
+pub fn gunzip(data: &[u8], limit: usize) -> Result<Vec<u8>, String> {
+    let mut unpacked = Vec::new();
+    GzDecoder::new(data).take(limit as u64 + 1).read_to_end(&mut unpacked).map_err(|error| error.to_string())?;
+    Ok(unpacked)
+}
+
+pub struct ZipEntry {
+    pub name: String,
+    method: u16,
+    crc: u32,
+    compressed_size: usize,
+    pub size: usize,
+    offset: usize,
+}
+
+pub struct Zip<'a> {
+    data: &'a [u8],
+    pub entries: Vec<ZipEntry>,
+}
+
+impl<'a> Zip<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self, String> {
+        //The end record sits at the very end, before a comment of up to 64 KiB
+        let end = (0..=data.len().saturating_sub(END_OF_DIRECTORY_SIZE)).rev()
+            .take(u16::MAX as usize + 1)
+            .find(|&offset| read_u32(data, offset) == Some(END_OF_DIRECTORY))
+            .ok_or("The zip file has no central directory")?;
+        let count = read_u16(data, end + 10).ok_or("The zip file is truncated")?;
+        let mut offset = read_u32(data, end + 16).ok_or("The zip file is truncated")? as usize;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let field = |at: usize| read_u32(data, offset + at).ok_or("The zip file is truncated");
+            let short = |at: usize| read_u16(data, offset + at).ok_or("The zip file is truncated");
+            if field(0)? != DIRECTORY_ENTRY {
+                return Err(String::from("The zip file has a damaged central directory"));
+            }
+            let name_length = short(28)? as usize;
+            let name = data.get(offset + DIRECTORY_ENTRY_SIZE..offset + DIRECTORY_ENTRY_SIZE + name_length).ok_or("The zip file is truncated")?;
+            let entry = ZipEntry {
+                name: String::from_utf8_lossy(name).into_owned(),
+                method: short(10)?,
+                crc: field(16)?,
+                compressed_size: field(20)? as usize,
+                size: field(24)? as usize,
+                offset: field(42)? as usize,
+            };
+            offset += DIRECTORY_ENTRY_SIZE + name_length + short(30)? as usize + short(32)? as usize;
+            if !entry.name.ends_with('/') {
+                entries.push(entry);
+            }
+        }
+        Ok(Zip{data, entries})
+    }
+
+    //Inflates at most limit bytes, whatever size the entry claims
+    pub fn read(&self, entry: &ZipEntry, limit: usize) -> Result<Vec<u8>, String> {
+        let header = entry.offset;
+        if read_u32(self.data, header) != Some(LOCAL_HEADER) {
+            return Err(format!("{} has a damaged header", entry.name));
+        }
+        let start = header + LOCAL_HEADER_SIZE
+            + read_u16(self.data, header + 26).unwrap_or(0) as usize
+            + read_u16(self.data, header + 28).unwrap_or(0) as usize;
+        let compressed = self.data.get(start..start + entry.compressed_size).ok_or(format!("{} is truncated", entry.name))?;
+        let mut unpacked = Vec::new();
+        match entry.method {
+            STORED => unpacked.extend(compressed),
+            DEFLATED => {
+                DeflateDecoder::new(compressed).take(limit as u64 + 1).read_to_end(&mut unpacked).map_err(|error| format!("{}: {}", entry.name, error))?;
+            }
+            method => return Err(format!("{} uses compression method {}, only stored and deflated are supported", entry.name, method)),
+        }
+        let mut crc = Crc::new();
+        crc.update(&unpacked);
+        if unpacked.len() != entry.size || crc.sum() != entry.crc {
+            return Err(format!("{} is corrupt", entry.name));
+        }
+        Ok(unpacked)
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+#[path = "./archive_test.rs"]
+mod archive_test;
@@ -0,0 +1,90 @@
+use std::io::Write;
+
+use flate2::write::{DeflateEncoder, GzEncoder};
+use flate2::{Compression, Crc};
+
+use super::{gunzip, is_gzip, is_zip, Zip};
+
+//Builds a zip of (name, data, deflate) entries
+fn zip(files: &[(&str, &[u8], bool)]) -> Vec<u8> {
+    let mut data = Vec::new();
+    let mut directory = Vec::new();
+    for &(name, contents, deflate) in files {
+        let packed = match deflate {
+            true => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(contents).unwrap();
+                encoder.finish().unwrap()
+            }
+            false => contents.to_vec(),
+        };
+        let mut crc = Crc::new();
+        crc.update(contents);
+        let method: u16 = if deflate { 8 } else { 0 };
+        let offset = data.len() as u32;
+        data.extend(0x04034B50u32.to_le_bytes());
+        data.extend([20, 0, 0, 0]);
+        data.extend(method.to_le_bytes());
+        data.extend([0; 4]);
+        data.extend(crc.sum().to_le_bytes());
+        data.extend((packed.len() as u32).to_le_bytes());
+        data.extend((contents.len() as u32).to_le_bytes());
+        data.extend((name.len() as u16).to_le_bytes());
+        data.extend([0, 0]);
+        data.extend(name.as_bytes());
+        data.extend(&packed);
+        directory.extend(0x02014B50u32.to_le_bytes());
+        directory.extend([20, 0, 20, 0, 0, 0]);
+        directory.extend(method.to_le_bytes());
+        directory.extend([0; 4]);
+        directory.extend(crc.sum().to_le_bytes());
+        directory.extend((packed.len() as u32).to_le_bytes());
+        directory.extend((contents.len() as u32).to_le_bytes());
+        directory.extend((name.len() as u16).to_le_bytes());
+        directory.extend([0; 12]);
+        directory.extend(offset.to_le_bytes());
+        directory.extend(name.as_bytes());
+    }
+    let start = data.len() as u32;
+    data.extend(&directory);
+    data.extend(0x06054B50u32.to_le_bytes());
+    data.extend([0; 4]);
+    data.extend((files.len() as u16).to_le_bytes());
+    data.extend((files.len() as u16).to_le_bytes());
+    data.extend((directory.len() as u32).to_le_bytes());
+    data.extend(start.to_le_bytes());
+    data.extend([0, 0]);
+    data
+}
+
+#[test]
+fn test_zip() {
+    let rom: Vec<u8> = (0..200).map(|n| (n % 7) as u8).collect();
+    let data = zip(&[("games/", &[], false), ("games/pong.ch8", &rom, true), ("readme.txt", b"Pong", false)]);
+    assert!(is_zip(&data));
+    let zip = Zip::new(&data).unwrap();
+    let names: Vec<&str> = zip.entries.iter().map(|entry| entry.name.as_str()).collect();
+    assert_eq!(names, vec!["games/pong.ch8", "readme.txt"]);
+    assert_eq!(zip.read(&zip.entries[0], 3584).unwrap(), rom);
+    assert_eq!(zip.read(&zip.entries[1], 3584).unwrap(), b"Pong");
+    assert_eq!(zip.read(&zip.entries[0], 100).err(), Some(String::from("games/pong.ch8 is corrupt")));
+}
+
+#[test]
+fn test_damaged_zip() {
+    let mut data = zip(&[("pong.ch8", b"\x00\xE0", false)]);
+    data[30 + 8] ^= 0xFF;
+    let zip = Zip::new(&data).unwrap();
+    assert_eq!(zip.read(&zip.entries[0], 3584).err(), Some(String::from("pong.ch8 is corrupt")));
+    assert!(Zip::new(b"PK\x03\x04").is_err());
+}
+
+#[test]
+fn test_gzip() {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&[0x00, 0xE0, 0x12, 0x00]).unwrap();
+    let data = encoder.finish().unwrap();
+    assert!(is_gzip(&data) && !is_zip(&data));
+    assert_eq!(gunzip(&data, 3584).unwrap(), vec![0x00, 0xE0, 0x12, 0x00]);
+    assert_eq!(gunzip(&data, 2).unwrap().len(), 3);
+}
@@ -1,58 +1,55 @@
-use sdl2;
 use sdl2::audio::{AudioDevice, AudioCallback, AudioSpecDesired};
 
+use crate::tone::{Tone, ToneSettings};
+use super::AudioSink;
+
 pub struct Audio {
-    device: AudioDevice<SquareWave>,
+    device: AudioDevice<Buzzer>,
+    beeping: bool,
 }
 
 impl Audio {
-    pub fn new(sdl_context: &sdl2::Sdl) -> Self {
+    pub fn new(sdl_context: &sdl2::Sdl, settings: ToneSettings) -> Self {
         let audio_subsystem = sdl_context.audio().unwrap();
 
         let desired_spec = AudioSpecDesired {
-            freq: Some(44100),
+            freq: Some(settings.sample_rate as i32),
             channels: Some(1), //Mono
             samples: None, //Default sample size
         };
 
         let device = audio_subsystem.open_playback(None, &desired_spec, |spec| {
-            println!("{:?}", spec);
-
-            SquareWave {
-                phase_increment: 240.0 / spec.freq as f32,
-                phase: 0.0,
-                volume: 0.25,
-            }
+            //The device may not support the rate that was asked for
+            Buzzer{tone: Tone::new(ToneSettings{sample_rate: spec.freq as u32, ..settings})}
         }).unwrap();
+        //The device keeps playing silence between beeps so the tone can fade in and out
+        device.resume();
 
-        Audio{device}
+        Audio{device, beeping: false}
     }
+}
 
-    pub fn start_beep(&self) {
-        self.device.resume();
+impl AudioSink for Audio {
+    fn set_beep(&mut self, on: bool) {
+        if on != self.beeping {
+            self.device.lock().tone.set_on(on);
+            self.beeping = on;
+        }
     }
 
-    pub fn stop_beep(&self) {
-        self.device.pause();
+    fn set_muted(&mut self, muted: bool) {
+        self.device.lock().tone.set_muted(muted);
     }
 }
 
-
-
-struct SquareWave {
-    phase_increment: f32,
-    phase: f32,
-    volume: f32,
+struct Buzzer {
+    tone: Tone,
 }
 
-impl AudioCallback for SquareWave {
+impl AudioCallback for Buzzer {
     type Channel = f32;
 
     fn callback(&mut self, out: &mut[f32]) {
-        //Generate a square wave
-        for x in out.iter_mut() {
-            *x = self.volume * if self.phase < 0.5 {1.0} else {-1.0};
-            self.phase = (self.phase + self.phase_increment) % 1.0;
-        }
+        self.tone.fill(out);
     }
-}
\ No newline at end of file
+}
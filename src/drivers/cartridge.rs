@@ -0,0 +1,96 @@
+//Octo saves programs as cartridges: GIF images of a label with the program hidden in the pixels.
+//The low nibble of each pixel's palette index carries four bits of payload, two pixels a byte with
+//the high nibble first, through every frame in turn. The payload is a 32 bit big-endian length and
+//then that many bytes of JSON: {"options": {...}, "program": "Octo source"}.
+use serde_json::Value;
+
+use crate::palette::Palette;
+
+const MAGIC: &[u8] = b"GIF8";
+const LENGTH_SIZE: usize = 4;
+//Octo's fastest speed. A larger tickrate would only stall the frame loop.
+const MAX_TICKRATE: f64 = 1000.0;
+
+//Octo quirk options, with the setting matching what CPU does
+const QUIRKS: [(&str, bool); 7] = [
+    ("shiftQuirks", true),
+    ("loadStoreQuirks", true),
+    ("jumpQuirks", false),
+    ("logicQuirks", false),
+    ("clipQuirks", false),
+    ("vfOrderQuirks", false),
+    //Only with --timing vip
+    ("vBlankQuirks", false),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CartridgeOptions {
+    //Instructions per 60 Hz frame
+    pub tickrate: Option<u32>,
+    pub palette: Option<Palette>,
+    //Quirk options that were set, by their Octo names
+    pub quirks: Vec<(String, bool)>,
+}
+
+impl CartridgeOptions {
+    fn from(options: &Value) -> Self {
+        let color = |name: &str| options.get(name).and_then(Value::as_str);
+        let colors: Vec<&str> = ["backgroundColor", "fillColor", "fillColor2", "blendColor"].iter().map_while(|name| color(name)).collect();
+        let palette = match colors.len() {
+            4 => Palette::parse(&format!("cartridge={}", colors.join(" "))),
+            2 | 3 => Palette::parse(&format!("cartridge={}", colors[..2].join(" "))),
+            _ => None,
+        };
+        CartridgeOptions {
+            tickrate: options.get("tickrate").and_then(Value::as_f64).map(|tickrate| tickrate.clamp(1.0, MAX_TICKRATE) as u32),
+            palette,
+            quirks: QUIRKS.iter().filter_map(|&(name, _)| Some((name.to_string(), options.get(name)?.as_bool()?))).collect(),
+        }
+    }
+
+    //Quirks the cartridge asks for that CPU does not behave as
+    pub fn unsupported_quirks(&self) -> Vec<String> {
+        self.quirks.iter()
+            .filter(|(name, value)| QUIRKS.iter().any(|(quirk, supported)| quirk == name && supported != value))
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect()
+    }
+}
+
+pub struct Cartridge {
+    pub source: String,
+    pub options: CartridgeOptions,
+}
+
+pub fn is_cartridge(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub fn decode(data: &[u8]) -> Result<Cartridge, String> {
+    let payload = payload(data)?;
+    let length = u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]) as usize;
+    let json = payload.get(LENGTH_SIZE..LENGTH_SIZE + length).ok_or("The cartridge is shorter than its payload")?;
+    //serde_json gives up on nesting deeper than 128, so a hostile payload cannot overflow the stack
+    let json: Value = serde_json::from_slice(json).map_err(|_| "The cartridge payload is not JSON")?;
+    let source = json.get("program").and_then(Value::as_str).ok_or("The cartridge has no program")?;
+    Ok(Cartridge{source: source.to_string(), options: CartridgeOptions::from(json.get("options").unwrap_or(&Value::Null))})
+}
+
+fn payload(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(data).map_err(|error| error.to_string())?;
+    let mut nibbles = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().map_err(|error| error.to_string())? {
+        nibbles.extend(frame.buffer.iter().map(|&index| index & 0xF));
+    }
+    let payload: Vec<u8> = nibbles.chunks_exact(2).map(|pair| pair[0] << 4 | pair[1]).collect();
+    if payload.len() < LENGTH_SIZE {
+        return Err(String::from("The image is too small to be a cartridge"));
+    }
+    Ok(payload)
+}
+
+#[cfg(test)]
+#[path = "./cartridge_test.rs"]
+mod cartridge_test;
@@ -0,0 +1,61 @@
+use super::{decode, is_cartridge};
+use crate::palette::{Palette, Rgb};
+
+//Hides the payload in a two frame GIF, with a label colour in the high nibble as Octo does
+fn cartridge(json: &str) -> Vec<u8> {
+    let mut payload = (json.len() as u32).to_be_bytes().to_vec();
+    payload.extend(json.as_bytes());
+    let mut pixels: Vec<u8> = payload.iter().flat_map(|byte| [0x10 | byte >> 4, 0x10 | byte & 0xF]).collect();
+    let (width, height) = (16, pixels.len().div_ceil(32) as u16);
+    pixels.resize(width as usize * height as usize * 2, 0);
+    let palette: Vec<u8> = (0..=255).flat_map(|n| [n, n, n]).collect();
+    let mut gif = Vec::new();
+    {
+        let mut encoder = gif::Encoder::new(&mut gif, width, height, &palette).unwrap();
+        for frame in pixels.chunks(width as usize * height as usize) {
+            encoder.write_frame(&gif::Frame::from_indexed_pixels(width, height, frame, None)).unwrap();
+        }
+    }
+    gif
+}
+
+#[test]
+fn test_decode() {
+    let data = cartridge(r##"{"options": {"tickrate": 500, "fillColor": "#FF0000", "backgroundColor": "#000000", "shiftQuirks": true, "clipQuirks": true}, "program": ": main\n\tclear"}"##);
+    assert!(is_cartridge(&data));
+    let cartridge = decode(&data).unwrap();
+    assert_eq!(cartridge.source, ": main\n\tclear");
+    assert_eq!(cartridge.options.tickrate, Some(500));
+    assert_eq!(cartridge.options.palette, Some(Palette::new("cartridge", &[Rgb(0, 0, 0), Rgb(255, 0, 0)])));
+    assert_eq!(cartridge.options.unsupported_quirks(), vec![String::from("clipQuirks=true")]);
+}
+
+//The payload as Octo exports it, with every option it saves and its default colours
+#[test]
+fn test_decode_octo_export() {
+    let json = concat!(r##"{"key":"","program":": main\n\tloop\n\t\tv0 += 1\n\tagain\n","options":{"tickrate":20,"##,
+        r##""fillColor":"#FFCC00","fillColor2":"#FF6600","blendColor":"#662200","backgroundColor":"#996600","##,
+        r##""buzzColor":"#FFAA00","quietColor":"#000000","shiftQuirks":false,"loadStoreQuirks":false,"##,
+        r##""vfOrderQuirks":false,"clipQuirks":false,"vBlankQuirks":false,"jumpQuirks":false,"logicQuirks":true,"##,
+        r##""screenRotation":0,"maxSize":3215,"touchInputMode":"none","fontStyle":"octo"}}"##);
+    let cartridge = decode(&cartridge(json)).unwrap();
+    assert_eq!(crate::octo::assemble(&cartridge.source).unwrap(), vec![0x12, 0x02, 0x70, 0x01, 0x12, 0x02]);
+    assert_eq!(cartridge.options.tickrate, Some(20));
+    let colors = [Rgb(0x99, 0x66, 0x00), Rgb(0xFF, 0xCC, 0x00), Rgb(0xFF, 0x66, 0x00), Rgb(0x66, 0x22, 0x00)];
+    assert_eq!(cartridge.options.palette, Some(Palette::new("cartridge", &colors)));
+    assert_eq!(cartridge.options.unsupported_quirks(),
+        vec![String::from("shiftQuirks=false"), String::from("loadStoreQuirks=false"), String::from("logicQuirks=true")]);
+}
+
+#[test]
+fn test_invalid() {
+    assert!(!is_cartridge(&[0x12, 0x00]));
+    assert_eq!(decode(&cartridge("{\"options\": {}}")).err(), Some(String::from("The cartridge has no program")));
+    assert_eq!(decode(&cartridge("not json")).err(), Some(String::from("The cartridge payload is not JSON")));
+    assert!(decode(b"GIF89a").is_err());
+    let tickrate = |json: &str| decode(&cartridge(json)).unwrap().options.tickrate;
+    assert_eq!(tickrate(r#"{"options": {"tickrate": 1e12}, "program": ""}"#), Some(1000));
+    assert_eq!(tickrate(r#"{"options": {"tickrate": 0}, "program": ""}"#), Some(1));
+    let nested = format!("{{\"program\": {}}}", "[".repeat(100_000));
+    assert_eq!(decode(&cartridge(&nested)).err(), Some(String::from("The cartridge payload is not JSON")));
+}
@@ -1,15 +1,46 @@
-use sdl2;
-use sdl2::render::Canvas;
+use sdl2::render::{Canvas, Texture};
 use sdl2::video::Window;
 use sdl2::pixels;
+use sdl2::pixels::PixelFormatEnum;
 use sdl2::rect::Rect;
 
-use crate::{DISPLAY_WIDTH, DISPLAY_HEIGHT, VRAM};
+use crate::{DISPLAY_WIDTH, DISPLAY_HEIGHT, VRAM, DirtyRows};
+use crate::palette::Palette;
+use super::font::{Image, CELL_WIDTH, GLYPH_HEIGHT};
+use super::memory_view::MemoryWindow;
+use super::{MemoryKey, MemoryState, VideoSink};
+
+const BYTES_PER_PIXEL: usize = 3;
+const WINDOW_TITLE: &str = "Chip 8 Emulator";
+//Refresh rates close enough to the 60 Hz CHIP-8 frame rate to pace emulation by
+const SYNCED_REFRESH_RATES: std::ops::RangeInclusive<i32> = 59..=61;
 
 pub struct Display {
-    displayCanvas: Canvas<Window>,
-    keypadCanvas: Canvas<Window>,
-    scale: usize,
+    display_canvas: Canvas<Window>,
+    keypad_canvas: Canvas<Window>,
+    //Streaming copy of VRAM that SDL scales to the window
+    texture: Texture,
+    scaling: Scaling,
+    palette: Palette,
+    memory_window: Option<MemoryWindow>,
+    //Shown in place of the display by show_image, with its size
+    image: Option<(Texture, (usize, usize))>,
+    //Drawn over the top left of the display, with its size
+    notice: Option<(Texture, (usize, usize))>,
+    //Speed and rates drawn over the bottom left of the display, with its size
+    status: Option<(Texture, (usize, usize))>,
+    //Presents wait for the display refresh, so draws are only shown by present
+    vsync: bool,
+    //The status changed since the window was last presented
+    status_changed: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Scaling {
+    //Largest whole multiple of the display size that fits the window
+    Integer,
+    //Fill the window as far as the aspect ratio allows
+    Fit,
 }
 
 pub enum WindowType {
@@ -18,13 +49,21 @@ pub enum WindowType {
 }
 
 impl Display {
-    pub fn from(sdl_context: &sdl2::Sdl, scale: usize) -> Self {
+    pub fn from(sdl_context: &sdl2::Sdl, scale: usize, scaling: Scaling) -> Self {
         let video_subsystem = sdl_context.video().unwrap();
-        let display_window = video_subsystem.window("Chip 8 Emulator", (DISPLAY_WIDTH * scale) as u32, (DISPLAY_HEIGHT * scale)as u32).position_centered().opengl().build().unwrap();
-        let mut display_canvas = display_window.into_canvas().build().unwrap();
+        let display_window = video_subsystem.window(WINDOW_TITLE, (DISPLAY_WIDTH * scale) as u32, (DISPLAY_HEIGHT * scale)as u32).position_centered().resizable().opengl().build().unwrap();
+        let vsync = display_window.display_mode().is_ok_and(|mode| SYNCED_REFRESH_RATES.contains(&mode.refresh_rate));
+        let canvas_builder = display_window.into_canvas();
+        let canvas_builder = if vsync { canvas_builder.present_vsync() } else { canvas_builder };
+        let mut display_canvas = canvas_builder.build().unwrap();
         display_canvas.set_draw_color(pixels::Color::RGB(0, 0, 0));
         display_canvas.clear();
         display_canvas.present();
+        let mut texture = display_canvas.texture_creator()
+            .create_texture_streaming(PixelFormatEnum::RGB24, DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32)
+            .unwrap();
+        let blank = [0u8; DISPLAY_WIDTH * DISPLAY_HEIGHT * BYTES_PER_PIXEL];
+        let _ = texture.update(None, &blank, DISPLAY_WIDTH * BYTES_PER_PIXEL);
 
         let keypad_window = video_subsystem.window("Keypad", 32 * 5, 32 * 5).position(0, 50).opengl().build().unwrap();
         let mut keypad_canvas = keypad_window.into_canvas().build().unwrap();
@@ -34,31 +73,185 @@ impl Display {
         let _ = keypad_canvas.draw_rect(Rect::new(4, 4, 10, 10));
         keypad_canvas.present();
 
-        Display{ displayCanvas: display_canvas, keypadCanvas: keypad_canvas, scale}
+        Display{ display_canvas, keypad_canvas, texture, scaling, palette: Palette::builtin().remove(0), memory_window: None, image: None, notice: None,
+            status: None, vsync, status_changed: false}
     }
 
-    pub fn draw(&mut self, pixels: &VRAM) {
-        for (y, row) in pixels.iter().enumerate() {
-            for (x, &col) in row.iter().enumerate() {
-                let x = x * self.scale;
-                let y = y * self.scale;
+    pub fn get_window_id(&self, window_type: WindowType) -> u32 {
+        match window_type {
+            WindowType::Display => self.display_canvas.window().id(),
+            WindowType::Keypad => self.keypad_canvas.window().id(),
+        }
+    }
 
-                self.displayCanvas.set_draw_color(color(col));
-                let _ = self.displayCanvas.fill_rect(Rect::new(x as i32, y as i32, self.scale as u32, self.scale as u32));
+    pub fn open_memory_window(&mut self, sdl_context: &sdl2::Sdl) {
+        self.memory_window = Some(MemoryWindow::open(sdl_context));
+    }
+
+    pub fn memory_window_id(&self) -> Option<u32> {
+        self.memory_window.as_ref().map(|window| window.id())
+    }
+
+    //Shows RGB pixels in place of the display until the next draw, fitted to the window.
+    //The image should have the shape of the display, as the launcher screen does.
+    pub fn show_image(&mut self, pixels: &[u8], width: usize, height: usize) {
+        if self.image.as_ref().is_none_or(|(_, size)| *size != (width, height)) {
+            let texture = self.display_canvas.texture_creator()
+                .create_texture_streaming(PixelFormatEnum::RGB24, width as u32, height as u32)
+                .unwrap();
+            self.image = Some((texture, (width, height)));
+        }
+        let Some((texture, _)) = self.image.as_mut() else {
+            return;
+        };
+        let _ = texture.update(None, pixels, width * BYTES_PER_PIXEL);
+        let (window_width, window_height) = self.display_canvas.output_size().unwrap_or((width as u32, height as u32));
+        self.display_canvas.set_draw_color(pixels::Color::RGB(0, 0, 0));
+        self.display_canvas.clear();
+        let _ = self.display_canvas.copy(texture, None, destination(window_width, window_height, Scaling::Fit));
+        self.display_canvas.present();
+    }
+
+    //Draws the display and the text over it, ready to present
+    fn render(&mut self) {
+        let (width, height) = self.display_canvas.output_size().unwrap_or((DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32));
+        let background = self.palette.background();
+        self.display_canvas.set_draw_color(pixels::Color::RGB(background.0, background.1, background.2));
+        self.display_canvas.clear();
+        let destination = destination(width, height, self.scaling);
+        let _ = self.display_canvas.copy(&self.texture, None, destination);
+        //Text is half the scale of the display, so a line of it fits across
+        let scale = (destination.height() / DISPLAY_HEIGHT as u32 / 2).max(1);
+        if let Some((notice, (notice_width, notice_height))) = self.notice.as_ref() {
+            let rect = Rect::new(destination.x(), destination.y(), *notice_width as u32 * scale, *notice_height as u32 * scale);
+            let _ = self.display_canvas.copy(notice, None, rect);
+        }
+        if let Some((status, (status_width, status_height))) = self.status.as_ref() {
+            let y = destination.bottom() - (*status_height as u32 * scale) as i32;
+            let rect = Rect::new(destination.x(), y, *status_width as u32 * scale, *status_height as u32 * scale);
+            let _ = self.display_canvas.copy(status, None, rect);
+        }
+    }
+
+}
+
+impl VideoSink for Display {
+    fn draw(&mut self, pixels: &VRAM, dirty_rows: DirtyRows) {
+        for (first, last) in row_spans(dirty_rows) {
+            let mut data = Vec::with_capacity((last - first + 1) * DISPLAY_WIDTH * BYTES_PER_PIXEL);
+            for row in &pixels[first..=last] {
+                for &shade in row.iter() {
+                    let color = self.palette.shade(shade);
+                    data.extend_from_slice(&[color.0, color.1, color.2]);
+                }
             }
+            let rect = Rect::new(0, first as i32, DISPLAY_WIDTH as u32, (last - first + 1) as u32);
+            let _ = self.texture.update(rect, &data, DISPLAY_WIDTH * BYTES_PER_PIXEL);
+        }
+
+        if !self.vsync {
+            self.render();
+            self.display_canvas.present();
+            self.status_changed = false;
         }
-        self.displayCanvas.present();
     }
 
-    pub fn get_window_id(&self, window_type: WindowType) -> u32 {
-        match window_type {
-            WindowType::Display => self.displayCanvas.window().id(),
-            WindowType::Keypad => self.keypadCanvas.window().id(),
+    fn set_palette(&mut self, palette: &Palette) {
+        self.palette = palette.clone();
+    }
+
+    //In the window title, and over the display from the next draw or present
+    fn set_status(&mut self, status: &str) {
+        let _ = self.display_canvas.window_mut().set_title(&format!("{} - {}", WINDOW_TITLE, status));
+        text_texture(&self.display_canvas, &mut self.status, status);
+        self.status_changed = true;
+    }
+
+    fn show_notice(&mut self, notice: Option<&str>) {
+        match notice {
+            Some(text) => text_texture(&self.display_canvas, &mut self.notice, text),
+            None => self.notice = None,
+        }
+    }
+
+    //Without vsync draws are already shown, so only a changed status needs presenting, as when paused
+    fn present(&mut self) -> bool {
+        if self.vsync || self.status_changed {
+            self.render();
+            self.display_canvas.present();
+            self.status_changed = false;
+        }
+        self.vsync
+    }
+
+    fn show_memory(&mut self, state: &MemoryState) {
+        if let Some(window) = self.memory_window.as_mut() {
+            window.show(state);
+        }
+    }
+
+    fn edit_memory(&mut self, key: MemoryKey, state: &MemoryState) -> Option<(usize, u8)> {
+        self.memory_window.as_mut()?.key(key, state)
+    }
+}
+
+//Writes white text on black into the texture, replacing it only when the text changes size, as textures
+//are kept until the canvas goes
+fn text_texture(canvas: &Canvas<Window>, texture: &mut Option<(Texture, (usize, usize))>, text: &str) {
+    let (width, height) = (text.chars().count() * CELL_WIDTH + 1, GLYPH_HEIGHT + 2);
+    if texture.as_ref().is_none_or(|(_, size)| *size != (width, height)) {
+        let created = canvas.texture_creator()
+            .create_texture_streaming(PixelFormatEnum::RGB24, width as u32, height as u32)
+            .unwrap();
+        *texture = Some((created, (width, height)));
+    }
+    let Some((texture, _)) = texture.as_mut() else {
+        return;
+    };
+    let mut image = Image::new(width, height, (0, 0, 0));
+    image.text(1, 0, text, (255, 255, 255));
+    let _ = texture.update(None, &image.pixels, width * BYTES_PER_PIXEL);
+}
+
+//Consecutive dirty rows are uploaded together, as (first, last) pairs
+fn row_spans(dirty_rows: DirtyRows) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    for y in 0..=DISPLAY_HEIGHT {
+        let dirty = y < DISPLAY_HEIGHT && dirty_rows & (1 << y) != 0;
+        match (start, dirty) {
+            (None, true) => start = Some(y),
+            (Some(first), false) => {
+                spans.push((first, y - 1));
+                start = None;
+            }
+            _ => {}
         }
     }
+    spans
+}
+
+//Where the display goes inside a window of the given size, centered
+fn destination(window_width: u32, window_height: u32, scaling: Scaling) -> Rect {
+    let (width, height) = match scaling {
+        Scaling::Integer => {
+            let scale = (window_width / DISPLAY_WIDTH as u32).min(window_height / DISPLAY_HEIGHT as u32).max(1);
+            (DISPLAY_WIDTH as u32 * scale, DISPLAY_HEIGHT as u32 * scale)
+        }
+        Scaling::Fit => {
+            if window_width * (DISPLAY_HEIGHT as u32) < window_height * (DISPLAY_WIDTH as u32) {
+                (window_width, window_width * DISPLAY_HEIGHT as u32 / DISPLAY_WIDTH as u32)
+            } else {
+                (window_height * DISPLAY_WIDTH as u32 / DISPLAY_HEIGHT as u32, window_height)
+            }
+        }
+    };
+    //A window smaller than one pixel per CHIP-8 pixel shows the top left corner
+    let x = ((window_width as i32 - width as i32) / 2).max(0);
+    let y = ((window_height as i32 - height as i32) / 2).max(0);
+    Rect::new(x, y, width.max(1), height.max(1))
 }
 
-fn color(value: u8) -> pixels::Color {
-    if value == 0 {pixels::Color::RGB(0, 0, 0)}
-    else {pixels::Color::RGB(0, 250, 0)}
-}
\ No newline at end of file
+#[cfg(test)]
+#[path = "./display_test.rs"]
+mod display_test;
@@ -0,0 +1,25 @@
+use sdl2::rect::Rect;
+
+use crate::ALL_ROWS;
+use super::{destination, row_spans, Scaling};
+
+#[test]
+fn test_row_spans() {
+    assert_eq!(row_spans(0), vec![]);
+    assert_eq!(row_spans(0b1110_0110), vec![(1, 2), (5, 7)]);
+    assert_eq!(row_spans(ALL_ROWS), vec![(0, 31)]);
+}
+
+#[test]
+fn test_integer_scaling() {
+    assert_eq!(destination(640, 320, Scaling::Integer), Rect::new(0, 0, 640, 320));
+    assert_eq!(destination(700, 400, Scaling::Integer), Rect::new(30, 40, 640, 320));
+    assert_eq!(destination(10, 10, Scaling::Integer), Rect::new(0, 0, 64, 32));
+    assert_eq!(destination(100, 20, Scaling::Integer), Rect::new(18, 0, 64, 32));
+}
+
+#[test]
+fn test_fit_scaling() {
+    assert_eq!(destination(700, 400, Scaling::Fit), Rect::new(0, 25, 700, 350));
+    assert_eq!(destination(900, 400, Scaling::Fit), Rect::new(50, 0, 800, 400));
+}
@@ -0,0 +1,78 @@
+//3x5 pixel text and rectangles drawn into RGB images, for the views that are more than the display
+pub type Color = (u8, u8, u8);
+
+pub const GLYPH_WIDTH: usize = 3;
+pub const GLYPH_HEIGHT: usize = 5;
+//Glyphs are spaced a pixel apart
+pub const CELL_WIDTH: usize = GLYPH_WIDTH + 1;
+pub const BYTES_PER_PIXEL: usize = 3;
+
+//3x5 glyphs for ASCII 0x20 to 0x7E, three bits per row with the top row in the high bits
+const FONT: [u16; 95] = [
+    0b000_000_000_000_000, 0b010_010_010_000_010, 0b101_101_000_000_000, 0b101_111_101_111_101, // !"#
+    0b011_110_010_011_110, 0b101_001_010_100_101, 0b010_101_010_101_011, 0b010_010_000_000_000, //$%&'
+    0b001_010_010_010_001, 0b100_010_010_010_100, 0b000_101_010_101_000, 0b000_010_111_010_000, //()*+
+    0b000_000_000_010_100, 0b000_000_111_000_000, 0b000_000_000_000_010, 0b001_001_010_100_100, //,-./
+    0b111_101_101_101_111, 0b010_110_010_010_111, 0b111_001_111_100_111, 0b111_001_111_001_111, //0123
+    0b101_101_111_001_001, 0b111_100_111_001_111, 0b111_100_111_101_111, 0b111_001_010_100_100, //4567
+    0b111_101_111_101_111, 0b111_101_111_001_111, 0b000_010_000_010_000, 0b000_010_000_010_100, //89:;
+    0b001_010_100_010_001, 0b000_111_000_111_000, 0b100_010_001_010_100, 0b111_001_010_000_010, //<=>?
+    0b010_101_111_100_011, 0b010_101_111_101_101, 0b110_101_110_101_110, 0b011_100_100_100_011, //@ABC
+    0b110_101_101_101_110, 0b111_100_110_100_111, 0b111_100_110_100_100, 0b011_100_101_101_011, //DEFG
+    0b101_101_111_101_101, 0b111_010_010_010_111, 0b001_001_001_101_010, 0b101_101_110_101_101, //HIJK
+    0b100_100_100_100_111, 0b101_111_111_101_101, 0b110_101_101_101_101, 0b010_101_101_101_010, //LMNO
+    0b110_101_110_100_100, 0b010_101_101_110_011, 0b110_101_110_101_101, 0b011_100_010_001_110, //PQRS
+    0b111_010_010_010_010, 0b101_101_101_101_111, 0b101_101_101_101_010, 0b101_101_111_111_101, //TUVW
+    0b101_101_010_101_101, 0b101_101_010_010_010, 0b111_001_010_100_111, 0b110_100_100_100_110, //XYZ[
+    0b100_100_010_001_001, 0b011_001_001_001_011, 0b010_101_000_000_000, 0b000_000_000_000_111, //\]^_
+    0b100_010_000_000_000, 0b000_011_101_101_011, 0b100_110_101_101_110, 0b000_011_100_100_011, //`abc
+    0b001_011_101_101_011, 0b000_010_111_100_011, 0b001_010_111_010_010, 0b000_011_101_011_110, //defg
+    0b100_110_101_101_101, 0b010_000_010_010_010, 0b001_000_001_101_010, 0b100_101_110_110_101, //hijk
+    0b110_010_010_010_111, 0b000_111_111_111_101, 0b000_110_101_101_101, 0b000_010_101_101_010, //lmno
+    0b000_110_101_110_100, 0b000_011_101_011_001, 0b000_011_100_100_100, 0b000_011_110_011_110, //pqrs
+    0b010_111_010_010_011, 0b000_101_101_101_011, 0b000_101_101_111_010, 0b000_101_111_111_111, //tuvw
+    0b000_101_010_010_101, 0b000_101_011_001_110, 0b000_111_011_110_111, 0b011_010_110_010_011, //xyz{
+    0b010_010_010_010_010, 0b110_010_011_010_110, 0b000_001_111_100_000,                        //|}~
+];
+
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    //RGB, row by row
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, background: Color) -> Self {
+        let mut image = Image{width, height, pixels: vec![0; width * height * BYTES_PER_PIXEL]};
+        image.fill(0, 0, width, height, background);
+        image
+    }
+
+    //Clipped to the image
+    pub fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
+        for row in y..(y + height).min(self.height) {
+            for column in x..(x + width).min(self.width) {
+                let offset = (row * self.width + column) * BYTES_PER_PIXEL;
+                self.pixels[offset..offset + BYTES_PER_PIXEL].copy_from_slice(&[color.0, color.1, color.2]);
+            }
+        }
+    }
+
+    //Draws text from the top left corner of a cell, one glyph per cell
+    pub fn text(&mut self, x: usize, y: usize, text: &str, color: Color) {
+        for (n, character) in text.chars().enumerate() {
+            let glyph = match character as usize {
+                code @ 0x20..=0x7E => FONT[code - 0x20],
+                _ => FONT['?' as usize - 0x20],
+            };
+            for row in 0..GLYPH_HEIGHT {
+                for column in 0..GLYPH_WIDTH {
+                    if glyph & (1 << ((GLYPH_HEIGHT - 1 - row) * GLYPH_WIDTH + GLYPH_WIDTH - 1 - column)) != 0 {
+                        self.fill(x + n * CELL_WIDTH + column, y + 1 + row, 1, 1, color);
+                    }
+                }
+            }
+        }
+    }
+}
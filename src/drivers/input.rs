@@ -1,44 +1,144 @@
-use sdl2;
 use sdl2::event::Event;
 use sdl2::event::WindowEvent;
 use sdl2::keyboard::Keycode;
-use sdl2::Sdl;
+use sdl2::mouse::MouseButton;
+
+use super::{InputSource, LauncherKey, MemoryKey};
 
 pub struct Input {
     events: sdl2::EventPump,
     keypad_window_id: u32,
     display_window_id: u32,
+    memory_window_id: Option<u32>,
 }
 
 #[derive(PartialEq)]
 pub enum WindowAction{
     Close,
+    //The display window was resized or uncovered and has to be drawn again
+    Redraw,
+    NextPalette,
+    Screenshot,
+    ToggleRecording,
+    ToggleMute,
+    ToggleAudioCapture,
+    TogglePause,
+    //Runs a single frame, pausing first if needed
+    AdvanceFrame,
+    ToggleFastForward,
+    ToggleSlowMotion,
+    //Navigation or an edit in the memory viewer
+    Memory(MemoryKey),
+    //Navigation in the launcher, ignored while a ROM runs
+    Launcher(LauncherKey),
+    //Leaves the ROM for the launcher, when there is one
+    ShowLauncher,
 }
 
 impl Input {
     pub fn from(sdl_context: &sdl2::Sdl, keypad_window_id: u32, display_window_id: u32) -> Self {
-        Input{events:sdl_context.event_pump().unwrap(), keypad_window_id, display_window_id}
+        Input{events:sdl_context.event_pump().unwrap(), keypad_window_id, display_window_id, memory_window_id: None}
+    }
+
+    pub fn set_memory_window_id(&mut self, memory_window_id: Option<u32>) {
+        self.memory_window_id = memory_window_id;
+    }
+
+    fn handle_window_event(&self, window_id: u32, win_event: WindowEvent) -> Option<WindowAction> {
+        let own_window = window_id == self.display_window_id || window_id == self.keypad_window_id || Some(window_id) == self.memory_window_id;
+        match win_event {
+            WindowEvent::Close if own_window => Some(WindowAction::Close),
+            WindowEvent::SizeChanged(..) | WindowEvent::Exposed if window_id == self.display_window_id => Some(WindowAction::Redraw),
+            _ => None,
+        }
+    }
+}
+
+fn hotkey(keycode: Keycode) -> Option<WindowAction> {
+    match keycode {
+        Keycode::P => Some(WindowAction::NextPalette),
+        Keycode::F12 => Some(WindowAction::Screenshot),
+        Keycode::F9 => Some(WindowAction::ToggleRecording),
+        Keycode::M => Some(WindowAction::ToggleMute),
+        Keycode::F8 => Some(WindowAction::ToggleAudioCapture),
+        Keycode::F5 => Some(WindowAction::TogglePause),
+        Keycode::F6 => Some(WindowAction::AdvanceFrame),
+        Keycode::Tab => Some(WindowAction::ToggleFastForward),
+        Keycode::F7 => Some(WindowAction::ToggleSlowMotion),
+        Keycode::Escape => Some(WindowAction::ShowLauncher),
+        _ => None,
+    }
+}
+
+//Keys that move through the launcher, which may repeat
+fn launcher_key(keycode: Keycode) -> Option<LauncherKey> {
+    match keycode {
+        Keycode::Up => Some(LauncherKey::Up),
+        Keycode::Down => Some(LauncherKey::Down),
+        Keycode::PageUp => Some(LauncherKey::PageUp),
+        Keycode::PageDown => Some(LauncherKey::PageDown),
+        Keycode::Return | Keycode::KpEnter => Some(LauncherKey::Start),
+        _ => None,
+    }
+}
+
+//Keys typed into the memory viewer, which may repeat
+fn memory_key(keycode: Keycode) -> Option<MemoryKey> {
+    match keycode {
+        Keycode::Up => Some(MemoryKey::Up),
+        Keycode::Down => Some(MemoryKey::Down),
+        Keycode::Left => Some(MemoryKey::Left),
+        Keycode::Right => Some(MemoryKey::Right),
+        Keycode::PageUp => Some(MemoryKey::PageUp),
+        Keycode::PageDown => Some(MemoryKey::PageDown),
+        Keycode::RightBracket => Some(MemoryKey::TallerSprites),
+        Keycode::LeftBracket => Some(MemoryKey::ShorterSprites),
+        Keycode::Num0 => Some(MemoryKey::Digit(0x0)),
+        Keycode::Num1 => Some(MemoryKey::Digit(0x1)),
+        Keycode::Num2 => Some(MemoryKey::Digit(0x2)),
+        Keycode::Num3 => Some(MemoryKey::Digit(0x3)),
+        Keycode::Num4 => Some(MemoryKey::Digit(0x4)),
+        Keycode::Num5 => Some(MemoryKey::Digit(0x5)),
+        Keycode::Num6 => Some(MemoryKey::Digit(0x6)),
+        Keycode::Num7 => Some(MemoryKey::Digit(0x7)),
+        Keycode::Num8 => Some(MemoryKey::Digit(0x8)),
+        Keycode::Num9 => Some(MemoryKey::Digit(0x9)),
+        Keycode::A => Some(MemoryKey::Digit(0xA)),
+        Keycode::B => Some(MemoryKey::Digit(0xB)),
+        Keycode::C => Some(MemoryKey::Digit(0xC)),
+        Keycode::D => Some(MemoryKey::Digit(0xD)),
+        Keycode::E => Some(MemoryKey::Digit(0xE)),
+        Keycode::F => Some(MemoryKey::Digit(0xF)),
+        _ => None,
     }
+}
 
-    pub fn poll_window_events(&mut self) -> Option<WindowAction> {
-        for event in self.events.poll_iter() {
-            return match event {
+impl InputSource for Input {
+    fn poll_window_events(&mut self) -> Option<WindowAction> {
+        //Events that are not actions are skipped, so they cannot hold up the ones behind them
+        while let Some(event) = self.events.poll_event() {
+            let action = match event {
                 Event::Window { timestamp: _timestamp, window_id, win_event} => self.handle_window_event(window_id, win_event),
                 Event::Quit { .. } => { Some(WindowAction::Close) }
+                Event::KeyDown { keycode: Some(keycode), window_id, repeat, .. } if Some(window_id) == self.memory_window_id => {
+                    memory_key(keycode).map(WindowAction::Memory).or_else(|| if repeat { None } else { hotkey(keycode) })
+                }
+                Event::KeyDown { keycode: Some(keycode), repeat, .. } => {
+                    launcher_key(keycode).map(WindowAction::Launcher).or_else(|| if repeat { None } else { hotkey(keycode) })
+                }
+                Event::MouseButtonDown { window_id, mouse_btn: MouseButton::Left, x, y, .. } if Some(window_id) == self.memory_window_id => {
+                    Some(WindowAction::Memory(MemoryKey::Select(x, y)))
+                }
                 _ => { None }
+            };
+            if action.is_some() {
+                return action;
             }
         }
         None
     }
 
-    fn handle_window_event(&self, window_id: u32, win_event: WindowEvent) -> Option<WindowAction> {
-        if win_event == WindowEvent::Close {
-            return Some(WindowAction::Close);
-        }
-        None
-    }
-
-    pub fn poll(&mut self) -> [bool; 16] {
+    fn poll(&mut self) -> [bool; 16] {
         let keys: Vec<Keycode> = self.events.keyboard_state().pressed_scancodes().filter_map(Keycode::from_scancode).collect();
 
         let mut chip8_keys = [false; 16];
@@ -70,4 +170,4 @@ impl Input {
         }
         chip8_keys
     }
-}
\ No newline at end of file
+}
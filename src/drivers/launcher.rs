@@ -0,0 +1,150 @@
+//The library browser shown in the display window before a ROM is picked: titles on the left and a
+//live thumbnail of the selected ROM on the right, which starts from a few seconds of headless running.
+use std::thread;
+use std::time::Duration;
+
+use crate::library::{LibraryEntry, Thumbnail};
+use crate::palette::Palette;
+use crate::{DISPLAY_WIDTH, DISPLAY_HEIGHT};
+use super::font::{Color, Image, CELL_WIDTH, GLYPH_HEIGHT};
+use super::{Display, Input, InputSource, VideoSink, WindowAction};
+
+//Half the width of the default window, and the same shape as the display
+pub const WIDTH: usize = 160;
+pub const HEIGHT: usize = 80;
+const MARGIN: usize = 2;
+const ROW_HEIGHT: usize = GLYPH_HEIGHT + 2;
+const LIST_Y: usize = MARGIN + ROW_HEIGHT + 1;
+const VISIBLE_ROWS: usize = (HEIGHT - LIST_Y) / ROW_HEIGHT;
+const THUMBNAIL_X: usize = WIDTH - MARGIN - DISPLAY_WIDTH;
+const THUMBNAIL_Y: usize = LIST_Y;
+const TITLE_COLUMNS: usize = (THUMBNAIL_X - MARGIN * 2) / CELL_WIDTH;
+const INFO_COLUMNS: usize = DISPLAY_WIDTH / CELL_WIDTH;
+const FRAME: Duration = Duration::from_micros(16_667);
+
+const BACKGROUND: Color = (16, 16, 16);
+const TEXT: Color = (200, 200, 200);
+const DIM_TEXT: Color = (110, 110, 110);
+const SELECTION: Color = (0, 80, 160);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LauncherKey {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    //Runs the selected ROM
+    Start,
+}
+
+pub struct Launcher {
+    entries: Vec<LibraryEntry>,
+    selected: usize,
+    //First entry of the list on screen
+    top: usize,
+    //Kept once started, so going back to a ROM carries on from where its preview was
+    thumbnails: Vec<Option<Thumbnail>>,
+    palette: Palette,
+}
+
+impl Launcher {
+    pub fn new(entries: Vec<LibraryEntry>, palette: Palette) -> Self {
+        let thumbnails = entries.iter().map(|_| None).collect();
+        Launcher{entries, selected: 0, top: 0, thumbnails, palette}
+    }
+
+    //Returns the path of the ROM to start
+    pub fn key(&mut self, key: LauncherKey) -> Option<String> {
+        let last = self.entries.len().saturating_sub(1);
+        self.selected = match key {
+            LauncherKey::Up => self.selected.saturating_sub(1),
+            LauncherKey::Down => (self.selected + 1).min(last),
+            LauncherKey::PageUp => self.selected.saturating_sub(VISIBLE_ROWS),
+            LauncherKey::PageDown => (self.selected + VISIBLE_ROWS).min(last),
+            LauncherKey::Start => return self.entries.get(self.selected).map(|entry| entry.path.clone()),
+        };
+        if self.selected < self.top {
+            self.top = self.selected;
+        } else if self.selected >= self.top + VISIBLE_ROWS {
+            self.top = self.selected + 1 - VISIBLE_ROWS;
+        }
+        None
+    }
+
+    //Runs the selected thumbnail one more frame, after its headless start the first time
+    pub fn update(&mut self) {
+        let Some(entry) = self.entries.get(self.selected) else {
+            return;
+        };
+        match self.thumbnails[self.selected].as_mut() {
+            Some(thumbnail) => thumbnail.run(1),
+            None => self.thumbnails[self.selected] = Some(Thumbnail::new(&entry.data)),
+        }
+    }
+
+    //RGB pixels, WIDTH by HEIGHT
+    pub fn render(&self) -> Vec<u8> {
+        let mut image = Image::new(WIDTH, HEIGHT, BACKGROUND);
+        image.text(MARGIN, MARGIN, &format!("LIBRARY  {} ROMS", self.entries.len()), TEXT);
+        if self.entries.is_empty() {
+            image.text(MARGIN, LIST_Y, "No ROMs found, add folders", DIM_TEXT);
+            image.text(MARGIN, LIST_Y + ROW_HEIGHT, "with --library dir", DIM_TEXT);
+            return image.pixels;
+        }
+
+        for (row, entry) in self.entries.iter().enumerate().skip(self.top).take(VISIBLE_ROWS) {
+            let y = LIST_Y + (row - self.top) * ROW_HEIGHT;
+            if row == self.selected {
+                image.fill(MARGIN - 1, y, TITLE_COLUMNS * CELL_WIDTH + 1, ROW_HEIGHT, SELECTION);
+            }
+            image.text(MARGIN, y, &truncate(&entry.title, TITLE_COLUMNS), TEXT);
+        }
+
+        if let Some(thumbnail) = self.thumbnails[self.selected].as_ref() {
+            let (on, off) = (self.palette.foreground(), self.palette.background());
+            for (y, row) in thumbnail.vram().iter().enumerate() {
+                for (x, &pixel) in row.iter().enumerate() {
+                    let color = if pixel != 0 { on } else { off };
+                    image.fill(THUMBNAIL_X + x, THUMBNAIL_Y + y, 1, 1, (color.0, color.1, color.2));
+                }
+            }
+        }
+        let path = &self.entries[self.selected].path;
+        let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+        let info_y = THUMBNAIL_Y + DISPLAY_HEIGHT + 2;
+        image.text(THUMBNAIL_X, info_y, &truncate(name, INFO_COLUMNS), DIM_TEXT);
+        image.text(THUMBNAIL_X, info_y + ROW_HEIGHT, &format!("{} OF {}", self.selected + 1, self.entries.len()), DIM_TEXT);
+        image.text(THUMBNAIL_X, info_y + ROW_HEIGHT * 2, "ENTER PLAYS", DIM_TEXT);
+        image.text(THUMBNAIL_X, info_y + ROW_HEIGHT * 3, "ESC COMES BACK", DIM_TEXT);
+        image.pixels
+    }
+
+    //Shows the library until a ROM is picked, or returns None when the window is closed
+    pub fn choose(&mut self, display: &mut Display, input: &mut Input) -> Option<String> {
+        display.set_status("Library");
+        loop {
+            while let Some(action) = input.poll_window_events() {
+                match action {
+                    WindowAction::Close => return None,
+                    WindowAction::Launcher(key) => {
+                        if let Some(path) = self.key(key) {
+                            return Some(path);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            self.update();
+            display.show_image(&self.render(), WIDTH, HEIGHT);
+            thread::sleep(FRAME);
+        }
+    }
+}
+
+fn truncate(text: &str, columns: usize) -> String {
+    text.chars().take(columns).collect()
+}
+
+#[cfg(test)]
+#[path = "./launcher_test.rs"]
+mod launcher_test;
@@ -0,0 +1,46 @@
+use crate::library::LibraryEntry;
+use crate::palette::{Palette, Rgb};
+use super::{Launcher, LauncherKey, HEIGHT, THUMBNAIL_X, THUMBNAIL_Y, VISIBLE_ROWS, WIDTH};
+
+fn launcher(count: usize) -> Launcher {
+    //Each ROM draws one pixel at the top left then loops forever
+    let entries = (0..count).map(|n| LibraryEntry{path: format!("roms/{}.ch8", n), title: format!("Game {}", n),
+        data: vec![0xA2, 0x06, 0xD0, 0x11, 0x12, 0x04, 0x80]}).collect();
+    Launcher::new(entries, Palette::new("test", &[Rgb(0, 0, 0), Rgb(255, 0, 0)]))
+}
+
+#[test]
+fn test_navigation() {
+    let mut launcher = launcher(VISIBLE_ROWS + 5);
+    assert_eq!(launcher.key(LauncherKey::Up), None);
+    assert_eq!(launcher.key(LauncherKey::Start), Some(String::from("roms/0.ch8")));
+    launcher.key(LauncherKey::PageDown);
+    launcher.key(LauncherKey::Down);
+    assert_eq!(launcher.top, 2);
+    launcher.key(LauncherKey::PageDown);
+    assert_eq!(launcher.key(LauncherKey::Start), Some(format!("roms/{}.ch8", VISIBLE_ROWS + 4)));
+    launcher.key(LauncherKey::PageUp);
+    launcher.key(LauncherKey::PageUp);
+    assert_eq!((launcher.selected, launcher.top), (0, 0));
+}
+
+#[test]
+fn test_thumbnail() {
+    let mut launcher = launcher(2);
+    let pixel = |pixels: &[u8]| pixels[(THUMBNAIL_Y * WIDTH + THUMBNAIL_X) * 3..][..3].to_vec();
+    assert_eq!(pixel(&launcher.render()), vec![16, 16, 16]);
+    launcher.update();
+    let pixels = launcher.render();
+    assert_eq!(pixels.len(), WIDTH * HEIGHT * 3);
+    assert_eq!(pixel(&pixels), vec![255, 0, 0]);
+    assert!(launcher.thumbnails[1].is_none());
+}
+
+#[test]
+fn test_empty_library() {
+    let mut launcher = launcher(0);
+    launcher.update();
+    launcher.key(LauncherKey::Down);
+    assert_eq!(launcher.key(LauncherKey::Start), None);
+    assert_eq!(launcher.render().len(), WIDTH * HEIGHT * 3);
+}
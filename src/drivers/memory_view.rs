@@ -0,0 +1,281 @@
+//A window showing all of memory as a hex and ASCII grid, with a sprite view of the bytes from the cursor on.
+//PC, I and the return addresses on the stack are highlighted and bytes that change flash for a moment.
+//Bytes can be typed over in hex while the emulator is paused.
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::render::{Canvas, Texture};
+use sdl2::video::Window;
+
+use crate::MEMORY_SIZE;
+use super::font::{Color, Image, BYTES_PER_PIXEL, CELL_WIDTH, GLYPH_HEIGHT};
+
+//Window pixels per viewer pixel
+const SCALE: usize = 2;
+const CELL_HEIGHT: usize = GLYPH_HEIGHT + 2;
+const MARGIN: usize = 2;
+const BYTES_PER_ROW: usize = 16;
+const VISIBLE_ROWS: usize = 32;
+//Columns of the grid in cells: the address, two digits and a gap per byte, then the ASCII column
+const HEX_COLUMN: usize = 4;
+const ASCII_COLUMN: usize = HEX_COLUMN + BYTES_PER_ROW * 3 + 1;
+const GRID_COLUMNS: usize = ASCII_COLUMN + BYTES_PER_ROW;
+const SPRITE_SCALE: usize = 2;
+const SPRITE_COLUMNS: usize = 4;
+const SPRITE_GAP: usize = 4;
+const SPRITE_X: usize = MARGIN + GRID_COLUMNS * CELL_WIDTH + 8;
+const SPRITE_Y: usize = MARGIN + CELL_HEIGHT;
+pub const WIDTH: usize = SPRITE_X + SPRITE_COLUMNS * (8 * SPRITE_SCALE + SPRITE_GAP) + MARGIN;
+pub const HEIGHT: usize = MARGIN * 2 + (VISIBLE_ROWS + 1) * CELL_HEIGHT;
+//Viewer updates a changed byte stays highlighted for
+const FLASH_FRAMES: u8 = 30;
+const MAX_SPRITE_HEIGHT: usize = 15;
+
+const BACKGROUND: Color = (16, 16, 16);
+const TEXT: Color = (200, 200, 200);
+const DIM_TEXT: Color = (110, 110, 110);
+const FLASH: Color = (255, 230, 0);
+const CURSOR: Color = (220, 220, 220);
+const PC: Color = (0, 80, 160);
+const INDEX: Color = (150, 90, 0);
+const STACK: Color = (90, 40, 120);
+const SPRITE_ON: Color = (240, 240, 240);
+const SPRITE_OFF: Color = (40, 40, 40);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MemoryKey {
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    //A hex digit typed over the byte under the cursor
+    Digit(u8),
+    //A click at this position in the window
+    Select(i32, i32),
+    TallerSprites,
+    ShorterSprites,
+}
+
+//What the viewer shows, borrowed from the CPU
+pub struct MemoryState<'a> {
+    pub memory: &'a [u8],
+    pub pc: usize,
+    pub i: usize,
+    pub stack: &'a [u16],
+    //Bytes are only edited while paused
+    pub paused: bool,
+}
+
+pub struct MemoryViewer {
+    cursor: usize,
+    //First row of the grid on screen
+    top_row: usize,
+    //High nibble typed so far
+    pending: Option<u8>,
+    sprite_height: usize,
+    previous: Vec<u8>,
+    //Updates left to flash each byte for
+    flashes: Vec<u8>,
+}
+
+impl MemoryViewer {
+    pub fn new() -> Self {
+        MemoryViewer{cursor: 0x200, top_row: 0x200 / BYTES_PER_ROW, pending: None, sprite_height: 5, previous: Vec::new(), flashes: Vec::new()}
+    }
+
+    //Called once per emulator loop, so flashes last the same time however fast the ROM runs
+    pub fn update(&mut self, memory: &[u8]) {
+        if self.previous.len() != memory.len() {
+            self.previous = memory.to_vec();
+            self.flashes = vec![0; memory.len()];
+            return;
+        }
+        for ((previous, flash), &byte) in self.previous.iter_mut().zip(self.flashes.iter_mut()).zip(memory.iter()) {
+            *flash = if *previous != byte { FLASH_FRAMES } else { flash.saturating_sub(1) };
+            *previous = byte;
+        }
+    }
+
+    //Returns the address and value of a byte once both of its digits have been typed
+    pub fn key(&mut self, key: MemoryKey, state: &MemoryState) -> Option<(usize, u8)> {
+        match key {
+            MemoryKey::Up => self.move_cursor(-(BYTES_PER_ROW as isize)),
+            MemoryKey::Down => self.move_cursor(BYTES_PER_ROW as isize),
+            MemoryKey::Left => self.move_cursor(-1),
+            MemoryKey::Right => self.move_cursor(1),
+            MemoryKey::PageUp => self.move_cursor(-((BYTES_PER_ROW * VISIBLE_ROWS) as isize)),
+            MemoryKey::PageDown => self.move_cursor((BYTES_PER_ROW * VISIBLE_ROWS) as isize),
+            MemoryKey::Select(x, y) => {
+                if let Some(address) = self.address_at(x, y) {
+                    self.pending = None;
+                    self.cursor = address;
+                }
+            }
+            MemoryKey::TallerSprites => self.sprite_height = (self.sprite_height + 1).min(MAX_SPRITE_HEIGHT),
+            MemoryKey::ShorterSprites => self.sprite_height = (self.sprite_height - 1).max(1),
+            MemoryKey::Digit(digit) if state.paused => match self.pending.take() {
+                None => self.pending = Some(digit & 0xF),
+                Some(high) => {
+                    let edit = (self.cursor, high << 4 | (digit & 0xF));
+                    self.move_cursor(1);
+                    return Some(edit);
+                }
+            },
+            MemoryKey::Digit(_) => {}
+        }
+        None
+    }
+
+    //Wraps around the ends of memory and scrolls to keep the cursor on screen
+    fn move_cursor(&mut self, delta: isize) {
+        self.pending = None;
+        self.cursor = (self.cursor as isize + delta).rem_euclid(MEMORY_SIZE as isize) as usize;
+        let row = self.cursor / BYTES_PER_ROW;
+        if row < self.top_row {
+            self.top_row = row;
+        } else if row >= self.top_row + VISIBLE_ROWS {
+            self.top_row = row + 1 - VISIBLE_ROWS;
+        }
+    }
+
+    //The byte under a point in viewer pixels, in either the hex or the ASCII column
+    fn address_at(&self, x: i32, y: i32) -> Option<usize> {
+        let column = usize::try_from(x - MARGIN as i32).ok()? / CELL_WIDTH;
+        let row = (usize::try_from(y - MARGIN as i32).ok()? / CELL_HEIGHT).checked_sub(1)?;
+        if row >= VISIBLE_ROWS {
+            return None;
+        }
+        let byte = match column {
+            column if (HEX_COLUMN..HEX_COLUMN + BYTES_PER_ROW * 3).contains(&column) => (column - HEX_COLUMN) / 3,
+            column if (ASCII_COLUMN..GRID_COLUMNS).contains(&column) => column - ASCII_COLUMN,
+            _ => return None,
+        };
+        Some((self.top_row + row) * BYTES_PER_ROW + byte)
+    }
+
+    //RGB pixels, WIDTH by HEIGHT
+    pub fn render(&self, state: &MemoryState) -> Vec<u8> {
+        let mut image = Image::new(WIDTH, HEIGHT, BACKGROUND);
+
+        let value = state.memory.get(self.cursor).copied().unwrap_or(0);
+        let mode = if state.paused { "PAUSED, TYPE HEX TO EDIT" } else { "RUNNING" };
+        let status = format!("{:03X}={:02X}  PC {:03X}  I {:03X}  SP {}  SPRITES 8x{}  {}",
+            self.cursor, value, state.pc, state.i, state.stack.len(), self.sprite_height, mode);
+        image.text(MARGIN, MARGIN, &status, TEXT);
+
+        for row in 0..VISIBLE_ROWS {
+            let line = (self.top_row + row) % (MEMORY_SIZE / BYTES_PER_ROW);
+            let y = MARGIN + (row + 1) * CELL_HEIGHT;
+            image.text(MARGIN, y, &format!("{:03X}", line * BYTES_PER_ROW), DIM_TEXT);
+            for byte in 0..BYTES_PER_ROW {
+                let address = line * BYTES_PER_ROW + byte;
+                let value = state.memory.get(address).copied().unwrap_or(0);
+                let hex_x = MARGIN + (HEX_COLUMN + byte * 3) * CELL_WIDTH;
+                let ascii_x = MARGIN + (ASCII_COLUMN + byte) * CELL_WIDTH;
+                if let Some(color) = self.highlight(address, state) {
+                    image.fill(hex_x - 1, y, CELL_WIDTH * 2 + 1, CELL_HEIGHT, color);
+                    image.fill(ascii_x - 1, y, CELL_WIDTH + 1, CELL_HEIGHT, color);
+                }
+                let color = if address == self.cursor { BACKGROUND } else { self.text_color(address) };
+                let digits = match self.pending {
+                    Some(high) if address == self.cursor => format!("{:X}_", high),
+                    _ => format!("{:02X}", value),
+                };
+                image.text(hex_x, y, &digits, color);
+                let character = if (0x20..0x7F).contains(&value) { value as char } else { '.' };
+                image.text(ascii_x, y, &character.to_string(), color);
+            }
+        }
+
+        self.render_sprites(&mut image, state.memory);
+        image.pixels
+    }
+
+    fn highlight(&self, address: usize, state: &MemoryState) -> Option<Color> {
+        let opcode_at = |start: usize| address == start || address == (start + 1) % MEMORY_SIZE;
+        if address == self.cursor {
+            Some(CURSOR)
+        } else if opcode_at(state.pc) {
+            Some(PC)
+        } else if address == state.i {
+            Some(INDEX)
+        } else if state.stack.iter().any(|&entry| opcode_at(entry as usize)) {
+            Some(STACK)
+        } else {
+            None
+        }
+    }
+
+    //Fades from the flash color back to the text color
+    fn text_color(&self, address: usize) -> Color {
+        let flash = self.flashes.get(address).copied().unwrap_or(0) as u32;
+        let blend = |from: u8, to: u8| ((from as u32 * flash + to as u32 * (FLASH_FRAMES as u32 - flash)) / FLASH_FRAMES as u32) as u8;
+        (blend(FLASH.0, TEXT.0), blend(FLASH.1, TEXT.1), blend(FLASH.2, TEXT.2))
+    }
+
+    //Consecutive sprites of sprite_height bytes from the cursor on, as DXYN would draw them
+    fn render_sprites(&self, image: &mut Image, memory: &[u8]) {
+        let sprite_width = 8 * SPRITE_SCALE;
+        let sprite_height = self.sprite_height * SPRITE_SCALE;
+        let rows = (HEIGHT - SPRITE_Y - MARGIN + SPRITE_GAP) / (sprite_height + SPRITE_GAP);
+        for sprite in 0..rows * SPRITE_COLUMNS {
+            let x = SPRITE_X + sprite % SPRITE_COLUMNS * (sprite_width + SPRITE_GAP);
+            let y = SPRITE_Y + sprite / SPRITE_COLUMNS * (sprite_height + SPRITE_GAP);
+            for line in 0..self.sprite_height {
+                let address = (self.cursor + sprite * self.sprite_height + line) % MEMORY_SIZE;
+                let byte = memory.get(address).copied().unwrap_or(0);
+                for bit in 0..8 {
+                    let color = if byte & (0x80 >> bit) != 0 { SPRITE_ON } else { SPRITE_OFF };
+                    image.fill(x + bit * SPRITE_SCALE, y + line * SPRITE_SCALE, SPRITE_SCALE, SPRITE_SCALE, color);
+                }
+            }
+        }
+    }
+}
+
+impl Default for MemoryViewer {
+    fn default() -> Self {
+        MemoryViewer::new()
+    }
+}
+
+pub struct MemoryWindow {
+    canvas: Canvas<Window>,
+    texture: Texture,
+    viewer: MemoryViewer,
+}
+
+impl MemoryWindow {
+    pub fn open(sdl_context: &sdl2::Sdl) -> Self {
+        let video_subsystem = sdl_context.video().unwrap();
+        let window = video_subsystem.window("Memory", (WIDTH * SCALE) as u32, (HEIGHT * SCALE) as u32).position(0, 250).opengl().build().unwrap();
+        let canvas = window.into_canvas().build().unwrap();
+        let texture = canvas.texture_creator()
+            .create_texture_streaming(PixelFormatEnum::RGB24, WIDTH as u32, HEIGHT as u32)
+            .unwrap();
+        MemoryWindow{canvas, texture, viewer: MemoryViewer::new()}
+    }
+
+    pub fn id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+
+    pub fn show(&mut self, state: &MemoryState) {
+        self.viewer.update(state.memory);
+        let _ = self.texture.update(None, &self.viewer.render(state), WIDTH * BYTES_PER_PIXEL);
+        let _ = self.canvas.copy(&self.texture, None, None);
+        self.canvas.present();
+    }
+
+    pub fn key(&mut self, key: MemoryKey, state: &MemoryState) -> Option<(usize, u8)> {
+        let key = match key {
+            MemoryKey::Select(x, y) => MemoryKey::Select(x / SCALE as i32, y / SCALE as i32),
+            key => key,
+        };
+        self.viewer.key(key, state)
+    }
+}
+
+#[cfg(test)]
+#[path = "./memory_view_test.rs"]
+mod memory_view_test;
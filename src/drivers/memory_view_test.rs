@@ -0,0 +1,113 @@
+use crate::MEMORY_SIZE;
+use super::{MemoryKey, MemoryState, MemoryViewer, BYTES_PER_PIXEL, CELL_HEIGHT, CELL_WIDTH, CURSOR, FLASH_FRAMES, HEX_COLUMN,
+    ASCII_COLUMN, MARGIN, PC, STACK, VISIBLE_ROWS, WIDTH};
+
+fn state(memory: &[u8], paused: bool) -> MemoryState<'_> {
+    MemoryState{memory, pc: 0x200, i: 0x300, stack: &[], paused}
+}
+
+//Top left corner of a byte's hex digits, in viewer pixels
+fn hex_cell(viewer: &MemoryViewer, address: usize) -> (usize, usize) {
+    let row = address / 16 - viewer.top_row;
+    (MARGIN + (HEX_COLUMN + address % 16 * 3) * CELL_WIDTH, MARGIN + (row + 1) * CELL_HEIGHT)
+}
+
+fn pixel(pixels: &[u8], x: usize, y: usize) -> (u8, u8, u8) {
+    let offset = (y * WIDTH + x) * BYTES_PER_PIXEL;
+    (pixels[offset], pixels[offset + 1], pixels[offset + 2])
+}
+
+#[test]
+fn test_edits_only_while_paused() {
+    let memory = vec![0; MEMORY_SIZE];
+    let mut viewer = MemoryViewer::new();
+    assert_eq!(viewer.key(MemoryKey::Digit(0xA), &state(&memory, false)), None);
+    assert_eq!(viewer.key(MemoryKey::Digit(0x5), &state(&memory, false)), None);
+    assert_eq!(viewer.key(MemoryKey::Digit(0xA), &state(&memory, true)), None);
+    assert_eq!(viewer.key(MemoryKey::Digit(0x5), &state(&memory, true)), Some((0x200, 0xA5)));
+    //The cursor moves on to the next byte
+    assert_eq!(viewer.key(MemoryKey::Digit(0x1), &state(&memory, true)), None);
+    assert_eq!(viewer.key(MemoryKey::Digit(0x2), &state(&memory, true)), Some((0x201, 0x12)));
+    //Moving drops a half typed byte
+    viewer.key(MemoryKey::Digit(0x3), &state(&memory, true));
+    viewer.key(MemoryKey::Left, &state(&memory, true));
+    assert_eq!(viewer.key(MemoryKey::Digit(0x4), &state(&memory, true)), None);
+    assert_eq!(viewer.pending, Some(0x4));
+}
+
+#[test]
+fn test_cursor_wraps_and_scrolls() {
+    let memory = vec![0; MEMORY_SIZE];
+    let mut viewer = MemoryViewer::new();
+    viewer.cursor = 0;
+    viewer.top_row = 0;
+    viewer.key(MemoryKey::Left, &state(&memory, true));
+    assert_eq!(viewer.cursor, MEMORY_SIZE - 1);
+    assert_eq!(viewer.top_row, MEMORY_SIZE / 16 - VISIBLE_ROWS);
+    viewer.key(MemoryKey::Right, &state(&memory, true));
+    viewer.key(MemoryKey::PageDown, &state(&memory, true));
+    assert_eq!(viewer.cursor, 16 * VISIBLE_ROWS);
+    assert_eq!(viewer.top_row, 1);
+    viewer.key(MemoryKey::Up, &state(&memory, true));
+    assert_eq!(viewer.top_row, 1);
+}
+
+#[test]
+fn test_select_by_click() {
+    let memory = vec![0; MEMORY_SIZE];
+    let mut viewer = MemoryViewer::new();
+    let (x, y) = hex_cell(&viewer, 0x235);
+    viewer.key(MemoryKey::Select(x as i32 + 1, y as i32 + 1), &state(&memory, true));
+    assert_eq!(viewer.cursor, 0x235);
+    let ascii_x = MARGIN + (ASCII_COLUMN + 0xC) * CELL_WIDTH;
+    viewer.key(MemoryKey::Select(ascii_x as i32, y as i32), &state(&memory, true));
+    assert_eq!(viewer.cursor, 0x23C);
+    //The status line and the margins select nothing
+    viewer.key(MemoryKey::Select(x as i32, 0), &state(&memory, true));
+    viewer.key(MemoryKey::Select(-5, y as i32), &state(&memory, true));
+    assert_eq!(viewer.cursor, 0x23C);
+}
+
+#[test]
+fn test_sprite_height_limits() {
+    let memory = vec![0; MEMORY_SIZE];
+    let mut viewer = MemoryViewer::new();
+    for _ in 0..20 {
+        viewer.key(MemoryKey::TallerSprites, &state(&memory, false));
+    }
+    assert_eq!(viewer.sprite_height, 15);
+    for _ in 0..20 {
+        viewer.key(MemoryKey::ShorterSprites, &state(&memory, false));
+    }
+    assert_eq!(viewer.sprite_height, 1);
+}
+
+#[test]
+fn test_changed_bytes_flash() {
+    let mut memory = vec![0; MEMORY_SIZE];
+    let mut viewer = MemoryViewer::new();
+    viewer.update(&memory);
+    assert!(viewer.flashes.iter().all(|&flash| flash == 0));
+    memory[0x250] = 1;
+    viewer.update(&memory);
+    assert_eq!(viewer.flashes[0x250], FLASH_FRAMES);
+    viewer.update(&memory);
+    assert_eq!(viewer.flashes[0x250], FLASH_FRAMES - 1);
+    assert_eq!(viewer.flashes[0x251], 0);
+}
+
+#[test]
+fn test_highlights() {
+    let memory = vec![0; MEMORY_SIZE];
+    let mut viewer = MemoryViewer::new();
+    viewer.cursor = 0x210;
+    let stack = [0x204];
+    let state = MemoryState{memory: &memory, pc: 0x202, i: 0x300, stack: &stack, paused: true};
+    let pixels = viewer.render(&state);
+    for (address, color) in [(0x202, PC), (0x203, PC), (0x204, STACK), (0x205, STACK), (0x210, CURSOR)] {
+        let (x, y) = hex_cell(&viewer, address);
+        assert_eq!(pixel(&pixels, x - 1, y), color, "{:03X}", address);
+    }
+    let (x, y) = hex_cell(&viewer, 0x206);
+    assert_ne!(pixel(&pixels, x - 1, y), STACK);
+}
@@ -2,11 +2,57 @@ mod display;
 mod input;
 mod rom;
 mod audio;
+mod terminal;
+mod null;
+mod font;
+mod memory_view;
+mod launcher;
+mod archive;
+mod cartridge;
 
+use crate::{VRAM, DirtyRows};
+use crate::palette::Palette;
 
-pub use self::display::Display;
+pub use self::display::{Display, Scaling};
 pub use self::display::WindowType;
 pub use self::input::Input;
 pub use self::input::WindowAction;
-pub use self::rom::ROM;
-pub use self::audio::Audio;
\ No newline at end of file
+pub use self::rom::{Platform, RomError, ROM};
+pub use self::audio::Audio;
+pub use self::terminal::{TerminalVideo, TerminalBell, TerminalInput, Glyphs, render_lines};
+pub use self::null::{NullVideo, NullAudio, NullInput};
+pub use self::memory_view::{MemoryKey, MemoryState};
+pub use self::cartridge::CartridgeOptions;
+pub use self::launcher::{Launcher, LauncherKey};
+
+pub trait VideoSink {
+    //Only the rows flagged in dirty_rows differ from the previous draw
+    fn draw(&mut self, pixels: &VRAM, dirty_rows: DirtyRows);
+    //Takes effect from the next draw
+    fn set_palette(&mut self, _palette: &Palette) {}
+    //Current speed and measured frame and instruction rates
+    fn set_status(&mut self, _status: &str) {}
+    //A short message shown over the display from the next draw, or None to take it down
+    fn show_notice(&mut self, _notice: Option<&str>) {}
+    //Shows what was drawn at the next display refresh and waits for it. False for sinks that do not sync
+    //to the refresh, which show each draw straight away and leave the pacing to the clock.
+    fn present(&mut self) -> bool {
+        false
+    }
+    //Refreshes the memory viewer, for sinks that have one
+    fn show_memory(&mut self, _state: &MemoryState) {}
+    //Returns the byte to write when a key in the memory viewer edits one
+    fn edit_memory(&mut self, _key: MemoryKey, _state: &MemoryState) -> Option<(usize, u8)> {
+        None
+    }
+}
+
+pub trait AudioSink {
+    fn set_beep(&mut self, on: bool);
+    fn set_muted(&mut self, _muted: bool) {}
+}
+
+pub trait InputSource {
+    fn poll_window_events(&mut self) -> Option<WindowAction>;
+    fn poll(&mut self) -> [bool; 16];
+}
@@ -0,0 +1,76 @@
+use crate::{DISPLAY_WIDTH, DISPLAY_HEIGHT, VRAM, DirtyRows};
+use super::{AudioSink, InputSource, VideoSink, WindowAction};
+
+//Keeps the last frame instead of showing it
+pub struct NullVideo {
+    pub vram: VRAM,
+    pub frames_drawn: usize,
+}
+
+impl NullVideo {
+    pub fn new() -> Self {
+        NullVideo{vram: [[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT], frames_drawn: 0}
+    }
+}
+
+impl Default for NullVideo {
+    fn default() -> Self {
+        NullVideo::new()
+    }
+}
+
+impl VideoSink for NullVideo {
+    fn draw(&mut self, pixels: &VRAM, _dirty_rows: DirtyRows) {
+        self.vram = *pixels;
+        self.frames_drawn += 1;
+    }
+}
+
+pub struct NullAudio {
+    pub beeping: bool,
+}
+
+impl NullAudio {
+    pub fn new() -> Self {
+        NullAudio{beeping: false}
+    }
+}
+
+impl Default for NullAudio {
+    fn default() -> Self {
+        NullAudio::new()
+    }
+}
+
+impl AudioSink for NullAudio {
+    fn set_beep(&mut self, on: bool) {
+        self.beeping = on;
+    }
+}
+
+//Presses no keys and asks to close after the given number of polls, or never
+pub struct NullInput {
+    polls_left: Option<usize>,
+}
+
+impl NullInput {
+    pub fn new(polls: Option<usize>) -> Self {
+        NullInput{polls_left: polls}
+    }
+}
+
+impl InputSource for NullInput {
+    fn poll_window_events(&mut self) -> Option<WindowAction> {
+        match self.polls_left {
+            Some(0) => Some(WindowAction::Close),
+            _ => None,
+        }
+    }
+
+    fn poll(&mut self) -> [bool; 16] {
+        if let Some(polls_left) = self.polls_left.as_mut() {
+            *polls_left = polls_left.saturating_sub(1);
+        }
+        [false; 16]
+    }
+}
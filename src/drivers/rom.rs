@@ -1,25 +1,156 @@
 use std::fs::File;
-use std::io::Read;
+use std::io::{ErrorKind, Read};
+
+use crate::disassembler::{disassemble, is_instruction};
+use crate::octo::{self, AssemblyError};
+use crate::MEMORY_SIZE;
+use super::archive::{self, Zip, ZipEntry};
+use super::cartridge::{self, CartridgeOptions};
+
+//Programs are loaded here, everything below belongs to the interpreter
+const PROGRAM_START: usize = 0x200;
+//Archives and cartridges are unpacked in memory, so they are read whole up to this size
+const MAX_FILE_SIZE: usize = 16 << 20;
+//Archives opened one inside another, enough for a gzipped ROM in a zip but not for a self-reproducing archive
+const MAX_ARCHIVE_DEPTH: usize = 2;
+//Octo assembler source, assembled on loading
+const SOURCE_EXTENSION: &str = ".8o";
+//Files in an archive that may be the ROM
+const ROM_EXTENSIONS: [&str; 5] = ["ch8", "c8", "rom", "xo8", "gif"];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Platform {
+    Chip8,
+    XoChip,
+}
+
+impl Platform {
+    //XO-CHIP for .xo8 files, otherwise CHIP-8
+    pub fn for_path(path: &str) -> Self {
+        match path.rsplit_once('.') {
+            Some((_, extension)) if extension.eq_ignore_ascii_case("xo8") => Platform::XoChip,
+            _ => Platform::Chip8,
+        }
+    }
+
+    //Bytes from the program start to the end of memory. The CPU only has the 4 KiB of CHIP-8 memory,
+    //so XO-CHIP ROMs are held to the same limit rather than losing everything past 0xFFF.
+    pub fn max_rom_size(self) -> usize {
+        match self {
+            Platform::Chip8 | Platform::XoChip => MEMORY_SIZE - PROGRAM_START,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RomError {
+    NotFound(String),
+    PermissionDenied(String),
+    TooLarge{size: usize, limit: usize},
+    Empty,
+    Unreadable(String, ErrorKind),
+    Archive(String),
+    NoRomInArchive,
+    //The archive holds these ROMs, pick one with ROM::from_entry
+    SeveralRoms(Vec<String>),
+    Cartridge(String),
+    Assembly(AssemblyError),
+}
 
 pub struct ROM {
-    pub rom: [u8; 3584],
-    pub size: usize,
+    pub data: Vec<u8>,
+    //Settings embedded in an Octo cartridge
+    pub options: Option<CartridgeOptions>,
 }
 
 impl ROM {
-    pub fn from(filename: &str) -> Self {
-        let mut f = File::open(filename).expect("File not found");
-        let mut buffer = [0 as u8; 3584];
-
-        let bytes_read = if let Ok(bytes_read) = f.read(&mut buffer) {
-            bytes_read
-        } else {
-            0
+    //Plain ROMs load as they are, zip and gzip files are unpacked and Octo cartridges and .8o sources assembled
+    pub fn from(filename: &str, platform: Platform) -> Result<Self, RomError> {
+        ROM::load(filename, None, platform)
+    }
+
+    //Loads the named file from a zip archive
+    pub fn from_entry(filename: &str, entry: &str, platform: Platform) -> Result<Self, RomError> {
+        ROM::load(filename, Some(entry), platform)
+    }
+
+    fn load(filename: &str, entry: Option<&str>, platform: Platform) -> Result<Self, RomError> {
+        let error = |error: std::io::Error| match error.kind() {
+            ErrorKind::NotFound => RomError::NotFound(filename.to_string()),
+            ErrorKind::PermissionDenied => RomError::PermissionDenied(filename.to_string()),
+            kind => RomError::Unreadable(filename.to_string(), kind),
         };
+        let file = File::open(filename).map_err(error)?;
+        let limit = platform.max_rom_size();
+        //One byte past the limit is enough to tell the file does not fit
+        let mut data = Vec::new();
+        file.take(MAX_FILE_SIZE as u64 + 1).read_to_end(&mut data).map_err(error)?;
+        if data.len() > MAX_FILE_SIZE {
+            let size = std::fs::metadata(filename).map(|metadata| metadata.len() as usize).unwrap_or(data.len());
+            return Err(RomError::TooLarge{size, limit});
+        }
+        let (data, options) = match filename.to_lowercase().ends_with(SOURCE_EXTENSION) {
+            true => (octo::assemble(&String::from_utf8_lossy(&data)).map_err(RomError::Assembly)?, None),
+            false => unpack(data, entry, 0)?,
+        };
+        if data.len() > limit {
+            return Err(RomError::TooLarge{size: data.len(), limit});
+        }
+        if data.is_empty() {
+            return Err(RomError::Empty);
+        }
+        Ok(ROM{data, options})
+    }
 
-        ROM {
-            rom: buffer,
-            size: bytes_read,
+    //Set when the ROM does not start with something CPU can run, as with a data file or a ROM for another platform
+    pub fn warning(&self) -> Option<String> {
+        let opcode = u16::from_be_bytes([self.data[0], self.data.get(1).copied().unwrap_or(0)]);
+        match self.data.len() {
+            1 => Some(String::from("The ROM is a single byte, too short for an instruction")),
+            _ if !is_instruction(opcode) => Some(format!("The first instruction {:04X} ({}) is not valid CHIP-8", opcode, disassemble(opcode))),
+            _ => None,
         }
     }
-}
\ No newline at end of file
+}
+
+fn is_rom_name(name: &str) -> bool {
+    name.rsplit_once('.').is_some_and(|(_, extension)| ROM_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+//Recognises the format from the first bytes, since ROM collections are often misnamed
+fn unpack(data: Vec<u8>, entry: Option<&str>, depth: usize) -> Result<(Vec<u8>, Option<CartridgeOptions>), RomError> {
+    let is_archive = archive::is_gzip(&data) || archive::is_zip(&data);
+    if is_archive && depth == MAX_ARCHIVE_DEPTH {
+        return Err(RomError::Archive(format!("Archives are nested more than {} deep", MAX_ARCHIVE_DEPTH)));
+    }
+    if archive::is_gzip(&data) {
+        let data = archive::gunzip(&data, MAX_FILE_SIZE).map_err(RomError::Archive)?;
+        return unpack(data, entry, depth + 1);
+    }
+    if archive::is_zip(&data) {
+        let zip = Zip::new(&data).map_err(RomError::Archive)?;
+        let roms: Vec<&ZipEntry> = zip.entries.iter()
+            .filter(|file| entry.map_or(is_rom_name(&file.name), |name| file.name == name))
+            .collect();
+        let rom = match roms.as_slice() {
+            [] => return Err(RomError::NoRomInArchive),
+            [rom] => rom,
+            _ => return Err(RomError::SeveralRoms(roms.iter().map(|rom| rom.name.clone()).collect())),
+        };
+        if rom.size > MAX_FILE_SIZE {
+            return Err(RomError::TooLarge{size: rom.size, limit: MAX_FILE_SIZE});
+        }
+        let data = zip.read(rom, MAX_FILE_SIZE).map_err(RomError::Archive)?;
+        return unpack(data, None, depth + 1);
+    }
+    if cartridge::is_cartridge(&data) {
+        let cartridge = cartridge::decode(&data).map_err(RomError::Cartridge)?;
+        let program = octo::assemble(&cartridge.source).map_err(RomError::Assembly)?;
+        return Ok((program, Some(cartridge.options)));
+    }
+    Ok((data, None))
+}
+
+#[cfg(test)]
+#[path = "./rom_test.rs"]
+mod rom_test;
@@ -0,0 +1,90 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use super::{Platform, RomError, ROM};
+
+fn rom_file(name: &str, data: &[u8]) -> PathBuf {
+    let path = env::temp_dir().join(format!("chip8_emulator_rom_test_{}.ch8", name));
+    fs::write(&path, data).unwrap();
+    path
+}
+
+fn load(name: &str, data: &[u8], platform: Platform) -> Result<ROM, RomError> {
+    let path = rom_file(name, data);
+    let rom = ROM::from(path.to_str().unwrap(), platform);
+    fs::remove_file(&path).unwrap();
+    rom
+}
+
+#[test]
+fn test_reads_whole_file() {
+    let data: Vec<u8> = (0..3584).map(|n| n as u8).collect();
+    let rom = load("whole", &data, Platform::Chip8).unwrap();
+    assert_eq!(rom.data, data);
+}
+
+#[test]
+fn test_size_limits() {
+    let data = vec![0x12; 3585];
+    assert_eq!(load("chip8_limit", &data, Platform::Chip8).err(), Some(RomError::TooLarge{size: 3585, limit: 3584}));
+    assert_eq!(load("xo_chip_limit", &data, Platform::XoChip).err(), Some(RomError::TooLarge{size: 3585, limit: 3584}));
+    assert_eq!(load("xo_chip_fits", &data[..3584], Platform::XoChip).unwrap().data.len(), 3584);
+}
+
+#[test]
+fn test_errors() {
+    assert_eq!(load("empty", &[], Platform::Chip8).err(), Some(RomError::Empty));
+    assert_eq!(ROM::from("missing.ch8", Platform::Chip8).err(), Some(RomError::NotFound(String::from("missing.ch8"))));
+}
+
+#[test]
+fn test_invalid_first_instruction() {
+    assert_eq!(load("valid", &[0x00, 0xE0], Platform::Chip8).unwrap().warning(), None);
+    let warning = load("invalid", &[0xFF, 0xFF, 0x00, 0xE0], Platform::Chip8).unwrap().warning();
+    assert_eq!(warning, Some(String::from("The first instruction FFFF (DW 0xFFFF) is not valid CHIP-8")));
+    assert!(load("single", &[0x00], Platform::Chip8).unwrap().warning().is_some());
+}
+
+#[test]
+fn test_unpacks_gzip() {
+    use std::io::Write;
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&[0x00, 0xE0]).unwrap();
+    let rom = load("gzip", &encoder.finish().unwrap(), Platform::Chip8).unwrap();
+    assert_eq!(rom.data, vec![0x00, 0xE0]);
+    assert!(rom.options.is_none());
+}
+
+#[test]
+fn test_limits_archive_nesting() {
+    use std::io::Write;
+    let gzip = |data: &[u8]| {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    };
+    let twice = gzip(&gzip(&[0x00, 0xE0]));
+    assert_eq!(load("gzip_twice", &twice, Platform::Chip8).unwrap().data, vec![0x00, 0xE0]);
+    let error = load("gzip_thrice", &gzip(&twice), Platform::Chip8).err();
+    assert_eq!(error, Some(RomError::Archive(String::from("Archives are nested more than 2 deep"))));
+}
+
+#[test]
+fn test_assembles_source() {
+    let path = env::temp_dir().join("chip8_emulator_rom_test_source.8o");
+    fs::write(&path, ": main\n\tclear\n").unwrap();
+    let rom = ROM::from(path.to_str().unwrap(), Platform::Chip8);
+    fs::write(&path, ": main\n\tmissing\n").unwrap();
+    let error = ROM::from(path.to_str().unwrap(), Platform::Chip8).err();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(rom.unwrap().data, vec![0x12, 0x02, 0x00, 0xE0]);
+    assert!(matches!(error, Some(RomError::Assembly(_))));
+}
+
+#[test]
+fn test_platform_for_path() {
+    assert_eq!(Platform::for_path("roms/game.XO8"), Platform::XoChip);
+    assert_eq!(Platform::for_path("roms/game.ch8"), Platform::Chip8);
+    assert_eq!(Platform::for_path("xo8"), Platform::Chip8);
+}
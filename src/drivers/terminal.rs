@@ -0,0 +1,304 @@
+use std::io::{self, Stdout, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crossterm::{cursor, event, execute, queue, style, terminal};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags};
+
+use crate::{DISPLAY_HEIGHT, VRAM, DirtyRows};
+use crate::palette::{Palette, Rgb};
+use crate::persistence::FULL_SHADE;
+use super::{AudioSink, InputSource, VideoSink, WindowAction};
+
+//Most terminals only report presses and key repeats, so there a key counts as held until this long after
+//its last event. It has to outlast the delay before autorepeat starts, about half a second by default.
+const KEY_HOLD_DURATION: Duration = Duration::from_millis(600);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Glyphs {
+    //One character per 1x2 pixels
+    HalfBlock,
+    //One character per 2x4 pixels
+    Braille,
+}
+
+impl Glyphs {
+    fn rows_per_line(self) -> usize {
+        match self {
+            Glyphs::HalfBlock => 2,
+            Glyphs::Braille => 4,
+        }
+    }
+}
+
+//Raw mode is shared by the terminal video and input, whichever is dropped last restores the terminal
+static RAW_MODE_USERS: AtomicUsize = AtomicUsize::new(0);
+
+fn acquire_raw_mode() -> io::Result<()> {
+    if RAW_MODE_USERS.fetch_add(1, Ordering::SeqCst) == 0 {
+        terminal::enable_raw_mode()?;
+    }
+    Ok(())
+}
+
+fn release_raw_mode() {
+    if RAW_MODE_USERS.fetch_sub(1, Ordering::SeqCst) == 1 {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+pub struct TerminalVideo {
+    stdout: Stdout,
+    glyphs: Glyphs,
+    foreground: Rgb,
+    background: Rgb,
+}
+
+impl TerminalVideo {
+    pub fn new(glyphs: Glyphs) -> io::Result<Self> {
+        let mut stdout = io::stdout();
+        acquire_raw_mode()?;
+        execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide, terminal::Clear(terminal::ClearType::All))?;
+        let palette = &Palette::builtin()[0];
+        Ok(TerminalVideo{stdout, glyphs, foreground: palette.foreground(), background: palette.background()})
+    }
+
+    fn write_frame(&mut self, pixels: &VRAM, dirty_rows: DirtyRows) -> io::Result<()> {
+        let rows_per_line = self.glyphs.rows_per_line();
+        let line_mask = (1 << rows_per_line) - 1;
+        queue!(self.stdout, style::SetForegroundColor(ansi_color(self.foreground)), style::SetBackgroundColor(ansi_color(self.background)))?;
+        for (y, line) in render_lines(pixels, self.glyphs).iter().enumerate() {
+            if dirty_rows & (line_mask << (y * rows_per_line)) != 0 {
+                queue!(self.stdout, cursor::MoveTo(0, y as u16), style::Print(line))?;
+            }
+        }
+        queue!(self.stdout, style::ResetColor)?;
+        self.stdout.flush()
+    }
+    //Replaces a line of text under the picture, counting from the one right below it
+    fn write_line(&mut self, line: usize, text: &str) -> io::Result<()> {
+        let y = DISPLAY_HEIGHT.div_ceil(self.glyphs.rows_per_line()) + line;
+        queue!(self.stdout, cursor::MoveTo(0, y as u16), terminal::Clear(terminal::ClearType::CurrentLine), style::Print(text))?;
+        self.stdout.flush()
+    }
+}
+
+impl VideoSink for TerminalVideo {
+    fn draw(&mut self, pixels: &VRAM, dirty_rows: DirtyRows) {
+        let _ = self.write_frame(pixels, dirty_rows);
+    }
+
+    fn set_palette(&mut self, palette: &Palette) {
+        self.foreground = palette.foreground();
+        self.background = palette.background();
+    }
+
+    //Shown on the line below the picture
+    fn set_status(&mut self, status: &str) {
+        let _ = self.write_line(0, status);
+    }
+
+    //Shown on the line below the status
+    fn show_notice(&mut self, notice: Option<&str>) {
+        let _ = self.write_line(1, notice.unwrap_or(""));
+    }
+}
+
+impl Drop for TerminalVideo {
+    fn drop(&mut self) {
+        let _ = execute!(self.stdout, style::ResetColor, cursor::Show, terminal::LeaveAlternateScreen);
+        release_raw_mode();
+    }
+}
+
+//Rings the terminal bell once each time the buzzer starts
+pub struct TerminalBell {
+    stdout: Stdout,
+    beeping: bool,
+    muted: bool,
+}
+
+impl TerminalBell {
+    pub fn new() -> Self {
+        TerminalBell{stdout: io::stdout(), beeping: false, muted: false}
+    }
+}
+
+impl Default for TerminalBell {
+    fn default() -> Self {
+        TerminalBell::new()
+    }
+}
+
+impl AudioSink for TerminalBell {
+    fn set_beep(&mut self, on: bool) {
+        if on && !self.beeping && !self.muted {
+            let _ = self.stdout.write_all(b"\x07").and_then(|_| self.stdout.flush());
+        }
+        self.beeping = on;
+    }
+
+    fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+}
+
+//CHIP-8 keys held down. Keys time out until the terminal has shown it reports releases by sending one.
+struct HeldKeys {
+    pressed_at: [Option<Instant>; 16],
+    release_seen: bool,
+}
+
+impl HeldKeys {
+    fn new() -> Self {
+        HeldKeys{pressed_at: [None; 16], release_seen: false}
+    }
+
+    fn press(&mut self, key: usize, now: Instant) {
+        self.pressed_at[key] = Some(now);
+    }
+
+    fn release(&mut self, key: usize) {
+        self.pressed_at[key] = None;
+        self.release_seen = true;
+    }
+
+    fn held(&self, now: Instant) -> [bool; 16] {
+        self.pressed_at.map(|pressed_at| pressed_at.is_some_and(|pressed_at| {
+            self.release_seen || now.duration_since(pressed_at) < KEY_HOLD_DURATION
+        }))
+    }
+}
+
+pub struct TerminalInput {
+    keys: HeldKeys,
+    actions: Vec<WindowAction>,
+    //The kitty keyboard protocol flags were pushed and have to be popped
+    enhanced: bool,
+}
+
+impl TerminalInput {
+    pub fn new() -> io::Result<Self> {
+        acquire_raw_mode()?;
+        //Terminals with the kitty keyboard protocol report releases, but for text keys like the hex digits
+        //only when every key is sent as an escape code
+        let flags = KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES | KeyboardEnhancementFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES
+            | KeyboardEnhancementFlags::REPORT_EVENT_TYPES;
+        let enhanced = terminal::supports_keyboard_enhancement().unwrap_or(false)
+            && execute!(io::stdout(), PushKeyboardEnhancementFlags(flags)).is_ok();
+        Ok(TerminalInput{keys: HeldKeys::new(), actions: Vec::new(), enhanced})
+    }
+
+    fn read_events(&mut self) {
+        while let Ok(true) = event::poll(Duration::ZERO) {
+            match event::read() {
+                Ok(Event::Key(key_event)) => self.handle_key_event(key_event),
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent) {
+        let ctrl_c = key_event.code == KeyCode::Char('c') && key_event.modifiers.contains(KeyModifiers::CONTROL);
+        if (ctrl_c || key_event.code == KeyCode::Esc) && key_event.kind != KeyEventKind::Release {
+            self.actions.push(WindowAction::Close);
+            return;
+        }
+        if key_event.kind != KeyEventKind::Release {
+            match key_event.code {
+                KeyCode::Char('p') => return self.actions.push(WindowAction::NextPalette),
+                KeyCode::F(12) => return self.actions.push(WindowAction::Screenshot),
+                KeyCode::F(9) => return self.actions.push(WindowAction::ToggleRecording),
+                KeyCode::Char('m') => return self.actions.push(WindowAction::ToggleMute),
+                KeyCode::F(8) => return self.actions.push(WindowAction::ToggleAudioCapture),
+                KeyCode::F(5) => return self.actions.push(WindowAction::TogglePause),
+                KeyCode::F(6) => return self.actions.push(WindowAction::AdvanceFrame),
+                KeyCode::Tab => return self.actions.push(WindowAction::ToggleFastForward),
+                KeyCode::F(7) => return self.actions.push(WindowAction::ToggleSlowMotion),
+                _ => {}
+            }
+        }
+        if let KeyCode::Char(c) = key_event.code {
+            if let Some(i) = c.to_digit(16) {
+                match key_event.kind {
+                    KeyEventKind::Release => self.keys.release(i as usize),
+                    _ => self.keys.press(i as usize, Instant::now()),
+                }
+            }
+        }
+    }
+}
+
+impl InputSource for TerminalInput {
+    fn poll_window_events(&mut self) -> Option<WindowAction> {
+        self.read_events();
+        if self.actions.contains(&WindowAction::Close) {
+            return Some(WindowAction::Close);
+        }
+        if self.actions.is_empty() { None } else { Some(self.actions.remove(0)) }
+    }
+
+    fn poll(&mut self) -> [bool; 16] {
+        self.read_events();
+        self.keys.held(Instant::now())
+    }
+}
+
+impl Drop for TerminalInput {
+    fn drop(&mut self) {
+        if self.enhanced {
+            let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+        }
+        release_raw_mode();
+    }
+}
+
+fn ansi_color(color: Rgb) -> style::Color {
+    style::Color::Rgb{r: color.0, g: color.1, b: color.2}
+}
+
+pub fn render_lines(pixels: &VRAM, glyphs: Glyphs) -> Vec<String> {
+    match glyphs {
+        Glyphs::HalfBlock => pixels.chunks(glyphs.rows_per_line()).map(|rows| {
+            rows[0].iter().zip(rows[1].iter()).map(|(&top, &bottom)| match (lit(top), lit(bottom)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            }).collect()
+        }).collect(),
+        Glyphs::Braille => pixels.chunks(glyphs.rows_per_line()).map(|rows| {
+            (0..rows[0].len() / 2).map(|column| {
+                let mut dots = 0u32;
+                for (y, row) in rows.iter().enumerate() {
+                    for x in 0..2 {
+                        if lit(row[column * 2 + x]) {
+                            dots |= braille_dot(x, y);
+                        }
+                    }
+                }
+                char::from_u32(0x2800 + dots).unwrap_or(' ')
+            }).collect()
+        }).collect(),
+    }
+}
+
+//Glyphs are on or off, so pixels fading out go dark once they are under half brightness
+fn lit(shade: u8) -> bool {
+    shade > FULL_SHADE / 2
+}
+
+fn braille_dot(x: usize, y: usize) -> u32 {
+    match (x, y) {
+        (0, 3) => 0x40,
+        (1, 3) => 0x80,
+        (0, y) => 1 << y,
+        (_, y) => 1 << (y + 3),
+    }
+}
+
+#[cfg(test)]
+#[path = "./terminal_test.rs"]
+mod terminal_test;
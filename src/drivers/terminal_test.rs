@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+
+use crate::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use crate::persistence::FULL_SHADE;
+use super::{render_lines, Glyphs, HeldKeys, KEY_HOLD_DURATION};
+
+#[test]
+fn test_render_half_block() {
+    let mut vram = [[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+    vram[0][0] = FULL_SHADE;
+    vram[1][0] = FULL_SHADE;
+    vram[0][1] = FULL_SHADE;
+    vram[1][2] = FULL_SHADE;
+    let lines = render_lines(&vram, Glyphs::HalfBlock);
+    assert_eq!(lines.len(), DISPLAY_HEIGHT / 2);
+    assert_eq!(lines[0].chars().count(), DISPLAY_WIDTH);
+    assert!(lines[0].starts_with("█▀▄ "));
+}
+
+#[test]
+fn test_render_braille() {
+    let mut vram = [[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+    vram[0][0] = FULL_SHADE;
+    vram[3][1] = FULL_SHADE;
+    for row in vram.iter_mut().take(4) {
+        row[2] = FULL_SHADE;
+        row[3] = FULL_SHADE;
+    }
+    let lines = render_lines(&vram, Glyphs::Braille);
+    assert_eq!(lines.len(), DISPLAY_HEIGHT / 4);
+    assert_eq!(lines[0].chars().count(), DISPLAY_WIDTH / 2);
+    assert!(lines[0].starts_with("⢁⣿⠀"));
+}
+
+#[test]
+fn test_render_fading_pixels() {
+    let mut vram = [[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+    vram[0][0] = FULL_SHADE / 2 + 1;
+    vram[0][1] = FULL_SHADE / 2;
+    let lines = render_lines(&vram, Glyphs::HalfBlock);
+    assert!(lines[0].starts_with("▀ "));
+}
+
+#[test]
+fn test_keys_held_until_release() {
+    let start = Instant::now();
+    let mut keys = HeldKeys::new();
+    keys.press(5, start);
+    keys.release(5);
+    keys.press(5, start);
+    assert!(keys.held(start + Duration::from_secs(5))[5]);
+    keys.release(5);
+    assert!(!keys.held(start + Duration::from_secs(5))[5]);
+}
+
+#[test]
+fn test_keys_time_out_without_releases() {
+    let start = Instant::now();
+    let mut keys = HeldKeys::new();
+    keys.press(0xA, start);
+    assert!(keys.held(start + Duration::from_millis(500))[0xA]);
+    assert!(!keys.held(start + KEY_HOLD_DURATION)[0xA]);
+}
@@ -0,0 +1,523 @@
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::cheats::{CheatConsole, Cheats};
+use crate::coverage::Coverage;
+use crate::cpu::CPU;
+use crate::gdb::{self, DebugCommand, GdbStub};
+use crate::drivers::{AudioSink, CartridgeOptions, InputSource, MemoryState, Platform, RomError, VideoSink, WindowAction, ROM};
+use crate::pacing::{FastForward, Pacing};
+use crate::palette::Palette;
+use crate::persistence::{AntiFlicker, Persistence};
+use crate::profiler::Profiler;
+use crate::recording::{RecordFormat, Recorder};
+use crate::screenshot;
+use crate::timing::Timing;
+use crate::tone::ToneSettings;
+use crate::watch::FileWatcher;
+use crate::wav::AudioCapture;
+use crate::ALL_ROWS;
+
+//Instructions run per 60 Hz frame when running one instruction per tick
+pub const TICKS_PER_FRAME: u32 = 8;
+const FRAMES_PER_SECOND: u64 = 60;
+//How long a notice stays on screen
+const NOTICE_DURATION: Duration = Duration::from_secs(2);
+
+//Why run returned
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Exit {
+    Closed,
+    Launcher,
+}
+
+//Drives the CPU with any combination of video, audio and input backends
+pub struct Emulator {
+    cpu: CPU,
+    timing: Timing,
+    throttled: bool,
+    pacing: Pacing,
+    persistence: Persistence,
+    palettes: Vec<Palette>,
+    palette: usize,
+    rom_path: String,
+    //What the ROM is loaded as when it is reloaded
+    platform: Platform,
+    screenshot_scale: usize,
+    recorder: Option<Recorder>,
+    record_format: RecordFormat,
+    record_scale: usize,
+    tone: ToneSettings,
+    muted: bool,
+    audio_capture: Option<AudioCapture>,
+    gdb: Option<GdbStub>,
+    //Stopped by the debugger
+    debug_halted: bool,
+    cheats: Cheats,
+    //Where cheat files are looked up, again when the ROM is reloaded
+    cheats_dir: Option<String>,
+    cheat_console: Option<CheatConsole>,
+    ticks_per_frame: u32,
+    //Escape leaves the ROM for the launcher
+    launcher: bool,
+    //Reloads the ROM from rom_path when the file is saved
+    watcher: Option<FileWatcher>,
+    //Breakpoints, watchpoints and the tickrate carry over to the reloaded ROM
+    keep_settings: bool,
+    //When the notice on screen is taken down
+    notice_until: Option<Instant>,
+}
+
+impl Emulator {
+    pub fn new(cpu: CPU, timing: Timing) -> Self {
+        Emulator{cpu, timing, throttled: true, pacing: Pacing::new(FastForward::Multiplier(4), 4), persistence: Persistence::new(AntiFlicker::Off),
+            palettes: Palette::builtin(), palette: 0, rom_path: String::new(), platform: Platform::Chip8, screenshot_scale: 1,
+            recorder: None, record_format: RecordFormat::Gif, record_scale: 1, tone: ToneSettings::new(), muted: false, audio_capture: None,
+            gdb: None, debug_halted: false, cheats: Cheats::new(), cheats_dir: None, cheat_console: None, ticks_per_frame: TICKS_PER_FRAME, launcher: false,
+            watcher: None, keep_settings: false, notice_until: None}
+    }
+
+    //The palette hotkey cycles through palettes starting from the current one
+    pub fn set_palettes(&mut self, palettes: Vec<Palette>, current: usize) {
+        self.palettes = palettes;
+        self.palette = current;
+    }
+
+    //Instructions per frame with instruction timing, as Octo cartridges set with their tickrate
+    pub fn set_ticks_per_frame(&mut self, ticks: u32) {
+        self.ticks_per_frame = ticks;
+    }
+
+    //Captures are named after the ROM
+    pub fn set_rom_path(&mut self, rom_path: &str) {
+        self.rom_path = rom_path.to_string();
+    }
+
+    pub fn set_platform(&mut self, platform: Platform) {
+        self.platform = platform;
+    }
+
+    pub fn set_screenshot_scale(&mut self, scale: usize) {
+        self.screenshot_scale = scale;
+    }
+
+    //Saves the frame currently presented, in the active palette
+    pub fn screenshot(&self, path: &Path, scale: usize) -> io::Result<()> {
+        screenshot::save_png(path, self.persistence.shades(), &self.palettes[self.palette], scale)
+    }
+
+    //Format and scale of recordings started with the hotkey
+    pub fn set_record_options(&mut self, format: RecordFormat, scale: usize) {
+        self.record_format = format;
+        self.record_scale = scale;
+    }
+
+    //Recordings with sound render the same tone the audio device plays
+    pub fn set_tone(&mut self, tone: ToneSettings) {
+        self.tone = tone;
+    }
+
+    pub fn start_recording(&mut self, path: &Path) -> io::Result<()> {
+        self.stop_recording()?;
+        self.recorder = Some(Recorder::start(path, &self.palettes[self.palette], self.record_scale, self.tone)?);
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) -> io::Result<()> {
+        match self.recorder.take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
+        }
+    }
+
+    //Captures the buzzer in emulated time, so it matches the ROM even when running unthrottled or muted
+    pub fn start_audio_capture(&mut self, path: &Path) -> io::Result<()> {
+        self.stop_audio_capture()?;
+        //The sound timer only changes once a frame, whichever timing runs the instructions
+        self.audio_capture = Some(AudioCapture::create(path, self.tone, FRAMES_PER_SECOND)?);
+        Ok(())
+    }
+
+    pub fn stop_audio_capture(&mut self) -> io::Result<()> {
+        match self.audio_capture.take() {
+            Some(capture) => capture.finish(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn set_anti_flicker(&mut self, mode: AntiFlicker) {
+        self.persistence = Persistence::new(mode);
+    }
+
+    //Speeds used by the fast-forward and slow-motion hotkeys
+    pub fn set_speeds(&mut self, fast_forward: FastForward, slow_motion: u32) {
+        self.pacing = Pacing::new(fast_forward, slow_motion);
+    }
+
+    //The CPU waits for the debugger to attach before running
+    pub fn set_gdb(&mut self, stub: GdbStub) {
+        self.gdb = Some(stub);
+        self.debug_halted = true;
+    }
+
+    //Applied before every frame
+    pub fn set_cheats(&mut self, cheats: Cheats) {
+        self.cheats = cheats;
+    }
+
+    //Loads the cheats saved for the ROM in the directory, which is kept for when the ROM is reloaded
+    pub fn load_cheats(&mut self, cheats_dir: &str, rom: &[u8]) {
+        self.cheats_dir = Some(cheats_dir.to_string());
+        match Cheats::load(&Cheats::path_for(cheats_dir, rom)) {
+            Ok(cheats) => {
+                if !cheats.is_empty() {
+                    println!("Loaded {} cheats", cheats.len());
+                }
+                self.cheats = cheats;
+            }
+            Err(error) => {
+                println!("ERROR {:?}", error);
+                self.cheats = Cheats::new();
+            }
+        }
+    }
+
+    //Takes the tickrate and palette from an Octo cartridge and warns about the quirks it sets that are not emulated
+    pub fn apply_cartridge_options(&mut self, options: &CartridgeOptions) {
+        if let Some(tickrate) = options.tickrate {
+            self.ticks_per_frame = tickrate;
+        }
+        if let Some(palette) = &options.palette {
+            self.palette = match self.palettes.iter().position(|existing| existing.name == palette.name) {
+                Some(index) => {
+                    self.palettes[index] = palette.clone();
+                    index
+                }
+                None => {
+                    self.palettes.push(palette.clone());
+                    self.palettes.len() - 1
+                }
+            };
+        }
+        for quirk in options.unsupported_quirks() {
+            println!("WARNING The cartridge sets {}, which is not emulated", quirk);
+        }
+    }
+
+    //Commands typed on stdin edit the cheats and search memory while the ROM runs
+    pub fn set_cheat_console(&mut self, console: CheatConsole) {
+        self.cheat_console = Some(console);
+    }
+
+    pub fn take_profiler(&mut self) -> Option<Profiler> {
+        self.cpu.take_profiler()
+    }
+
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.cpu.take_coverage()
+    }
+
+    //Starts the ROM over whenever the watched file is saved, reassembling it if it is Octo source.
+    //Otherwise breakpoints are cleared and the tickrate taken from the new file, as when starting up.
+    pub fn set_watcher(&mut self, watcher: FileWatcher, keep_settings: bool) {
+        self.watcher = Some(watcher);
+        self.keep_settings = keep_settings;
+    }
+
+    //Lets run return to the launcher that started the ROM
+    pub fn set_launcher(&mut self, launcher: bool) {
+        self.launcher = launcher;
+    }
+
+    //Without throttling the emulator runs as fast as the host allows
+    pub fn set_throttled(&mut self, throttled: bool) {
+        self.throttled = throttled;
+    }
+
+    pub fn run<V: VideoSink, A: AudioSink, I: InputSource>(&mut self, video: &mut V, audio: &mut A, input: &mut I) -> Exit {
+        video.set_palette(&self.palettes[self.palette]);
+        let mut exit = Exit::Closed;
+        'running: loop {
+            while let Some(action) = input.poll_window_events() {
+                match action {
+                    WindowAction::Close => break 'running,
+                    WindowAction::ShowLauncher if self.launcher => {
+                        exit = Exit::Launcher;
+                        break 'running;
+                    }
+                    WindowAction::ShowLauncher | WindowAction::Launcher(_) => {}
+                    WindowAction::Redraw => video.draw(self.persistence.shades(), ALL_ROWS),
+                    WindowAction::NextPalette => {
+                        self.palette = (self.palette + 1) % self.palettes.len();
+                        video.set_palette(&self.palettes[self.palette]);
+                        video.draw(self.persistence.shades(), ALL_ROWS);
+                    }
+                    WindowAction::Screenshot => {
+                        let name = screenshot::capture_name(&self.rom_path, "png");
+                        match self.screenshot(Path::new(&name), self.screenshot_scale) {
+                            Ok(()) => println!("Saved screenshot {}", name),
+                            Err(error) => println!("ERROR {:?}", error),
+                        }
+                    }
+                    WindowAction::ToggleRecording => self.toggle_recording(),
+                    WindowAction::ToggleAudioCapture => self.toggle_audio_capture(),
+                    WindowAction::ToggleMute => {
+                        self.muted = !self.muted;
+                        audio.set_muted(self.muted);
+                    }
+                    WindowAction::TogglePause => self.pacing.toggle_pause(),
+                    WindowAction::AdvanceFrame => self.pacing.advance_frame(),
+                    WindowAction::ToggleFastForward => self.pacing.toggle_fast_forward(),
+                    WindowAction::ToggleSlowMotion => self.pacing.toggle_slow_motion(),
+                    WindowAction::Memory(key) => {
+                        if let Some((address, value)) = video.edit_memory(key, &self.memory_state()) {
+                            self.cpu.write_memory(address, &[value]);
+                        }
+                    }
+                }
+            }
+            if let Some(console) = self.cheat_console.as_ref() {
+                while let Some(line) = console.poll() {
+                    println!("{}", self.cheats.command(&line, &mut self.cpu));
+                }
+            }
+            if self.watcher.as_mut().is_some_and(FileWatcher::changed) {
+                self.reload(video);
+            }
+            let keypad = input.poll();
+            self.poll_debugger(video, &keypad);
+            let frames = if self.debug_halted { 0 } else { self.pacing.frames_due() };
+            if frames == 0 {
+                //Paused, the buzzer should not hold a note
+                audio.set_beep(false);
+            }
+            for _ in 0..frames {
+                self.run_frame(video, audio, &keypad);
+                if self.debug_halted {
+                    break;
+                }
+            }
+            if let Some(status) = self.pacing.status() {
+                video.set_status(&status);
+            }
+            video.show_memory(&self.memory_state());
+            if self.notice_until.is_some_and(|until| Instant::now() >= until) {
+                self.notice_until = None;
+                video.show_notice(None);
+                video.draw(self.persistence.shades(), ALL_ROWS);
+            }
+
+            let synced = self.pacing.present_due() && video.present();
+            if self.throttled {
+                self.pacing.wait(synced);
+            }
+        }
+        if let Err(error) = self.stop_recording().and_then(|_| self.stop_audio_capture()) {
+            println!("ERROR {:?}", error);
+        }
+        exit
+    }
+
+    //Loads the ROM again from rom_path and starts it over, or keeps the running one if the file does not load
+    fn reload<V: VideoSink>(&mut self, video: &mut V) {
+        let rom = match ROM::from(&self.rom_path, self.platform) {
+            Ok(rom) => rom,
+            Err(error) => {
+                println!("ERROR {:?}", error);
+                let notice = match error {
+                    RomError::Assembly(error) => format!("Line {}: {}", error.line, error.message),
+                    _ => String::from("Reload failed"),
+                };
+                return self.notify(video, &notice);
+            }
+        };
+        if let Some(warning) = rom.warning() {
+            println!("WARNING {}", warning);
+        }
+        self.cpu.reload(&rom.data, self.keep_settings);
+        if !self.keep_settings {
+            self.ticks_per_frame = TICKS_PER_FRAME;
+            if let Some(options) = &rom.options {
+                self.apply_cartridge_options(options);
+            }
+            video.set_palette(&self.palettes[self.palette]);
+        }
+        //Cheat files are per ROM, so an edited program has cheats of its own
+        if let Some(cheats_dir) = self.cheats_dir.clone() {
+            self.load_cheats(&cheats_dir, &rom.data);
+        }
+        //Coverage of the old program would not line up with the new one
+        if self.cpu.take_coverage().is_some() {
+            self.cpu.set_coverage(Some(Coverage::new(&rom.data)));
+        }
+        self.persistence.immediate(self.cpu.vram(), ALL_ROWS);
+        let name = Path::new(&self.rom_path).file_name().map_or(self.rom_path.clone(), |name| name.to_string_lossy().into_owned());
+        self.notify(video, &format!("Reloaded {}", name));
+    }
+
+    //Prints the message and shows it over the display for a moment
+    fn notify<V: VideoSink>(&mut self, video: &mut V, message: &str) {
+        println!("{}", message);
+        video.show_notice(Some(message));
+        video.draw(self.persistence.shades(), ALL_ROWS);
+        self.notice_until = Some(Instant::now() + NOTICE_DURATION);
+    }
+
+    fn memory_state(&self) -> MemoryState<'_> {
+        let registers = self.cpu.registers();
+        MemoryState{memory: self.cpu.memory(), pc: registers.pc as usize, i: registers.i as usize, stack: self.cpu.stack(),
+            paused: self.debug_halted || self.pacing.is_paused()}
+    }
+
+    //Runs one 60 Hz frame worth of instructions and presents the result
+    fn run_frame<V: VideoSink, A: AudioSink>(&mut self, video: &mut V, audio: &mut A, keypad: &[bool; 16]) {
+        let mut vram_changed = false;
+        let mut dirty_rows = 0;
+        let mut instructions = 0;
+        let mut beep = false;
+        let ticks = match self.timing {
+            Timing::Instruction => self.ticks_per_frame,
+            Timing::CosmacVip => 1,
+        };
+        self.cheats.apply(&mut self.cpu);
+        for _ in 0..ticks {
+            let output = match self.timing {
+                Timing::Instruction => self.cpu.tick(keypad),
+                Timing::CosmacVip => self.cpu.run_frame(keypad),
+            };
+            vram_changed |= output.vram_changed;
+            dirty_rows |= output.dirty_rows;
+            instructions += output.instructions;
+            beep = output.beep;
+            if output.breakpoint && self.gdb.is_some() {
+                self.debug_halted = true;
+                if let Some(gdb) = self.gdb.as_mut() {
+                    gdb.stopped(gdb::SIGTRAP);
+                }
+                break;
+            }
+        }
+
+        if self.persistence.presents_on_vblank() {
+            let (frame, dirty_rows) = self.persistence.vblank(self.cpu.vram());
+            if dirty_rows != 0 {
+                video.draw(frame, dirty_rows);
+            }
+        } else if vram_changed {
+            let (frame, dirty_rows) = self.persistence.immediate(self.cpu.vram(), dirty_rows);
+            video.draw(frame, dirty_rows);
+        }
+
+        audio.set_beep(beep);
+        self.capture_audio(beep);
+        self.record_frame(beep);
+        self.pacing.count_frame(instructions);
+        self.cpu.end_frame();
+    }
+
+    fn poll_debugger<V: VideoSink>(&mut self, video: &mut V, keypad: &[bool; 16]) {
+        let Some(mut gdb) = self.gdb.take() else {
+            return;
+        };
+        while let Some(command) = gdb.poll(&mut self.cpu) {
+            match command {
+                DebugCommand::Attach => self.debug_halted = true,
+                DebugCommand::Continue => {
+                    //Move off the breakpoint the CPU is stopped at, or it would stop there again
+                    if self.cpu.at_breakpoint() {
+                        self.debug_step(video, keypad);
+                    }
+                    self.debug_halted = false;
+                }
+                DebugCommand::Step => {
+                    self.debug_step(video, keypad);
+                    gdb.stopped(gdb::SIGTRAP);
+                }
+                DebugCommand::StepBack | DebugCommand::ReverseContinue => {
+                    let stopped = match command {
+                        DebugCommand::StepBack => self.cpu.step_back(),
+                        _ => self.cpu.reverse_continue(),
+                    };
+                    let (frame, dirty_rows) = self.persistence.immediate(self.cpu.vram(), ALL_ROWS);
+                    video.draw(frame, dirty_rows);
+                    if stopped {
+                        gdb.stopped(gdb::SIGTRAP);
+                    } else {
+                        gdb.history_exhausted();
+                    }
+                }
+                DebugCommand::Interrupt => {
+                    self.debug_halted = true;
+                    gdb.stopped(gdb::SIGINT);
+                }
+                DebugCommand::Detach => {
+                    self.cpu.clear_breakpoints();
+                    self.debug_halted = false;
+                }
+            }
+        }
+        self.gdb = Some(gdb);
+    }
+
+    //Runs one instruction and shows what it drew right away
+    fn debug_step<V: VideoSink>(&mut self, video: &mut V, keypad: &[bool; 16]) {
+        let output = self.cpu.step_instruction(keypad);
+        if output.vram_changed {
+            let (frame, dirty_rows) = self.persistence.immediate(self.cpu.vram(), output.dirty_rows);
+            video.draw(frame, dirty_rows);
+        }
+    }
+
+    fn toggle_recording(&mut self) {
+        if self.recorder.is_some() {
+            match self.stop_recording() {
+                Ok(()) => println!("Stopped recording"),
+                Err(error) => println!("ERROR {:?}", error),
+            }
+        } else {
+            let name = screenshot::capture_name(&self.rom_path, self.record_format.extension());
+            match self.start_recording(Path::new(&name)) {
+                Ok(()) => println!("Recording to {}", name),
+                Err(error) => println!("ERROR {:?}", error),
+            }
+        }
+    }
+
+    fn toggle_audio_capture(&mut self) {
+        if self.audio_capture.is_some() {
+            match self.stop_audio_capture() {
+                Ok(()) => println!("Stopped audio capture"),
+                Err(error) => println!("ERROR {:?}", error),
+            }
+        } else {
+            let name = screenshot::capture_name(&self.rom_path, "wav");
+            match self.start_audio_capture(Path::new(&name)) {
+                Ok(()) => println!("Capturing audio to {}", name),
+                Err(error) => println!("ERROR {:?}", error),
+            }
+        }
+    }
+
+    fn capture_audio(&mut self, beep: bool) {
+        if let Some(capture) = self.audio_capture.as_mut() {
+            if let Err(error) = capture.tick(beep) {
+                println!("ERROR {:?}", error);
+                self.audio_capture = None;
+            }
+        }
+    }
+
+    fn record_frame(&mut self, beep: bool) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(error) = recorder.frame(self.persistence.shades(), &self.palettes[self.palette], beep) {
+                println!("ERROR {:?}", error);
+                self.recorder = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./emulator_test.rs"]
+mod emulator_test;
@@ -0,0 +1,135 @@
+use std::env;
+use std::fs;
+
+use crate::cheats::Cheats;
+use crate::cpu::CPU;
+use crate::drivers::{NullAudio, NullInput, NullVideo};
+use crate::persistence::{AntiFlicker, FULL_SHADE};
+use crate::timing::Timing;
+use crate::tone::ToneSettings;
+use super::{Emulator, TICKS_PER_FRAME};
+
+#[test]
+fn test_run_with_null_backend() {
+    let mut cpu = CPU::new();
+    //Draw a sprite at (0, 0), set the sound timer, then loop forever
+    cpu.load(&[0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05, 0x61, 0x10, 0xF1, 0x18, 0x12, 0x0A]);
+    let mut emulator = Emulator::new(cpu, Timing::CosmacVip);
+    emulator.set_throttled(false);
+
+    let mut video = NullVideo::new();
+    let mut audio = NullAudio::new();
+    let mut input = NullInput::new(Some(3));
+    emulator.run(&mut video, &mut audio, &mut input);
+
+    assert_eq!(video.frames_drawn, 1);
+    assert!(audio.beeping);
+}
+
+#[test]
+fn test_anti_flicker_presents_on_vblank() {
+    let mut cpu = CPU::new();
+    //Draw and erase the same sprite every frame
+    cpu.load(&[0xA2, 0x08, 0xD0, 0x01, 0xD0, 0x01, 0x12, 0x02, 0x80]);
+    let mut emulator = Emulator::new(cpu, Timing::CosmacVip);
+    emulator.set_throttled(false);
+    emulator.set_anti_flicker(AntiFlicker::FrameOr);
+
+    let mut video = NullVideo::new();
+    let mut input = NullInput::new(Some(5));
+    emulator.run(&mut video, &mut NullAudio::new(), &mut input);
+
+    assert_eq!(video.frames_drawn, 1);
+    assert_eq!(video.vram[0][0], FULL_SHADE);
+}
+
+#[test]
+fn test_screenshot_after_run() {
+    let mut cpu = CPU::new();
+    cpu.load(&[0xA2, 0x04, 0xD0, 0x01, 0x80]);
+    let mut emulator = Emulator::new(cpu, Timing::CosmacVip);
+    emulator.set_throttled(false);
+    emulator.run(&mut NullVideo::new(), &mut NullAudio::new(), &mut NullInput::new(Some(1)));
+
+    let path = env::temp_dir().join("chip8_emulator_screenshot_test.png");
+    emulator.screenshot(&path, 3).unwrap();
+    let size = fs::metadata(&path).unwrap().len();
+    fs::remove_file(&path).unwrap();
+    assert!(size > 0);
+}
+
+#[test]
+fn test_audio_capture_in_emulated_time() {
+    let mut cpu = CPU::new();
+    //Sound the buzzer for 6 frames, then loop forever
+    cpu.load(&[0x61, 0x06, 0xF1, 0x18, 0x12, 0x04]);
+    let mut emulator = Emulator::new(cpu, Timing::CosmacVip);
+    emulator.set_throttled(false);
+    emulator.set_tone(ToneSettings{sample_rate: 6000, ..ToneSettings::new()});
+    let path = env::temp_dir().join("chip8_emulator_audio_capture_test.wav");
+    emulator.start_audio_capture(&path).unwrap();
+    emulator.run(&mut NullVideo::new(), &mut NullAudio::new(), &mut NullInput::new(Some(12)));
+
+    let data = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    let samples: Vec<i16> = data[44..].chunks(2).map(|c| i16::from_le_bytes([c[0], c[1]])).collect();
+    //100 samples a frame
+    assert_eq!(samples.len(), 1200);
+    assert!(samples[..600].iter().any(|&sample| sample != 0));
+    assert!(samples[700..].iter().all(|&sample| sample == 0));
+}
+
+#[test]
+fn test_instruction_timing_runs_a_batch_per_frame() {
+    let mut rom = [0x60, 0x00].repeat(7);
+    //The 9th instruction draws a sprite
+    rom.extend_from_slice(&[0xF0, 0x29, 0xD0, 0x05, 0x12, 0x12]);
+    for (frames, frames_drawn) in [(1, 0), (2, 1)] {
+        let mut cpu = CPU::new();
+        cpu.load(&rom);
+        let mut emulator = Emulator::new(cpu, Timing::Instruction);
+        emulator.set_throttled(false);
+        let mut video = NullVideo::new();
+        emulator.run(&mut video, &mut NullAudio::new(), &mut NullInput::new(Some(frames)));
+        assert_eq!(video.frames_drawn, frames_drawn);
+    }
+}
+
+#[test]
+fn test_reload_starts_the_saved_rom_over() {
+    let dir = env::temp_dir().join("chip8_emulator_reload_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("game.ch8");
+    let path = path.to_str().unwrap();
+    //Draws a pixel at the top left then loops forever
+    let rom = [0xA2, 0x06, 0xD0, 0x01, 0x12, 0x04, 0x80];
+    fs::write(path, rom).unwrap();
+    fs::write(Cheats::path_for(dir.to_str().unwrap(), &rom), "lives 300 freeze 05\n").unwrap();
+
+    let mut cpu = CPU::new();
+    //Loops forever without drawing
+    cpu.load(&[0x12, 0x00]);
+    cpu.add_breakpoint(0x300);
+    let mut emulator = Emulator::new(cpu, Timing::Instruction);
+    emulator.set_rom_path(path);
+    emulator.set_ticks_per_frame(20);
+    emulator.load_cheats(dir.to_str().unwrap(), &[0x12, 0x00]);
+    assert_eq!(emulator.cheats.len(), 0);
+
+    let mut video = NullVideo::new();
+    emulator.reload(&mut video);
+    assert!(!emulator.cpu.has_breakpoint(0x300));
+    assert_eq!(emulator.ticks_per_frame, TICKS_PER_FRAME);
+    assert_eq!(emulator.cheats.len(), 1);
+    assert!(emulator.notice_until.is_some());
+    emulator.set_throttled(false);
+    emulator.run(&mut video, &mut NullAudio::new(), &mut NullInput::new(Some(1)));
+    assert_ne!(video.vram[0][0], 0);
+
+    //A file that no longer loads leaves the running ROM alone
+    fs::write(path, []).unwrap();
+    emulator.reload(&mut video);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(emulator.cpu.memory()[0x200], 0xA2);
+}
@@ -0,0 +1,354 @@
+//GDB remote serial protocol server, so gdb can debug the ROM the emulator is running.
+//Only one debugger is served at a time, on localhost.
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::cpu::{CPU, Registers};
+
+pub const SIGINT: u8 = 2;
+pub const SIGTRAP: u8 = 5;
+
+//Register numbers as listed in the target description
+const REGISTER_I: usize = 16;
+const REGISTER_PC: usize = 17;
+const REGISTER_SP: usize = 18;
+const REGISTER_DT: usize = 19;
+const REGISTER_ST: usize = 20;
+//Bytes of all registers in the order of the g packet
+const REGISTERS_SIZE: usize = 23;
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.chip8.core">
+    <reg name="v0" bitsize="8" type="uint8" regnum="0"/>
+    <reg name="v1" bitsize="8" type="uint8"/>
+    <reg name="v2" bitsize="8" type="uint8"/>
+    <reg name="v3" bitsize="8" type="uint8"/>
+    <reg name="v4" bitsize="8" type="uint8"/>
+    <reg name="v5" bitsize="8" type="uint8"/>
+    <reg name="v6" bitsize="8" type="uint8"/>
+    <reg name="v7" bitsize="8" type="uint8"/>
+    <reg name="v8" bitsize="8" type="uint8"/>
+    <reg name="v9" bitsize="8" type="uint8"/>
+    <reg name="va" bitsize="8" type="uint8"/>
+    <reg name="vb" bitsize="8" type="uint8"/>
+    <reg name="vc" bitsize="8" type="uint8"/>
+    <reg name="vd" bitsize="8" type="uint8"/>
+    <reg name="ve" bitsize="8" type="uint8"/>
+    <reg name="vf" bitsize="8" type="uint8"/>
+    <reg name="i" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="sp" bitsize="8" type="uint8"/>
+    <reg name="dt" bitsize="8" type="uint8"/>
+    <reg name="st" bitsize="8" type="uint8"/>
+  </feature>
+</target>
+"#;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DebugCommand {
+    //A debugger connected and expects the CPU to be stopped
+    Attach,
+    Continue,
+    Step,
+    //Ctrl-C in gdb
+    Interrupt,
+    StepBack,
+    ReverseContinue,
+    //The debugger left, the CPU runs freely again
+    Detach,
+}
+
+pub struct GdbStub {
+    listener: TcpListener,
+    connection: Option<TcpStream>,
+    input: Vec<u8>,
+}
+
+impl GdbStub {
+    pub fn bind(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+        Ok(GdbStub{listener, connection: None, input: Vec::new()})
+    }
+
+    pub fn port(&self) -> io::Result<u16> {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    //Answers the packets that arrived, without blocking, until one needs the emulator to act
+    pub fn poll(&mut self, cpu: &mut CPU) -> Option<DebugCommand> {
+        let mut connection = match self.connection.take() {
+            Some(connection) => connection,
+            None => {
+                let (connection, _) = self.listener.accept().ok()?;
+                if connection.set_nonblocking(true).is_err() {
+                    return None;
+                }
+                let _ = connection.set_nodelay(true);
+                self.input.clear();
+                self.connection = Some(connection);
+                return Some(DebugCommand::Attach);
+            }
+        };
+
+        let mut buffer = [0; 1024];
+        loop {
+            match connection.read(&mut buffer) {
+                Ok(0) => return Some(DebugCommand::Detach),
+                Ok(read) => self.input.extend_from_slice(&buffer[..read]),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => return Some(DebugCommand::Detach),
+            }
+        }
+
+        let mut command = None;
+        while command.is_none() {
+            let Some(packet) = next_packet(&mut self.input) else {
+                break;
+            };
+            let (reply, packet_command) = match packet {
+                Packet::Interrupt => (None, Some(DebugCommand::Interrupt)),
+                Packet::Invalid => (Some(String::from("-")), None),
+                Packet::Data(data) => {
+                    let (reply, command) = handle_packet(&data, cpu);
+                    (Some(format!("+{}", reply.map(|reply| frame(&reply)).unwrap_or_default())), command)
+                }
+            };
+            if let Some(reply) = reply {
+                if connection.write_all(reply.as_bytes()).is_err() {
+                    return Some(DebugCommand::Detach);
+                }
+            }
+            command = packet_command;
+        }
+        if command != Some(DebugCommand::Detach) {
+            self.connection = Some(connection);
+        }
+        command
+    }
+
+    //Tells the debugger that stepping back ran out of history
+    pub fn history_exhausted(&mut self) {
+        if let Some(connection) = self.connection.as_mut() {
+            let _ = connection.write_all(frame(&format!("T{:02x}replaylog:begin;", SIGTRAP)).as_bytes());
+        }
+    }
+
+    //Tells the debugger the CPU stopped, after a step, a breakpoint or an interrupt
+    pub fn stopped(&mut self, signal: u8) {
+        if let Some(connection) = self.connection.as_mut() {
+            let _ = connection.write_all(frame(&format!("S{:02x}", signal)).as_bytes());
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Packet {
+    Data(String),
+    Interrupt,
+    //Bad checksum, the debugger is asked to send it again
+    Invalid,
+}
+
+//Takes the next complete packet off the front of the input, skipping acknowledgements
+fn next_packet(input: &mut Vec<u8>) -> Option<Packet> {
+    loop {
+        match input.first()? {
+            b'$' => break,
+            0x03 => {
+                input.remove(0);
+                return Some(Packet::Interrupt);
+            }
+            _ => {
+                input.remove(0);
+            }
+        }
+    }
+    let end = input.iter().position(|&byte| byte == b'#')?;
+    if input.len() < end + 3 {
+        return None;
+    }
+    let packet: Vec<u8> = input.drain(..end + 3).collect();
+    let data = &packet[1..end];
+    let checksum = std::str::from_utf8(&packet[end + 1..]).ok().and_then(|checksum| u8::from_str_radix(checksum, 16).ok());
+    if checksum != Some(checksum_of(data)) {
+        return Some(Packet::Invalid);
+    }
+    Some(Packet::Data(String::from_utf8_lossy(data).into_owned()))
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum: u8, &byte| sum.wrapping_add(byte))
+}
+
+fn frame(data: &str) -> String {
+    format!("${}#{:02x}", data, checksum_of(data.as_bytes()))
+}
+
+//Returns the reply, if one is due right away, and what the emulator has to do
+fn handle_packet(packet: &str, cpu: &mut CPU) -> (Option<String>, Option<DebugCommand>) {
+    let reply = match packet.as_bytes().first() {
+        Some(b'?') => format!("S{:02x}", SIGTRAP),
+        Some(b'g') => hex(&register_bytes(&cpu.registers())),
+        Some(b'G') => {
+            match parse_hex(&packet[1..]).filter(|bytes| bytes.len() == REGISTERS_SIZE) {
+                Some(bytes) => {
+                    cpu.set_registers(&registers_from(&bytes));
+                    String::from("OK")
+                }
+                None => String::from("E01"),
+            }
+        }
+        Some(b'p') => {
+            match usize::from_str_radix(&packet[1..], 16).ok().and_then(register_range) {
+                Some(range) => hex(&register_bytes(&cpu.registers())[range]),
+                None => String::from("E01"),
+            }
+        }
+        Some(b'P') => write_register(&packet[1..], cpu).unwrap_or_else(|| String::from("E01")),
+        Some(b'm') => {
+            let memory = parse_address_length(&packet[1..])
+                .and_then(|(address, length)| cpu.memory().get(address..address.checked_add(length)?));
+            match memory {
+                Some(memory) => hex(memory),
+                None => String::from("E01"),
+            }
+        }
+        Some(b'M') => {
+            let written = packet[1..].split_once(':').and_then(|(range, data)| {
+                let (address, length) = parse_address_length(range)?;
+                let data = parse_hex(data).filter(|data| data.len() == length)?;
+                cpu.write_memory(address, &data).then_some(())
+            });
+            match written {
+                Some(()) => String::from("OK"),
+                None => String::from("E01"),
+            }
+        }
+        //Write watchpoints cover each byte of the watched range
+        Some(b'Z') | Some(b'z') if packet[1..].starts_with('2') => {
+            match packet.get(3..).and_then(parse_address_length) {
+                Some((address, length)) => {
+                    for address in address..address.saturating_add(length) {
+                        if packet.starts_with('Z') {
+                            cpu.add_watchpoint(address);
+                        } else {
+                            cpu.remove_watchpoint(address);
+                        }
+                    }
+                    String::from("OK")
+                }
+                None => String::from("E01"),
+            }
+        }
+        //Software and hardware breakpoints are the same thing here
+        Some(b'Z') | Some(b'z') if packet[1..].starts_with('0') || packet[1..].starts_with('1') => {
+            match packet.get(3..).and_then(|rest| rest.split(',').next()).and_then(|address| usize::from_str_radix(address, 16).ok()) {
+                Some(address) if packet.starts_with('Z') => {
+                    cpu.add_breakpoint(address);
+                    String::from("OK")
+                }
+                Some(address) => {
+                    cpu.remove_breakpoint(address);
+                    String::from("OK")
+                }
+                None => String::from("E01"),
+            }
+        }
+        //Resuming at another address is not supported, the CPU carries on from PC
+        Some(b'c') => return (None, Some(DebugCommand::Continue)),
+        Some(b's') => return (None, Some(DebugCommand::Step)),
+        _ if packet == "bs" => return (None, Some(DebugCommand::StepBack)),
+        _ if packet == "bc" => return (None, Some(DebugCommand::ReverseContinue)),
+        Some(b'D') => return (Some(String::from("OK")), Some(DebugCommand::Detach)),
+        Some(b'k') => return (None, Some(DebugCommand::Detach)),
+        Some(b'H') => String::from("OK"),
+        _ if packet.starts_with("qSupported") => String::from("PacketSize=1000;qXfer:features:read+;ReverseStep+;ReverseContinue+"),
+        _ if packet.starts_with("qXfer:features:read:target.xml:") => {
+            match parse_address_length(&packet["qXfer:features:read:target.xml:".len()..]) {
+                Some((offset, length)) => {
+                    let xml = TARGET_XML.as_bytes();
+                    let start = offset.min(xml.len());
+                    let end = offset.saturating_add(length).min(xml.len());
+                    let more = if end < xml.len() { "m" } else { "l" };
+                    format!("{}{}", more, String::from_utf8_lossy(&xml[start..end]))
+                }
+                None => String::from("E01"),
+            }
+        }
+        _ if packet == "qAttached" => String::from("1"),
+        _ if packet == "qC" => String::from("QC1"),
+        _ if packet == "qfThreadInfo" => String::from("m1"),
+        _ if packet == "qsThreadInfo" => String::from("l"),
+        //An empty reply tells the debugger the packet is not supported
+        _ => String::new(),
+    };
+    (Some(reply), None)
+}
+
+//Multi-byte registers are sent little endian
+fn register_bytes(registers: &Registers) -> Vec<u8> {
+    let mut bytes = registers.v.to_vec();
+    bytes.extend_from_slice(&registers.i.to_le_bytes());
+    bytes.extend_from_slice(&registers.pc.to_le_bytes());
+    bytes.extend_from_slice(&[registers.sp, registers.dt, registers.st]);
+    bytes
+}
+
+fn registers_from(bytes: &[u8]) -> Registers {
+    let mut v = [0; 16];
+    v.copy_from_slice(&bytes[..16]);
+    Registers {
+        v,
+        i: u16::from_le_bytes([bytes[16], bytes[17]]),
+        pc: u16::from_le_bytes([bytes[18], bytes[19]]),
+        sp: bytes[20],
+        dt: bytes[21],
+        st: bytes[22],
+    }
+}
+
+//Where a register is in the bytes of the g packet
+fn register_range(register: usize) -> Option<std::ops::Range<usize>> {
+    match register {
+        0..=15 => Some(register..register + 1),
+        REGISTER_I => Some(16..18),
+        REGISTER_PC => Some(18..20),
+        REGISTER_SP => Some(20..21),
+        REGISTER_DT => Some(21..22),
+        REGISTER_ST => Some(22..23),
+        _ => None,
+    }
+}
+
+fn write_register(packet: &str, cpu: &mut CPU) -> Option<String> {
+    let (register, value) = packet.split_once('=')?;
+    let range = register_range(usize::from_str_radix(register, 16).ok()?)?;
+    let value = parse_hex(value).filter(|value| value.len() == range.len())?;
+    let mut bytes = register_bytes(&cpu.registers());
+    bytes[range].copy_from_slice(&value);
+    cpu.set_registers(&registers_from(&bytes));
+    Some(String::from("OK"))
+}
+
+fn parse_address_length(text: &str) -> Option<(usize, usize)> {
+    let (address, length) = text.split_once(',')?;
+    Some((usize::from_str_radix(address, 16).ok()?, usize::from_str_radix(length, 16).ok()?))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
+}
+
+#[cfg(test)]
+#[path = "./gdb_test.rs"]
+mod gdb_test;
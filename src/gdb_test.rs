@@ -0,0 +1,103 @@
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+
+use crate::cpu::CPU;
+use super::{frame, handle_packet, next_packet, DebugCommand, GdbStub, Packet, TARGET_XML};
+
+fn reply(packet: &str, cpu: &mut CPU) -> String {
+    handle_packet(packet, cpu).0.unwrap()
+}
+
+#[test]
+fn test_packet_framing() {
+    assert_eq!(frame("OK"), "$OK#9a");
+    let mut input = b"+$g#67\x03$m0,1#00".to_vec();
+    assert_eq!(next_packet(&mut input), Some(Packet::Data(String::from("g"))));
+    assert_eq!(next_packet(&mut input), Some(Packet::Interrupt));
+    assert_eq!(next_packet(&mut input), Some(Packet::Invalid));
+    assert_eq!(next_packet(&mut input), None);
+    let mut partial = b"$g#6".to_vec();
+    assert_eq!(next_packet(&mut partial), None);
+}
+
+#[test]
+fn test_registers() {
+    let mut cpu = CPU::new();
+    //V0..VF, I, PC = 0x200, SP, DT, ST
+    assert_eq!(reply("g", &mut cpu), format!("{}0000{}000000", "00".repeat(16), "0002"));
+    assert_eq!(reply("P3=2a", &mut cpu), "OK");
+    assert_eq!(reply("P11=1002", &mut cpu), "OK");
+    assert_eq!(reply("p3", &mut cpu), "2a");
+    assert_eq!(reply("p11", &mut cpu), "1002");
+    assert_eq!(cpu.registers().pc, 0x210);
+    assert_eq!(reply("p20", &mut cpu), "E01");
+}
+
+#[test]
+fn test_memory() {
+    let mut cpu = CPU::new();
+    cpu.load(&[0x12, 0x34]);
+    assert_eq!(reply("m200,2", &mut cpu), "1234");
+    assert_eq!(reply("M200,2:abcd", &mut cpu), "OK");
+    assert_eq!(reply("m200,2", &mut cpu), "abcd");
+    assert_eq!(reply("mfff,2", &mut cpu), "E01");
+    assert_eq!(reply("Mfff,2:abcd", &mut cpu), "E01");
+}
+
+#[test]
+fn test_breakpoints_and_execution() {
+    let mut cpu = CPU::new();
+    assert_eq!(reply("Z0,200,2", &mut cpu), "OK");
+    assert!(cpu.at_breakpoint());
+    assert_eq!(reply("z0,200,2", &mut cpu), "OK");
+    assert!(!cpu.at_breakpoint());
+    assert_eq!(handle_packet("c", &mut cpu), (None, Some(DebugCommand::Continue)));
+    assert_eq!(handle_packet("s", &mut cpu), (None, Some(DebugCommand::Step)));
+    assert_eq!(handle_packet("D", &mut cpu), (Some(String::from("OK")), Some(DebugCommand::Detach)));
+    assert_eq!(handle_packet("bs", &mut cpu), (None, Some(DebugCommand::StepBack)));
+    assert_eq!(handle_packet("bc", &mut cpu), (None, Some(DebugCommand::ReverseContinue)));
+    assert_eq!(reply("Z2,300,2", &mut cpu), "OK");
+    assert!(cpu.has_watchpoint(0x301));
+    assert_eq!(reply("z2,300,2", &mut cpu), "OK");
+    assert!(!cpu.has_watchpoint(0x300));
+    assert_eq!(reply("vMustReplyEmpty", &mut cpu), "");
+}
+
+#[test]
+fn test_target_description() {
+    let mut cpu = CPU::new();
+    assert!(reply("qSupported:multiprocess+", &mut cpu).contains("qXfer:features:read+"));
+    let first = reply("qXfer:features:read:target.xml:0,10", &mut cpu);
+    assert_eq!(first, format!("m{}", &TARGET_XML[..0x10]));
+    let rest = reply(&format!("qXfer:features:read:target.xml:10,{:x}", TARGET_XML.len()), &mut cpu);
+    assert_eq!(rest, format!("l{}", &TARGET_XML[0x10..]));
+}
+
+#[test]
+fn test_stub_over_tcp() {
+    let mut cpu = CPU::new();
+    let mut stub = GdbStub::bind(0).unwrap();
+    let mut client = TcpStream::connect(("127.0.0.1", stub.port().unwrap())).unwrap();
+    let mut command = None;
+    for _ in 0..100 {
+        command = stub.poll(&mut cpu);
+        if command.is_some() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(command, Some(DebugCommand::Attach));
+
+    client.write_all(b"$?#3f").unwrap();
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(stub.poll(&mut cpu), None);
+    let mut received = [0; 8];
+    client.read_exact(&mut received).unwrap();
+    assert_eq!(&received, b"+$S05#b8");
+
+    drop(client);
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(stub.poll(&mut cpu), Some(DebugCommand::Detach));
+}
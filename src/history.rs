@@ -0,0 +1,63 @@
+//Undo log of executed instructions, for stepping backwards in the debuggers
+use std::collections::VecDeque;
+
+use crate::DISPLAY_WIDTH;
+use crate::cpu::Registers;
+
+//What an instruction overwrote, enough to put the CPU back to just before it ran
+pub struct UndoEntry {
+    pub registers: Registers,
+    pub stack: [u16; 16],
+    pub waiting_for_key_press: Option<usize>,
+    pub cycle_overrun: u32,
+    //Previous values of the memory bytes written, in write order
+    pub memory: Vec<(usize, u8)>,
+    //Previous contents of the VRAM rows written
+    pub rows: Vec<(usize, [u8; DISPLAY_WIDTH])>,
+    //The instruction wrote to a watched address
+    pub watch_hit: bool,
+}
+
+pub struct History {
+    entries: VecDeque<UndoEntry>,
+    //Oldest entries are dropped past this many
+    limit: usize,
+}
+
+impl History {
+    pub fn new(limit: usize) -> Self {
+        History{entries: VecDeque::new(), limit}
+    }
+
+    pub fn push(&mut self, entry: UndoEntry) {
+        if self.entries.len() == self.limit {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn pop(&mut self) -> Option<UndoEntry> {
+        self.entries.pop_back()
+    }
+
+    //The entry of the instruction being executed
+    pub fn current(&mut self) -> Option<&mut UndoEntry> {
+        self.entries.back_mut()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+#[path = "./history_test.rs"]
+mod history_test;
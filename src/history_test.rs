@@ -0,0 +1,35 @@
+use crate::cpu::CPU;
+use super::{History, UndoEntry};
+
+fn entry(pc: u16) -> UndoEntry {
+    UndoEntry {
+        registers: crate::cpu::Registers{pc, ..CPU::new().registers()},
+        stack: [0; 16],
+        waiting_for_key_press: None,
+        cycle_overrun: 0,
+        memory: Vec::new(),
+        rows: Vec::new(),
+        watch_hit: false,
+    }
+}
+
+#[test]
+fn test_oldest_entries_are_dropped() {
+    let mut history = History::new(2);
+    history.push(entry(0x200));
+    history.push(entry(0x202));
+    history.push(entry(0x204));
+    assert_eq!(history.len(), 2);
+    assert_eq!(history.pop().unwrap().registers.pc, 0x204);
+    assert_eq!(history.pop().unwrap().registers.pc, 0x202);
+    assert!(history.pop().is_none());
+}
+
+#[test]
+fn test_current_entry() {
+    let mut history = History::new(4);
+    assert!(history.current().is_none());
+    history.push(entry(0x200));
+    history.current().unwrap().memory.push((0x300, 1));
+    assert_eq!(history.pop().unwrap().memory, vec![(0x300, 1)]);
+}
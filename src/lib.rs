@@ -0,0 +1,37 @@
+#![allow(clippy::upper_case_acronyms)]
+
+extern crate rand;
+extern crate sdl2;
+
+pub mod cheats;
+pub mod config;
+pub mod coverage;
+pub mod cpu;
+pub mod debugger;
+pub mod disassembler;
+pub mod drivers;
+pub mod emulator;
+pub mod gdb;
+pub mod history;
+pub mod library;
+pub mod octo;
+pub mod pacing;
+pub mod palette;
+pub mod persistence;
+pub mod profiler;
+pub mod recording;
+pub mod screenshot;
+pub mod wav;
+pub mod watch;
+pub mod timing;
+pub mod tone;
+pub mod trace;
+
+pub const DISPLAY_WIDTH: usize = 64;
+pub const DISPLAY_HEIGHT: usize = 32;
+pub const MEMORY_SIZE: usize = 4096;
+
+pub type VRAM = [[u8; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+//One bit per row of VRAM, wide enough for a 64 row display
+pub type DirtyRows = u64;
+pub const ALL_ROWS: DirtyRows = (1 << DISPLAY_HEIGHT) - 1;
@@ -0,0 +1,127 @@
+//ROMs found in the library directories, titled from the ROM database, and the headless runs that preview them
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+use sha1_smol::Sha1;
+
+use crate::cpu::CPU;
+use crate::drivers::{Platform, ROM};
+use crate::emulator::TICKS_PER_FRAME;
+use crate::VRAM;
+
+const EXTENSIONS: [&str; 4] = ["ch8", "c8", "rom", "xo8"];
+//Frames a thumbnail runs before it is first shown, three seconds at 60 Hz
+pub const THUMBNAIL_FRAMES: usize = 180;
+
+//Titles by the SHA-1 of the ROM, read from lines of "sha1 title" where # starts a comment line
+pub struct RomDatabase {
+    titles: HashMap<String, String>,
+}
+
+impl RomDatabase {
+    pub fn new() -> Self {
+        RomDatabase{titles: HashMap::new()}
+    }
+
+    pub fn parse(contents: &str) -> Self {
+        let titles = contents.lines().map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once(char::is_whitespace))
+            .map(|(sha1, title)| (sha1.to_lowercase(), title.trim().to_string()))
+            .collect();
+        RomDatabase{titles}
+    }
+
+    //A missing database has no titles
+    pub fn load(path: &str) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(RomDatabase::parse(&contents)),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(RomDatabase::new()),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn title(&self, data: &[u8]) -> Option<&str> {
+        self.titles.get(&Sha1::from(data).digest().to_string()).map(String::as_str)
+    }
+}
+
+impl Default for RomDatabase {
+    fn default() -> Self {
+        RomDatabase::new()
+    }
+}
+
+pub struct LibraryEntry {
+    pub path: String,
+    //From the ROM database, or the file name for unknown ROMs
+    pub title: String,
+    pub data: Vec<u8>,
+}
+
+//Every ROM in the directories, sorted by title. Subdirectories are not searched, since the default is the
+//working directory, and files that do not load as ROMs are left out. Without a platform each file is
+//loaded for the one its extension stands for.
+pub fn scan(dirs: &[String], database: &RomDatabase, platform: Option<Platform>) -> Vec<LibraryEntry> {
+    let mut entries = Vec::new();
+    for dir in dirs {
+        scan_dir(Path::new(dir), database, platform, &mut entries);
+    }
+    entries.sort_by(|a, b| a.title.to_lowercase().cmp(&b.title.to_lowercase()).then_with(|| a.path.cmp(&b.path)));
+    entries.dedup_by(|a, b| a.path == b.path);
+    entries
+}
+
+fn scan_dir(dir: &Path, database: &RomDatabase, platform: Option<Platform>, entries: &mut Vec<LibraryEntry>) {
+    let Ok(listing) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in listing.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        let is_rom = path.extension().and_then(|extension| extension.to_str())
+            .is_some_and(|extension| EXTENSIONS.contains(&extension.to_lowercase().as_str()));
+        let Some(name) = path.to_str().filter(|_| is_rom) else {
+            continue;
+        };
+        if let Ok(rom) = ROM::from(name, platform.unwrap_or_else(|| Platform::for_path(name))) {
+            let title = match database.title(&rom.data) {
+                Some(title) => title.to_string(),
+                None => path.file_stem().map_or(name.to_string(), |stem| stem.to_string_lossy().into_owned()),
+            };
+            entries.push(LibraryEntry{path: name.to_string(), title, data: rom.data});
+        }
+    }
+}
+
+//Runs a ROM headless with no keys pressed, to show what it draws
+pub struct Thumbnail {
+    cpu: CPU,
+}
+
+impl Thumbnail {
+    pub fn new(data: &[u8]) -> Self {
+        let mut cpu = CPU::new();
+        cpu.load(data);
+        let mut thumbnail = Thumbnail{cpu};
+        thumbnail.run(THUMBNAIL_FRAMES);
+        thumbnail
+    }
+
+    pub fn run(&mut self, frames: usize) {
+        for _ in 0..frames {
+            for _ in 0..TICKS_PER_FRAME {
+                self.cpu.tick(&[false; 16]);
+            }
+            self.cpu.end_frame();
+        }
+    }
+
+    pub fn vram(&self) -> &VRAM {
+        self.cpu.vram()
+    }
+}
+
+#[cfg(test)]
+#[path = "./library_test.rs"]
+mod library_test;
@@ -0,0 +1,46 @@
+use std::env;
+use std::fs;
+
+use crate::drivers::Platform;
+use super::{scan, RomDatabase, Thumbnail};
+
+#[test]
+fn test_database() {
+    let database = RomDatabase::parse("# Known ROMs\n237756A4014FB3AA82A29246A7CDD534F8DC2DBB  Breakout\n\nmalformed\n");
+    assert_eq!(database.title(&fs::read("breakout.rom").unwrap()), Some("Breakout"));
+    assert_eq!(database.title(&[0x00, 0xE0]), None);
+    assert!(RomDatabase::load("missing_roms.txt").is_ok());
+}
+
+#[test]
+fn test_scan() {
+    let dir = env::temp_dir().join("chip8_emulator_library_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("more")).unwrap();
+    fs::write(dir.join("more").join("hidden.ch8"), [0x00, 0xE0]).unwrap();
+    fs::write(dir.join("zebra.ch8"), [0x00, 0xE0]).unwrap();
+    fs::write(dir.join("apple.C8"), [0x12, 0x00]).unwrap();
+    fs::write(dir.join("game.xo8"), [0x00, 0xE0]).unwrap();
+    //Larger than memory on either platform
+    fs::write(dir.join("huge.xo8"), vec![0x00; 0x1000]).unwrap();
+    fs::write(dir.join("empty.rom"), []).unwrap();
+    fs::write(dir.join("notes.txt"), "Not a ROM").unwrap();
+    let database = RomDatabase::parse("92a5652d382a18e89c4881ec57041fc7d885ca80 Jump Loop");
+    let dirs = [dir.to_str().unwrap().to_string()];
+    let entries = scan(&dirs, &database, None);
+    let chip8_entries = scan(&dirs, &database, Some(Platform::Chip8));
+    fs::remove_dir_all(&dir).unwrap();
+    let titles: Vec<&str> = entries.iter().map(|entry| entry.title.as_str()).collect();
+    assert_eq!(titles, vec!["game", "Jump Loop", "zebra"]);
+    assert_eq!(chip8_entries.len(), 3);
+    assert_eq!(entries[2].data, vec![0x00, 0xE0]);
+}
+
+#[test]
+fn test_thumbnail() {
+    //Draws one pixel at the top left then loops forever
+    let mut thumbnail = Thumbnail::new(&[0xA2, 0x06, 0xD0, 0x11, 0x12, 0x04, 0x80]);
+    assert_eq!(thumbnail.vram()[0][0], 1);
+    thumbnail.run(1);
+    assert_eq!(thumbnail.vram()[0][1], 0);
+}
@@ -1,53 +1,256 @@
-extern crate rand;
-extern crate sdl2;
-
-mod cpu;
-mod drivers;
-
-use std::thread;
-use std::time::Duration;
-use cpu::CPU;
+use chip8_emulator::cheats::CheatConsole;
+use chip8_emulator::config::{Config, Frontend, DEFAULT_CONFIG_FILE};
+use chip8_emulator::coverage::Coverage;
+use chip8_emulator::cpu::CPU;
+use chip8_emulator::emulator::{Emulator, Exit, TICKS_PER_FRAME};
+use chip8_emulator::gdb::GdbStub;
+use chip8_emulator::library::{self, RomDatabase};
+use chip8_emulator::profiler::Profiler;
+use chip8_emulator::trace::{self, Tracer};
+use chip8_emulator::watch::FileWatcher;
+use chip8_emulator::debugger;
+use chip8_emulator::{DISPLAY_WIDTH, DISPLAY_HEIGHT, ALL_ROWS};
 use std::env;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
 
-use drivers::{Display, Input, ROM, WindowType, WindowAction, Audio};
+use chip8_emulator::drivers::{Display, Input, Launcher, Platform, RomError, ROM, WindowType, Audio, TerminalVideo, TerminalBell, TerminalInput, NullVideo, NullAudio, NullInput, VideoSink, AudioSink};
 
-const DISPLAY_WIDTH: usize = 64;
-const DISPLAY_HEIGHT: usize = 32;
-const MEMORY_SIZE: usize = 4096;
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let config = match Config::from_args_or_file(&args, Some(DEFAULT_CONFIG_FILE)) {
+        Ok(config) => config,
+        Err(error) => {
+            println!("ERROR {:?}", error);
+            return;
+        }
+    };
 
-type VRAM = [[u8; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+    if let Some(path) = &config.decode_trace {
+        let result = File::open(path).and_then(|mut file| trace::binary_to_text(&mut file, &mut io::stdout()));
+        if let Err(error) = result {
+            println!("ERROR {:?}", error);
+        }
+        return;
+    }
 
-fn main() {
-    let sleep_duration = Duration::from_millis(2);
+    if config.launcher {
+        if let Err(error) = run_launcher(&config) {
+            println!("ERROR {:?}", error);
+        }
+        return;
+    }
 
-    let sdl_context = sdl2::init().unwrap();
+    let cheat_console = config.cheat_console.then(CheatConsole::start);
+    let Some(mut emulator) = start(&config, &config.rom_path, cheat_console) else {
+        return;
+    };
+    let result = match config.frontend {
+        Frontend::Sdl => run_sdl(&config, &mut emulator),
+        Frontend::Terminal => run_terminal(&config, &mut emulator),
+        Frontend::Null => run_null(&config, &mut emulator),
+    };
+    if let Err(error) = result {
+        println!("ERROR {:?}", error);
+    }
 
-    // let args: Vec<String> = env::args().collect();
-    let rom_path = "tetris.rom";//&args[1];
-    let scale = 5; //&args[2];
+    finish(&config, &config.rom_path, &mut emulator);
+}
 
-    let mut display = Display::from(&sdl_context, scale);
-    let audio = Audio::new(&sdl_context);
-    let mut input = Input::from(&sdl_context, display.get_window_id(WindowType::Keypad), display.get_window_id(WindowType::Display));
-    let rom = ROM::from(rom_path);
+//Loads the ROM and sets up the emulator as configured. None when the ROM cannot run or the debugger already ran it.
+fn start(config: &Config, rom_path: &str, cheat_console: Option<CheatConsole>) -> Option<Emulator> {
+    let platform = config.platform.unwrap_or_else(|| Platform::for_path(rom_path));
+    let rom = match load_rom(rom_path, platform) {
+        Ok(rom) => rom,
+        Err(error) => {
+            println!("ERROR {:?}", error);
+            return None;
+        }
+    };
+    if let Some(warning) = rom.warning() {
+        println!("WARNING {}", warning);
+    }
     let mut cpu = CPU::new();
 
-    cpu.load(&rom.rom);
+    cpu.load(&rom.data);
 
-    loop {
-        if let Some(window_action) = input.poll_window_events() {
-            if window_action == WindowAction::Close {
-                break;
+    if let Some(path) = &config.trace_path {
+        match Tracer::create(path, config.trace_format, config.trace_filter.clone()) {
+            Ok(tracer) => cpu.set_tracer(Some(tracer)),
+            Err(error) => {
+                println!("ERROR {:?}", error);
+                return None;
             }
         }
-        let keypad = input.poll();
-        let output = cpu.tick(&keypad);
-        if output.vram_changed {
-            display.draw(output.vram);
+    }
+    if config.profile_path.is_some() || config.profile_folded.is_some() {
+        cpu.set_profiler(Some(Profiler::new()));
+    }
+    if config.coverage_path.is_some() {
+        cpu.set_coverage(Some(Coverage::new(&rom.data)));
+    }
+    if config.debugger || config.gdb_port.is_some() {
+        cpu.set_history_limit(config.history);
+    }
+    if config.debugger {
+        let ticks_per_frame = rom.options.as_ref().and_then(|options| options.tickrate).unwrap_or(TICKS_PER_FRAME);
+        match debugger::run(cpu, config.timing, ticks_per_frame) {
+            Ok(mut cpu) => save_reports(config, rom_path, cpu.take_profiler(), cpu.take_coverage()),
+            Err(error) => println!("ERROR {:?}", error),
         }
+        return None;
+    }
 
-        if output.beep {audio.start_beep()} else {audio.stop_beep()}
+    let mut emulator = Emulator::new(cpu, config.timing);
+    emulator.set_anti_flicker(config.anti_flicker);
+    emulator.set_speeds(config.fast_forward, config.slow_motion);
+    emulator.set_palettes(config.palettes.clone(), config.palette);
+    if let Some(options) = &rom.options {
+        emulator.apply_cartridge_options(options);
+    }
+    emulator.set_rom_path(rom_path);
+    emulator.set_platform(platform);
+    emulator.set_screenshot_scale(config.screenshot_scale);
+    emulator.set_record_options(config.record_format, config.record_scale);
+    emulator.set_tone(config.tone);
+    if let Some(path) = &config.record_path {
+        if let Err(error) = emulator.start_recording(Path::new(path)) {
+            println!("ERROR {:?}", error);
+            return None;
+        }
+    }
 
-        thread::sleep(sleep_duration);
+    emulator.load_cheats(&config.cheats_dir, &rom.data);
+    if let Some(console) = cheat_console {
+        emulator.set_cheat_console(console);
+    }
+    if config.watch {
+        match FileWatcher::new(rom_path) {
+            Ok(watcher) => emulator.set_watcher(watcher, config.watch_keep_settings),
+            Err(error) => println!("ERROR {:?}", error),
+        }
+    }
+
+    if let Some(path) = &config.wav_path {
+        if let Err(error) = emulator.start_audio_capture(Path::new(path)) {
+            println!("ERROR {:?}", error);
+            return None;
+        }
+    }
+
+    if let Some(port) = config.gdb_port {
+        match GdbStub::bind(port) {
+            Ok(stub) => {
+                println!("Waiting for gdb on 127.0.0.1:{}", stub.port().unwrap_or(port));
+                emulator.set_gdb(stub);
+            }
+            Err(error) => {
+                println!("ERROR {:?}", error);
+                return None;
+            }
+        }
     }
+    Some(emulator)
+}
+
+//Writes the reports collected while the ROM ran
+fn finish(config: &Config, rom_path: &str, emulator: &mut Emulator) {
+    save_reports(config, rom_path, emulator.take_profiler(), emulator.take_coverage());
+}
+
+fn save_reports(config: &Config, rom_path: &str, profiler: Option<Profiler>, coverage: Option<Coverage>) {
+    if let Some(profiler) = profiler {
+        let saved = config.profile_path.as_ref().map_or(Ok(()), |path| profiler.save_report(path))
+            .and_then(|_| config.profile_folded.as_ref().map_or(Ok(()), |path| profiler.save_folded(path)));
+        if let Err(error) = saved {
+            println!("ERROR {:?}", error);
+        }
+    }
+    if let (Some(coverage), Some(path)) = (coverage, &config.coverage_path) {
+        if let Err(error) = coverage.save(path, config.coverage_format, rom_path) {
+            println!("ERROR {:?}", error);
+        }
+    }
+}
+
+//Asks which ROM to run when an archive holds several
+fn load_rom(path: &str, platform: Platform) -> Result<ROM, RomError> {
+    match ROM::from(path, platform) {
+        Err(RomError::SeveralRoms(names)) => {
+            println!("The archive holds several ROMs:");
+            for (number, name) in names.iter().enumerate() {
+                println!("{:>3} {}", number + 1, name);
+            }
+            print!("Run which one? ");
+            let mut line = String::new();
+            let _ = io::stdout().flush();
+            let _ = io::stdin().read_line(&mut line);
+            let choice = line.trim().parse::<usize>().ok().and_then(|number| names.get(number.checked_sub(1)?));
+            match choice {
+                Some(name) => ROM::from_entry(path, name, platform),
+                None => Err(RomError::SeveralRoms(names)),
+            }
+        }
+        result => result,
+    }
+}
+
+fn open_sdl(config: &Config, sdl_context: &sdl2::Sdl) -> (Display, Audio, Input) {
+    let mut display = Display::from(sdl_context, config.scale, config.scaling);
+    if config.memory_viewer {
+        display.open_memory_window(sdl_context);
+    }
+    let audio = Audio::new(sdl_context, config.tone);
+    let mut input = Input::from(sdl_context, display.get_window_id(WindowType::Keypad), display.get_window_id(WindowType::Display));
+    input.set_memory_window_id(display.memory_window_id());
+    (display, audio, input)
+}
+
+fn run_sdl(config: &Config, emulator: &mut Emulator) -> Result<(), String> {
+    let sdl_context = sdl2::init()?;
+    let (mut display, mut audio, mut input) = open_sdl(config, &sdl_context);
+
+    emulator.run(&mut display, &mut audio, &mut input);
+    Ok(())
+}
+
+//Picks ROMs from the library in the SDL window until it is closed, coming back to it when a ROM is left with Escape
+fn run_launcher(config: &Config) -> Result<(), String> {
+    let database = RomDatabase::load(&config.rom_database).map_err(|error| error.to_string())?;
+    let entries = library::scan(&config.library_dirs, &database, config.platform);
+    let mut launcher = Launcher::new(entries, config.palettes[config.palette].clone());
+    let sdl_context = sdl2::init()?;
+    let (mut display, mut audio, mut input) = open_sdl(config, &sdl_context);
+    let cheat_console = config.cheat_console.then(CheatConsole::start);
+
+    while let Some(rom_path) = launcher.choose(&mut display, &mut input) {
+        let Some(mut emulator) = start(config, &rom_path, cheat_console.clone()) else {
+            continue;
+        };
+        emulator.set_launcher(true);
+        display.draw(&[[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT], ALL_ROWS);
+        let exit = emulator.run(&mut display, &mut audio, &mut input);
+        audio.set_beep(false);
+        finish(config, &rom_path, &mut emulator);
+        if exit == Exit::Closed {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn run_terminal(config: &Config, emulator: &mut Emulator) -> Result<(), String> {
+    let mut video = TerminalVideo::new(config.glyphs).map_err(|error| error.to_string())?;
+    let mut bell = TerminalBell::new();
+    let mut input = TerminalInput::new().map_err(|error| error.to_string())?;
+
+    emulator.run(&mut video, &mut bell, &mut input);
+    Ok(())
+}
+
+fn run_null(config: &Config, emulator: &mut Emulator) -> Result<(), String> {
+    emulator.set_throttled(config.frames.is_none());
+    emulator.run(&mut NullVideo::new(), &mut NullAudio::new(), &mut NullInput::new(config.frames));
+    Ok(())
 }
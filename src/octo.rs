@@ -0,0 +1,442 @@
+//Assembler for the plain CHIP-8 subset of Octo, the language of the Octo IDE, for cartridges and
+//.8o sources that keep to it. As in Octo, the program starts with a jump to the label main.
+//Macros, :calc and the other directives, strings, the comparisons that need VF and the SUPER-CHIP
+//and XO-CHIP instructions are reported as unsupported Octo features rather than assembled.
+use std::collections::HashMap;
+
+use crate::MEMORY_SIZE;
+
+const PROGRAM_START: usize = 0x200;
+//SUPER-CHIP and XO-CHIP statements, which have no CHIP-8 encoding
+const EXTENDED_STATEMENTS: [&str; 14] = ["hires", "lores", "scroll-down", "scroll-up", "scroll-left", "scroll-right",
+    "exit", "saveflags", "loadflags", "plane", "audio", "pitch", "bighex", "long"];
+
+#[derive(Debug, PartialEq)]
+pub struct AssemblyError {
+    pub line: usize,
+    pub message: String,
+}
+
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+}
+
+#[derive(Copy, Clone)]
+enum Condition {
+    Equal(u8, Operand),
+    NotEqual(u8, Operand),
+    Key(u8),
+    NotKey(u8),
+}
+
+#[derive(Copy, Clone)]
+enum Operand {
+    Register(u8),
+    Byte(u8),
+}
+
+//A control structure waiting for its end, with the jumps to patch when it arrives
+enum Block {
+    If(usize),
+    Else(usize),
+    Loop(usize, Vec<usize>),
+}
+
+struct Assembler<'a> {
+    tokens: Vec<Token<'a>>,
+    position: usize,
+    //Memory from PROGRAM_START up to the last byte written
+    rom: Vec<u8>,
+    here: usize,
+    labels: HashMap<&'a str, usize>,
+    constants: HashMap<&'a str, i64>,
+    aliases: HashMap<&'a str, u8>,
+    //Address fields of instructions that use a label defined further on
+    fixups: Vec<(usize, Token<'a>)>,
+    blocks: Vec<Block>,
+}
+
+pub fn assemble(source: &str) -> Result<Vec<u8>, AssemblyError> {
+    let tokens = source.lines().enumerate()
+        .flat_map(|(n, line)| line.split('#').next().unwrap_or("").split_whitespace().map(move |text| Token{text, line: n + 1}))
+        .collect();
+    let mut assembler = Assembler{tokens, position: 0, rom: vec![0; 2], here: PROGRAM_START + 2, labels: HashMap::new(),
+        constants: HashMap::new(), aliases: HashMap::new(), fixups: Vec::new(), blocks: Vec::new()};
+    while assembler.position < assembler.tokens.len() {
+        assembler.statement()?;
+    }
+    assembler.finish()
+}
+
+impl<'a> Assembler<'a> {
+    fn error<T>(&self, message: String) -> Result<T, AssemblyError> {
+        let line = self.tokens.get(self.position.saturating_sub(1)).map_or(0, |token| token.line);
+        Err(AssemblyError{line, message})
+    }
+
+    fn unsupported<T>(&self, feature: &str) -> Result<T, AssemblyError> {
+        self.error(format!("Unsupported Octo feature: {}", feature))
+    }
+
+    fn next(&mut self) -> Result<&'a str, AssemblyError> {
+        match self.tokens.get(self.position) {
+            Some(token) => {
+                self.position += 1;
+                Ok(token.text)
+            }
+            None => self.error(String::from("Unexpected end of the program")),
+        }
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).map(|token| token.text)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), AssemblyError> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => self.error(format!("Expected {}, found {}", expected, token)),
+        }
+    }
+
+    fn emit_byte(&mut self, byte: u8) -> Result<(), AssemblyError> {
+        if self.here >= MEMORY_SIZE {
+            return self.error(String::from("The program does not fit in memory"));
+        }
+        let offset = self.here - PROGRAM_START;
+        if offset >= self.rom.len() {
+            self.rom.resize(offset + 1, 0);
+        }
+        self.rom[offset] = byte;
+        self.here += 1;
+        Ok(())
+    }
+
+    fn emit(&mut self, opcode: u16) -> Result<(), AssemblyError> {
+        let [high, low] = opcode.to_be_bytes();
+        self.emit_byte(high)?;
+        self.emit_byte(low)
+    }
+
+    //Fills in the address field of the instruction at address
+    fn patch(&mut self, address: usize, target: usize) {
+        let offset = address - PROGRAM_START;
+        self.rom[offset] = (self.rom[offset] & 0xF0) | (target >> 8) as u8;
+        self.rom[offset + 1] = target as u8;
+    }
+
+    fn number(&self, text: &str) -> Option<i64> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text),
+        };
+        let value = if let Some(hex) = digits.strip_prefix("0x") {
+            i64::from_str_radix(hex, 16).ok()?
+        } else if let Some(binary) = digits.strip_prefix("0b") {
+            i64::from_str_radix(binary, 2).ok()?
+        } else {
+            digits.parse().ok()?
+        };
+        Some(if negative { -value } else { value })
+    }
+
+    fn constant(&mut self) -> Result<i64, AssemblyError> {
+        let text = self.next()?;
+        match self.number(text).or_else(|| self.constants.get(text).copied()) {
+            Some(value) => Ok(value),
+            None => self.error(format!("Expected a number, found {}", text)),
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, AssemblyError> {
+        match self.constant()? {
+            value @ -128..=255 => Ok(value as u8),
+            value => self.error(format!("{} does not fit in a byte", value)),
+        }
+    }
+
+    fn register_named(&self, text: &str) -> Option<u8> {
+        if let Some(&register) = self.aliases.get(text) {
+            return Some(register);
+        }
+        let digit = text.strip_prefix('v').or_else(|| text.strip_prefix('V'))?;
+        if digit.len() != 1 {
+            return None;
+        }
+        u8::from_str_radix(digit, 16).ok()
+    }
+
+    fn register(&mut self) -> Result<u8, AssemblyError> {
+        let text = self.next()?;
+        match self.register_named(text) {
+            Some(register) => Ok(register),
+            None => self.error(format!("Expected a register, found {}", text)),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, AssemblyError> {
+        match self.peek().and_then(|text| self.register_named(text)) {
+            Some(register) => {
+                self.position += 1;
+                Ok(Operand::Register(register))
+            }
+            None => Ok(Operand::Byte(self.byte()?)),
+        }
+    }
+
+    //Emits an instruction with an address field, resolved later if the label is not known yet
+    fn emit_address(&mut self, opcode: u16) -> Result<(), AssemblyError> {
+        let text = self.next()?;
+        let address = match self.labels.get(text) {
+            Some(&address) => address,
+            None => match self.number(text).or_else(|| self.constants.get(text).copied()) {
+                Some(value) if (0..MEMORY_SIZE as i64).contains(&value) => value as usize,
+                Some(value) => return self.error(format!("Address {} is outside memory", value)),
+                None => {
+                    self.fixups.push((self.here, Token{text, line: self.tokens[self.position - 1].line}));
+                    0
+                }
+            },
+        };
+        self.emit(opcode | address as u16)
+    }
+
+    fn condition(&mut self) -> Result<Condition, AssemblyError> {
+        let x = self.register()?;
+        match self.next()? {
+            "==" => Ok(Condition::Equal(x, self.operand()?)),
+            "!=" => Ok(Condition::NotEqual(x, self.operand()?)),
+            "key" => Ok(Condition::Key(x)),
+            "-key" => Ok(Condition::NotKey(x)),
+            comparison @ ("<" | ">" | "<=" | ">=") => self.unsupported(&format!("comparison {}", comparison)),
+            token => self.error(format!("Expected a comparison, found {}", token)),
+        }
+    }
+
+    //The instruction that skips the next one when the condition is as given
+    fn skip(condition: Condition, when: bool) -> u16 {
+        let (x, equal, operand) = match condition {
+            Condition::Key(x) => return (x as u16) << 8 | if when { 0xE09E } else { 0xE0A1 },
+            Condition::NotKey(x) => return (x as u16) << 8 | if when { 0xE0A1 } else { 0xE09E },
+            Condition::Equal(x, operand) => (x, when, operand),
+            Condition::NotEqual(x, operand) => (x, !when, operand),
+        };
+        let x = (x as u16) << 8;
+        match (operand, equal) {
+            (Operand::Byte(kk), true) => 0x3000 | x | kk as u16,
+            (Operand::Byte(kk), false) => 0x4000 | x | kk as u16,
+            (Operand::Register(y), true) => 0x5000 | x | (y as u16) << 4,
+            (Operand::Register(y), false) => 0x9000 | x | (y as u16) << 4,
+        }
+    }
+
+    fn statement(&mut self) -> Result<(), AssemblyError> {
+        let token = self.next()?;
+        match token {
+            ":" => {
+                let name = self.next()?;
+                if self.labels.insert(name, self.here).is_some() {
+                    return self.error(format!("Label {} is defined twice", name));
+                }
+            }
+            ":const" => {
+                let name = self.next()?;
+                let value = self.constant()?;
+                self.constants.insert(name, value);
+            }
+            ":alias" => {
+                let name = self.next()?;
+                let register = self.register()?;
+                self.aliases.insert(name, register);
+            }
+            ":org" => {
+                self.here = match self.constant()? {
+                    address @ 0x200..=0xFFF => address as usize,
+                    address => return self.error(format!("Cannot assemble at {}", address)),
+                };
+            }
+            ":byte" => {
+                let byte = self.byte()?;
+                self.emit_byte(byte)?;
+            }
+            ":call" => self.emit_address(0x2000)?,
+            //Only meaningful to the Octo debugger
+            ":breakpoint" => {
+                self.next()?;
+            }
+            ":monitor" => {
+                self.next()?;
+                self.next()?;
+            }
+            "clear" => self.emit(0x00E0)?,
+            "return" | ";" => self.emit(0x00EE)?,
+            "jump" => self.emit_address(0x1000)?,
+            "jump0" => self.emit_address(0xB000)?,
+            "sprite" => {
+                let x = self.register()? as u16;
+                let y = self.register()? as u16;
+                let n = match self.constant()? {
+                    n @ 0..=15 => n as u16,
+                    n => return self.error(format!("Sprites are 0 to 15 rows, not {}", n)),
+                };
+                self.emit(0xD000 | x << 8 | y << 4 | n)?;
+            }
+            "bcd" | "save" | "load" => {
+                let x = (self.register()? as u16) << 8;
+                if self.peek() == Some("-") {
+                    return self.unsupported(&format!("{} of a register range (XO-CHIP)", token));
+                }
+                self.emit(x | match token { "bcd" => 0xF033, "save" => 0xF055, _ => 0xF065 })?;
+            }
+            "delay" | "buzzer" => {
+                self.expect(":=")?;
+                let x = (self.register()? as u16) << 8;
+                self.emit(x | if token == "delay" { 0xF015 } else { 0xF018 })?;
+            }
+            "i" => match self.next()? {
+                ":=" if matches!(self.peek(), Some("long" | "bighex")) => {
+                    let extended = self.next()?;
+                    return self.unsupported(&format!("i := {} (SUPER-CHIP and XO-CHIP)", extended));
+                }
+                ":=" if self.peek() == Some("hex") => {
+                    self.next()?;
+                    let x = self.register()? as u16;
+                    self.emit(0xF029 | x << 8)?;
+                }
+                ":=" => self.emit_address(0xA000)?,
+                "+=" => {
+                    let x = self.register()? as u16;
+                    self.emit(0xF01E | x << 8)?;
+                }
+                operator => return self.error(format!("Unknown operation i {}", operator)),
+            },
+            "if" => {
+                let condition = self.condition()?;
+                match self.next()? {
+                    "then" => self.emit(Assembler::skip(condition, false))?,
+                    "begin" => {
+                        self.emit(Assembler::skip(condition, true))?;
+                        self.blocks.push(Block::If(self.here));
+                        self.emit(0x1000)?;
+                    }
+                    token => return self.error(format!("Expected then or begin, found {}", token)),
+                }
+            }
+            "else" => match self.blocks.pop() {
+                Some(Block::If(jump)) => {
+                    let end = self.here;
+                    self.emit(0x1000)?;
+                    self.patch(jump, self.here);
+                    self.blocks.push(Block::Else(end));
+                }
+                _ => return self.error(String::from("else without if ... begin")),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If(jump) | Block::Else(jump)) => self.patch(jump, self.here),
+                _ => return self.error(String::from("end without if ... begin")),
+            },
+            "loop" => self.blocks.push(Block::Loop(self.here, Vec::new())),
+            "while" => {
+                let condition = self.condition()?;
+                self.emit(Assembler::skip(condition, true))?;
+                let here = self.here;
+                match self.blocks.iter_mut().rev().find(|block| matches!(block, Block::Loop(..))) {
+                    Some(Block::Loop(_, breaks)) => breaks.push(here),
+                    _ => return self.error(String::from("while outside a loop")),
+                }
+                self.emit(0x1000)?;
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop(start, breaks)) => {
+                    self.emit(0x1000 | start as u16)?;
+                    for jump in breaks {
+                        self.patch(jump, self.here);
+                    }
+                }
+                _ => return self.error(String::from("again without loop")),
+            },
+            token if self.register_named(token).is_some() => self.register_statement(token)?,
+            token if EXTENDED_STATEMENTS.contains(&token) => return self.unsupported(&format!("{} (SUPER-CHIP and XO-CHIP)", token)),
+            token if token.starts_with(':') => return self.unsupported(token),
+            token if token.starts_with('"') => return self.unsupported("strings"),
+            token => match self.number(token).or_else(|| self.constants.get(token).copied()) {
+                //Bare numbers are data
+                Some(value @ -128..=255) => self.emit_byte(value as u8)?,
+                Some(value) => return self.error(format!("{} does not fit in a byte", value)),
+                //Anything else names a subroutine to call
+                None => {
+                    self.position -= 1;
+                    self.emit_address(0x2000)?;
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn register_statement(&mut self, register: &str) -> Result<(), AssemblyError> {
+        let x = (self.register_named(register).unwrap_or(0) as u16) << 8;
+        let operator = self.next()?;
+        let opcode = match (operator, self.peek()) {
+            (":=", Some("random")) => {
+                self.next()?;
+                0xC000 | x | self.byte()? as u16
+            }
+            (":=", Some("delay")) => {
+                self.next()?;
+                0xF007 | x
+            }
+            (":=", Some("key")) => {
+                self.next()?;
+                0xF00A | x
+            }
+            _ => {
+                let operand = self.operand()?;
+                match (operator, operand) {
+                    (":=", Operand::Byte(kk)) => 0x6000 | x | kk as u16,
+                    ("+=", Operand::Byte(kk)) => 0x7000 | x | kk as u16,
+                    ("-=", Operand::Byte(kk)) => 0x7000 | x | kk.wrapping_neg() as u16,
+                    (operator, Operand::Register(y)) => {
+                        let n = match operator {
+                            ":=" => 0x0,
+                            "|=" => 0x1,
+                            "&=" => 0x2,
+                            "^=" => 0x3,
+                            "+=" => 0x4,
+                            "-=" => 0x5,
+                            ">>=" => 0x6,
+                            "=-" => 0x7,
+                            "<<=" => 0xE,
+                            _ => return self.error(format!("Unknown operation {} {}", register, operator)),
+                        };
+                        0x8000 | x | (y as u16) << 4 | n
+                    }
+                    (operator, _) => return self.error(format!("{} {} needs a register", register, operator)),
+                }
+            }
+        };
+        self.emit(opcode)
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, AssemblyError> {
+        if !self.blocks.is_empty() {
+            return self.error(String::from("A loop or if ... begin is not closed"));
+        }
+        for (address, token) in std::mem::take(&mut self.fixups) {
+            match self.labels.get(token.text) {
+                Some(&target) => self.patch(address, target),
+                None => return Err(AssemblyError{line: token.line, message: format!("Undefined name {}", token.text)}),
+            }
+        }
+        match self.labels.get("main") {
+            Some(&main) => self.patch(PROGRAM_START, main),
+            None => return Err(AssemblyError{line: 0, message: String::from("The program has no main label")}),
+        }
+        self.rom[0] |= 0x10;
+        Ok(self.rom)
+    }
+}
+
+#[cfg(test)]
+#[path = "./octo_test.rs"]
+mod octo_test;
@@ -0,0 +1,91 @@
+use super::{assemble, AssemblyError};
+
+fn opcodes(rom: &[u8]) -> Vec<u16> {
+    rom.chunks(2).map(|pair| u16::from_be_bytes([pair[0], pair.get(1).copied().unwrap_or(0)])).collect()
+}
+
+#[test]
+fn test_instructions() {
+    let source = "
+        : main
+            clear
+            v0 := 5        v1 := v0      v2 += 1   v2 -= 1
+            v3 += v4       v3 -= v4      v3 =- v4  v3 |= v4  v3 &= v4  v3 ^= v4
+            v3 >>= v4      v3 <<= v4     v5 := random 0x0F
+            v6 := delay    v7 := key     delay := v6   buzzer := v7
+            i := 0x300     i := hex v8   i += v9
+            sprite v0 v1 5 bcd va        save vb       load vc
+            jump0 0x204    return
+    ";
+    assert_eq!(opcodes(&assemble(source).unwrap()), vec![
+        0x1202, 0x00E0,
+        0x6005, 0x8100, 0x7201, 0x72FF,
+        0x8344, 0x8345, 0x8347, 0x8341, 0x8342, 0x8343,
+        0x8346, 0x834E, 0xC50F,
+        0xF607, 0xF70A, 0xF615, 0xF718,
+        0xA300, 0xF829, 0xF91E,
+        0xD015, 0xFA33, 0xFB55, 0xFC65,
+        0xB204, 0x00EE,
+    ]);
+}
+
+#[test]
+fn test_labels_constants_and_data() {
+    let source = "
+        :const speed 3
+        :alias lives v4
+        : main
+            lives := speed
+            i := ball      # ball is defined below
+            draw
+            loop again
+        : draw ;
+        : ball 0b11000000 0xC0 :byte 7
+    ";
+    assert_eq!(assemble(source).unwrap(), vec![0x12, 0x02, 0x64, 0x03, 0xA2, 0x0C, 0x22, 0x0A, 0x12, 0x08, 0x00, 0xEE, 0xC0, 0xC0, 0x07]);
+}
+
+#[test]
+fn test_control_flow() {
+    let source = "
+        : main
+            if v0 == 1 then v1 := 2
+            if v0 != v2 then v1 := 3
+            if v3 key begin v1 := 4 else v1 := 5 end
+            loop
+                while v4 -key
+                v4 += 1
+            again
+    ";
+    assert_eq!(opcodes(&assemble(source).unwrap()), vec![
+        0x1202,
+        0x4001, 0x6102,
+        0x5020, 0x6103,
+        0xE39E, 0x1212, 0x6104, 0x1214, 0x6105,
+        0xE4A1, 0x121C, 0x7401, 0x1214,
+    ]);
+}
+
+#[test]
+fn test_errors() {
+    let error = |source: &str| assemble(source).err().map(|error| error.message);
+    assert_eq!(assemble(": start clear"), Err(AssemblyError{line: 0, message: String::from("The program has no main label")}));
+    assert_eq!(assemble(": main\nmissing"), Err(AssemblyError{line: 2, message: String::from("Undefined name missing")}));
+    assert_eq!(error(": main v0 := 300"), Some(String::from("300 does not fit in a byte")));
+    assert_eq!(error(": main loop"), Some(String::from("A loop or if ... begin is not closed")));
+    assert_eq!(error(": main end"), Some(String::from("end without if ... begin")));
+    assert_eq!(error(": main : main"), Some(String::from("Label main is defined twice")));
+}
+
+#[test]
+fn test_unsupported_features() {
+    let error = |source: &str| assemble(source).err().map(|error| error.message);
+    assert_eq!(error(":macro m { clear } : main"), Some(String::from("Unsupported Octo feature: :macro")));
+    assert_eq!(error(":calc size { 2 * 3 } : main"), Some(String::from("Unsupported Octo feature: :calc")));
+    assert_eq!(error(": main if v0 < 3 then clear"), Some(String::from("Unsupported Octo feature: comparison <")));
+    assert_eq!(error(": main \"hello\""), Some(String::from("Unsupported Octo feature: strings")));
+    assert_eq!(error(": main hires"), Some(String::from("Unsupported Octo feature: hires (SUPER-CHIP and XO-CHIP)")));
+    assert_eq!(error(": main plane 2"), Some(String::from("Unsupported Octo feature: plane (SUPER-CHIP and XO-CHIP)")));
+    assert_eq!(error(": main i := long 0x4000"), Some(String::from("Unsupported Octo feature: i := long (SUPER-CHIP and XO-CHIP)")));
+    assert_eq!(error(": main save v1 - v3"), Some(String::from("Unsupported Octo feature: save of a register range (XO-CHIP)")));
+}
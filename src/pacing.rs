@@ -0,0 +1,157 @@
+//Paces emulated 60 Hz frames against the host clock and measures the achieved speed
+use std::thread;
+use std::time::{Duration, Instant};
+
+const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+const MEASURE_INTERVAL: Duration = Duration::from_secs(1);
+//Showing a frame on a display synced to its refresh waits for the refresh, so uncapped it is only done this often
+const UNCAPPED_PRESENT_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FastForward {
+    //As fast as the host allows
+    Uncapped,
+    //This many emulated frames per 60 Hz frame
+    Multiplier(u32),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Speed {
+    Normal,
+    Paused,
+    FastForward,
+    SlowMotion,
+}
+
+pub struct Pacing {
+    speed: Speed,
+    fast_forward: FastForward,
+    //Each emulated frame lasts this many 60 Hz frames
+    slow_motion: u32,
+    advance: bool,
+    next_frame: Instant,
+    presented_at: Instant,
+    measured_since: Instant,
+    frames: u32,
+    instructions: u64,
+    fps: f64,
+    ips: f64,
+    status_changed: bool,
+}
+
+impl Pacing {
+    pub fn new(fast_forward: FastForward, slow_motion: u32) -> Self {
+        let now = Instant::now();
+        Pacing{speed: Speed::Normal, fast_forward, slow_motion: slow_motion.max(1), advance: false, next_frame: now,
+            presented_at: now, measured_since: now, frames: 0, instructions: 0, fps: 0.0, ips: 0.0, status_changed: true}
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.set_speed(if self.speed == Speed::Paused { Speed::Normal } else { Speed::Paused });
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.speed == Speed::Paused
+    }
+
+    //Runs a single frame and stays paused
+    pub fn advance_frame(&mut self) {
+        self.set_speed(Speed::Paused);
+        self.advance = true;
+    }
+
+    pub fn toggle_fast_forward(&mut self) {
+        self.set_speed(if self.speed == Speed::FastForward { Speed::Normal } else { Speed::FastForward });
+    }
+
+    pub fn toggle_slow_motion(&mut self) {
+        self.set_speed(if self.speed == Speed::SlowMotion { Speed::Normal } else { Speed::SlowMotion });
+    }
+
+    fn set_speed(&mut self, speed: Speed) {
+        self.speed = speed;
+        self.advance = false;
+        self.status_changed = true;
+    }
+
+    //Emulated frames to run before the next wait
+    pub fn frames_due(&mut self) -> u32 {
+        match (self.speed, self.fast_forward) {
+            (Speed::Paused, _) => std::mem::take(&mut self.advance) as u32,
+            (Speed::FastForward, FastForward::Multiplier(multiplier)) => multiplier,
+            _ => 1,
+        }
+    }
+
+    //Called after each emulated frame
+    pub fn count_frame(&mut self, instructions: u32) {
+        self.frames += 1;
+        self.instructions += instructions as u64;
+    }
+
+    //Whether to show the frame now, which is every time unless running uncapped
+    pub fn present_due(&mut self) -> bool {
+        if self.speed != Speed::FastForward || self.fast_forward != FastForward::Uncapped {
+            return true;
+        }
+        let now = Instant::now();
+        let due = now.duration_since(self.presented_at) >= UNCAPPED_PRESENT_INTERVAL;
+        if due {
+            self.presented_at = now;
+        }
+        due
+    }
+
+    //Sleeps until the next 60 Hz frame, or a few of them in slow motion. When the frame was just shown
+    //on a display synced to a 60 Hz refresh, that already waited for the next frame.
+    pub fn wait(&mut self, synced: bool) {
+        if synced && self.speed != Speed::SlowMotion {
+            self.next_frame = Instant::now();
+            return;
+        }
+        let frame_duration = match (self.speed, self.fast_forward) {
+            (Speed::FastForward, FastForward::Uncapped) => Duration::ZERO,
+            (Speed::SlowMotion, _) => FRAME_DURATION * self.slow_motion,
+            _ => FRAME_DURATION,
+        };
+        self.next_frame += frame_duration;
+        let now = Instant::now();
+        if self.next_frame > now {
+            thread::sleep(self.next_frame - now);
+        } else {
+            //Running behind, do not try to catch up
+            self.next_frame = now;
+        }
+    }
+
+    //The status text, when it has changed since the last call
+    pub fn status(&mut self) -> Option<String> {
+        let elapsed = self.measured_since.elapsed();
+        if elapsed >= MEASURE_INTERVAL {
+            self.fps = self.frames as f64 / elapsed.as_secs_f64();
+            self.ips = self.instructions as f64 / elapsed.as_secs_f64();
+            self.measured_since = Instant::now();
+            self.frames = 0;
+            self.instructions = 0;
+            self.status_changed = true;
+        }
+        if !std::mem::take(&mut self.status_changed) {
+            return None;
+        }
+        Some(format!("{} | {:.0} fps | {:.0} ips", self.speed_label(), self.fps, self.ips))
+    }
+
+    fn speed_label(&self) -> String {
+        match (self.speed, self.fast_forward) {
+            (Speed::Normal, _) => String::from("100%"),
+            (Speed::Paused, _) => String::from("Paused"),
+            (Speed::FastForward, FastForward::Uncapped) => String::from("Uncapped"),
+            (Speed::FastForward, FastForward::Multiplier(multiplier)) => format!("{}%", multiplier * 100),
+            (Speed::SlowMotion, _) => format!("{}%", 100 / self.slow_motion),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./pacing_test.rs"]
+mod pacing_test;
@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+use super::{FastForward, Pacing};
+
+#[test]
+fn test_frames_due() {
+    let mut pacing = Pacing::new(FastForward::Multiplier(4), 2);
+    assert_eq!(pacing.frames_due(), 1);
+    pacing.toggle_fast_forward();
+    assert_eq!(pacing.frames_due(), 4);
+    pacing.toggle_slow_motion();
+    assert_eq!(pacing.frames_due(), 1);
+    pacing.toggle_pause();
+    assert_eq!(pacing.frames_due(), 0);
+    pacing.toggle_pause();
+    assert_eq!(pacing.frames_due(), 1);
+}
+
+#[test]
+fn test_frame_advance() {
+    let mut pacing = Pacing::new(FastForward::Uncapped, 2);
+    pacing.advance_frame();
+    assert_eq!(pacing.frames_due(), 1);
+    assert_eq!(pacing.frames_due(), 0);
+    pacing.advance_frame();
+    pacing.toggle_pause();
+    assert_eq!(pacing.frames_due(), 1);
+}
+
+#[test]
+fn test_status_only_when_changed() {
+    let mut pacing = Pacing::new(FastForward::Uncapped, 4);
+    assert_eq!(pacing.status(), Some(String::from("100% | 0 fps | 0 ips")));
+    assert_eq!(pacing.status(), None);
+    pacing.toggle_slow_motion();
+    assert_eq!(pacing.status(), Some(String::from("25% | 0 fps | 0 ips")));
+    pacing.toggle_fast_forward();
+    assert_eq!(pacing.status(), Some(String::from("Uncapped | 0 fps | 0 ips")));
+    pacing.toggle_pause();
+    assert_eq!(pacing.status(), Some(String::from("Paused | 0 fps | 0 ips")));
+}
+
+#[test]
+fn test_present_due() {
+    let mut pacing = Pacing::new(FastForward::Uncapped, 4);
+    assert!(pacing.present_due());
+    pacing.toggle_fast_forward();
+    assert!(!pacing.present_due());
+    pacing.toggle_fast_forward();
+    assert!(pacing.present_due());
+}
+
+#[test]
+fn test_synced_wait_does_not_sleep() {
+    let mut pacing = Pacing::new(FastForward::Multiplier(4), 4);
+    let start = Instant::now();
+    for _ in 0..10 {
+        pacing.wait(true);
+    }
+    assert!(start.elapsed() < Duration::from_millis(100));
+}
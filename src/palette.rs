@@ -0,0 +1,72 @@
+use crate::persistence::FULL_SHADE;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+//Colour 0 is the background and colour 1 the foreground. Displays with several
+//bit-planes index the palette with the plane bits, so a palette has 2, 4 or 16 colours.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    pub name: String,
+    pub colors: Vec<Rgb>,
+}
+
+impl Palette {
+    pub fn new(name: &str, colors: &[Rgb]) -> Self {
+        Palette{name: name.to_string(), colors: colors.to_vec()}
+    }
+
+    //Built in themes, the first one is the default
+    pub fn builtin() -> Vec<Palette> {
+        vec![
+            Palette::new("green", &[Rgb(0, 0, 0), Rgb(0, 250, 0)]),
+            Palette::new("vip", &[Rgb(16, 16, 16), Rgb(232, 232, 232)]),
+            Palette::new("octo", &[Rgb(0x99, 0x66, 0x00), Rgb(0xFF, 0xCC, 0x00), Rgb(0xFF, 0x66, 0x00), Rgb(0x66, 0x22, 0x00)]),
+            Palette::new("lcd", &[Rgb(0x9B, 0xBC, 0x0F), Rgb(0x0F, 0x38, 0x0F), Rgb(0x30, 0x62, 0x30), Rgb(0x8B, 0xAC, 0x0F)]),
+            Palette::new("amber", &[Rgb(0x1A, 0x0F, 0x00), Rgb(0xFF, 0xB0, 0x00), Rgb(0xCC, 0x70, 0x00), Rgb(0x66, 0x38, 0x00)]),
+            Palette::new("high-contrast", &[Rgb(0, 0, 0), Rgb(255, 255, 255), Rgb(255, 255, 0), Rgb(0, 255, 255)]),
+        ]
+    }
+
+    //Parses a theme written as "name=#rrggbb #rrggbb ..."
+    pub fn parse(theme: &str) -> Option<Palette> {
+        let (name, colors) = theme.split_once('=')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+        let colors = colors.split_whitespace().map(parse_color).collect::<Option<Vec<Rgb>>>()?;
+        match colors.len() {
+            2 | 4 | 16 => Some(Palette::new(name, &colors)),
+            _ => None,
+        }
+    }
+
+    pub fn background(&self) -> Rgb {
+        self.colors[0]
+    }
+
+    pub fn foreground(&self) -> Rgb {
+        self.colors[1]
+    }
+
+    //Blends from the background to the foreground as the shade goes from 0 to FULL_SHADE
+    pub fn shade(&self, shade: u8) -> Rgb {
+        let (background, foreground) = (self.background(), self.foreground());
+        let mix = |from: u8, to: u8| (from as i32 + (to as i32 - from as i32) * shade as i32 / FULL_SHADE as i32) as u8;
+        Rgb(mix(background.0, foreground.0), mix(background.1, foreground.1), mix(background.2, foreground.2))
+    }
+}
+
+fn parse_color(color: &str) -> Option<Rgb> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some(Rgb((value >> 16) as u8, (value >> 8) as u8, value as u8))
+}
+
+#[cfg(test)]
+#[path = "./palette_test.rs"]
+mod palette_test;
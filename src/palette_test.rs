@@ -0,0 +1,32 @@
+use super::{Palette, Rgb};
+
+#[test]
+fn test_builtin_palettes() {
+    let palettes = Palette::builtin();
+    assert_eq!(palettes[0].name, "green");
+    assert_eq!(palettes[0].foreground(), Rgb(0, 250, 0));
+    for palette in palettes.iter() {
+        assert!(palette.colors.len() == 2 || palette.colors.len() == 4);
+    }
+}
+
+#[test]
+fn test_parse_theme() {
+    let palette = Palette::parse("mine=#102030 #FFFFFF").unwrap();
+    assert_eq!(palette.name, "mine");
+    assert_eq!(palette.colors, vec![Rgb(0x10, 0x20, 0x30), Rgb(0xFF, 0xFF, 0xFF)]);
+    assert_eq!(Palette::parse("mine=#102030"), None);
+    assert_eq!(Palette::parse("mine=#102030 #FFF"), None);
+    assert_eq!(Palette::parse("#102030 #FFFFFF"), None);
+    assert_eq!(Palette::parse("=#102030 #FFFFFF"), None);
+    assert_eq!(Palette::parse(" =#102030 #FFFFFF"), None);
+}
+
+#[test]
+fn test_shade_blends_background_to_foreground() {
+    let palette = Palette::new("test", &[Rgb(0, 100, 200), Rgb(255, 0, 100)]);
+    assert_eq!(palette.shade(0), Rgb(0, 100, 200));
+    assert_eq!(palette.shade(255), Rgb(255, 0, 100));
+    assert_eq!(palette.shade(51), Rgb(51, 80, 180));
+}
+
@@ -0,0 +1,93 @@
+//Anti-flicker processing of VRAM before it is presented.
+//Presented frames hold a shade per pixel, from 0 (dark) to FULL_SHADE (fully lit).
+use crate::{DISPLAY_WIDTH, DISPLAY_HEIGHT, VRAM, DirtyRows};
+
+pub const FULL_SHADE: u8 = 255;
+//Fraction of its shade, in 256ths, a pixel keeps each frame after it is switched off
+const PHOSPHOR_DECAY: u32 = 150;
+//Dimmer pixels are switched off entirely
+const PHOSPHOR_CUTOFF: u8 = 16;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AntiFlicker {
+    //The VRAM is presented as it is at the end of each frame
+    Off,
+    //Switched off pixels fade out over a few frames, like a CRT
+    Phosphor,
+    //A pixel is lit if it was lit in this frame or the one before
+    FrameOr,
+    //Changes are only presented once per 60 Hz frame
+    Vblank,
+}
+
+pub struct Persistence {
+    mode: AntiFlicker,
+    previous: VRAM,
+    shades: VRAM,
+}
+
+impl Persistence {
+    pub fn new(mode: AntiFlicker) -> Self {
+        Persistence {
+            mode,
+            previous: [[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
+            shades: [[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
+        }
+    }
+
+    pub fn presents_on_vblank(&self) -> bool {
+        self.mode != AntiFlicker::Off
+    }
+
+    //The frame presented last
+    pub fn shades(&self) -> &VRAM {
+        &self.shades
+    }
+
+    //Shades the changed rows right away, for when presentation is not tied to vblank
+    pub fn immediate(&mut self, vram: &VRAM, dirty_rows: DirtyRows) -> (&VRAM, DirtyRows) {
+        for (y, (shades, row)) in self.shades.iter_mut().zip(vram.iter()).enumerate() {
+            if dirty_rows & (1 << y) != 0 {
+                for (shade, &pixel) in shades.iter_mut().zip(row.iter()) {
+                    *shade = lit(pixel);
+                }
+            }
+        }
+        (&self.shades, dirty_rows)
+    }
+
+    //Called once per 60 Hz frame with the current VRAM, returns the frame to present and its changed rows
+    pub fn vblank(&mut self, vram: &VRAM) -> (&VRAM, DirtyRows) {
+        let mut dirty_rows = 0;
+        for (y, (shades, (row, previous))) in self.shades.iter_mut().zip(vram.iter().zip(self.previous.iter())).enumerate() {
+            for (shade, (&pixel, &previous)) in shades.iter_mut().zip(row.iter().zip(previous.iter())) {
+                let new_shade = match self.mode {
+                    AntiFlicker::Off | AntiFlicker::Vblank => lit(pixel),
+                    AntiFlicker::FrameOr => lit(pixel | previous),
+                    AntiFlicker::Phosphor => {
+                        if pixel != 0 {
+                            FULL_SHADE
+                        } else {
+                            let faded = (*shade as u32 * PHOSPHOR_DECAY / 256) as u8;
+                            if faded < PHOSPHOR_CUTOFF { 0 } else { faded }
+                        }
+                    }
+                };
+                if new_shade != *shade {
+                    *shade = new_shade;
+                    dirty_rows |= 1 << y;
+                }
+            }
+        }
+        self.previous = *vram;
+        (&self.shades, dirty_rows)
+    }
+}
+
+fn lit(pixel: u8) -> u8 {
+    if pixel != 0 { FULL_SHADE } else { 0 }
+}
+
+#[cfg(test)]
+#[path = "./persistence_test.rs"]
+mod persistence_test;
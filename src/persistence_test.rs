@@ -0,0 +1,61 @@
+use crate::{DISPLAY_HEIGHT, DISPLAY_WIDTH, ALL_ROWS};
+use super::{AntiFlicker, Persistence, FULL_SHADE};
+
+#[test]
+fn test_immediate_shades_dirty_rows() {
+    let mut persistence = Persistence::new(AntiFlicker::Off);
+    let mut vram = [[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+    vram[0][0] = 1;
+    vram[1][0] = 1;
+    let (shades, dirty_rows) = persistence.immediate(&vram, 0b1);
+    assert_eq!(dirty_rows, 0b1);
+    assert_eq!(shades[0][0], FULL_SHADE);
+    assert_eq!(shades[1][0], 0);
+    assert!(!persistence.presents_on_vblank());
+}
+
+#[test]
+fn test_frame_or_keeps_pixels_for_one_frame() {
+    let mut persistence = Persistence::new(AntiFlicker::FrameOr);
+    let mut vram = [[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+    vram[3][5] = 1;
+    let (shades, dirty_rows) = persistence.vblank(&vram);
+    assert_eq!(shades[3][5], FULL_SHADE);
+    assert_eq!(dirty_rows, 1 << 3);
+    vram[3][5] = 0;
+    let (shades, dirty_rows) = persistence.vblank(&vram);
+    assert_eq!(shades[3][5], FULL_SHADE);
+    assert_eq!(dirty_rows, 0);
+    let (shades, dirty_rows) = persistence.vblank(&vram);
+    assert_eq!(shades[3][5], 0);
+    assert_eq!(dirty_rows, 1 << 3);
+}
+
+#[test]
+fn test_phosphor_fades_out() {
+    let mut persistence = Persistence::new(AntiFlicker::Phosphor);
+    let mut vram = [[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+    vram[0][0] = 1;
+    persistence.vblank(&vram);
+    vram[0][0] = 0;
+    let mut last_shade = FULL_SHADE;
+    for _ in 0..3 {
+        let (shades, _) = persistence.vblank(&vram);
+        assert!(shades[0][0] < last_shade);
+        assert!(shades[0][0] > 0);
+        last_shade = shades[0][0];
+    }
+    for _ in 0..10 {
+        persistence.vblank(&vram);
+    }
+    assert_eq!(persistence.shades()[0][0], 0);
+}
+
+#[test]
+fn test_vblank_presents_current_frame() {
+    let mut persistence = Persistence::new(AntiFlicker::Vblank);
+    let vram = [[1; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+    let (_, dirty_rows) = persistence.vblank(&vram);
+    assert_eq!(dirty_rows, ALL_ROWS);
+    assert!(persistence.presents_on_vblank());
+}
@@ -0,0 +1,177 @@
+//Counts where a ROM spends its COSMAC VIP machine cycles, per instruction and per subroutine.
+//Subroutines are followed through 2NNN and 00EE, so call stacks can be exported in the folded
+//format of flamegraph.pl and inferno: one line per stack, frames separated by ';', then the cycles.
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use crate::disassembler::disassemble;
+use crate::timing::VIP_CYCLES_AVAILABLE_PER_FRAME;
+use crate::MEMORY_SIZE;
+
+//Hot spots and subroutines listed in reports
+const REPORT_LINES: usize = 20;
+
+pub struct Profiler {
+    executions: Vec<u64>,
+    cycles: Vec<u64>,
+    //Last opcode seen at each address
+    opcodes: Vec<u16>,
+    //Entry addresses of the subroutines being run, outermost first
+    stack: Vec<u16>,
+    calls: HashMap<u16, u64>,
+    //Cycles spent with each call stack, charged to the innermost subroutine
+    stacks: HashMap<Vec<u16>, u64>,
+    frames: u64,
+}
+
+struct Subroutine {
+    address: Option<u16>,
+    calls: u64,
+    self_cycles: u64,
+    total_cycles: u64,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler {
+            executions: vec![0; MEMORY_SIZE],
+            cycles: vec![0; MEMORY_SIZE],
+            opcodes: vec![0; MEMORY_SIZE],
+            stack: Vec::new(),
+            calls: HashMap::new(),
+            stacks: HashMap::new(),
+            frames: 0,
+        }
+    }
+
+    //Called after each instruction with where it was, and PC and SP after it ran
+    pub fn record(&mut self, pc: usize, opcode: u16, cycles: u32, next_pc: usize, sp: usize) {
+        self.executions[pc] += 1;
+        self.cycles[pc] += cycles as u64;
+        self.opcodes[pc] = opcode;
+        match self.stacks.get_mut(&self.stack[..]) {
+            Some(total) => *total += cycles as u64,
+            None => {
+                self.stacks.insert(self.stack.clone(), cycles as u64);
+            }
+        }
+        //Following SP rather than the opcodes keeps the stack right when a call overflows
+        if sp > self.stack.len() {
+            self.stack.push(next_pc as u16);
+            *self.calls.entry(next_pc as u16).or_default() += 1;
+        } else {
+            self.stack.truncate(sp);
+        }
+    }
+
+    pub fn count_frame(&mut self) {
+        self.frames += 1;
+    }
+
+    fn total_cycles(&self) -> u64 {
+        self.cycles.iter().sum()
+    }
+
+    //Top level code first, then by cycles spent inside each subroutine and what it called
+    fn subroutines(&self) -> Vec<Subroutine> {
+        let mut subroutines: HashMap<Option<u16>, Subroutine> = HashMap::new();
+        for (stack, &cycles) in self.stacks.iter() {
+            let mut seen = vec![None];
+            seen.extend(stack.iter().map(|&address| Some(address)));
+            let innermost = *seen.last().unwrap_or(&None);
+            for (n, &address) in seen.iter().enumerate() {
+                //Recursive calls are only counted once
+                if seen[..n].contains(&address) {
+                    continue;
+                }
+                let subroutine = subroutines.entry(address).or_insert_with(|| Subroutine {
+                    address,
+                    calls: address.map_or(0, |address| self.calls.get(&address).copied().unwrap_or(0)),
+                    self_cycles: 0,
+                    total_cycles: 0,
+                });
+                subroutine.total_cycles += cycles;
+                if address == innermost {
+                    subroutine.self_cycles += cycles;
+                }
+            }
+        }
+        let mut subroutines: Vec<Subroutine> = subroutines.into_values().collect();
+        subroutines.sort_by_key(|subroutine| (subroutine.address.is_some(), u64::MAX - subroutine.total_cycles, subroutine.address));
+        subroutines
+    }
+
+    pub fn write_report(&self, writer: &mut dyn Write) -> io::Result<()> {
+        let total = self.total_cycles();
+        let share = |cycles: u64| if total == 0 { 0.0 } else { cycles as f64 * 100.0 / total as f64 };
+        let instructions: u64 = self.executions.iter().sum();
+        writeln!(writer, "Profile of {} frames, {} instructions, {} cycles", self.frames, instructions, total)?;
+        if let Some(per_frame) = total.checked_div(self.frames) {
+            writeln!(writer, "Cycles per frame: {} ({:.0}% of the {} available on the COSMAC VIP)",
+                per_frame, per_frame as f64 * 100.0 / VIP_CYCLES_AVAILABLE_PER_FRAME as f64, VIP_CYCLES_AVAILABLE_PER_FRAME)?;
+        }
+
+        writeln!(writer)?;
+        writeln!(writer, "Hot spots")?;
+        writeln!(writer, "  {:>4}  {:<6}  {:<16}  {:>10}  {:>12}  {:>5}", "PC", "opcode", "instruction", "executions", "cycles", "%")?;
+        let mut hot_spots: Vec<usize> = (0..MEMORY_SIZE).filter(|&pc| self.executions[pc] > 0).collect();
+        hot_spots.sort_by_key(|&pc| (u64::MAX - self.cycles[pc], pc));
+        for &pc in hot_spots.iter().take(REPORT_LINES) {
+            writeln!(writer, "  {:>4}  {:<6}  {:<16}  {:>10}  {:>12}  {:>5.1}", format!("{:03X}", pc), format!("{:04X}", self.opcodes[pc]),
+                disassemble(self.opcodes[pc]), self.executions[pc], self.cycles[pc], share(self.cycles[pc]))?;
+        }
+
+        writeln!(writer)?;
+        writeln!(writer, "Subroutines")?;
+        writeln!(writer, "  {:<7}  {:>8}  {:>12}  {:>12}  {:>5}", "address", "calls", "self cycles", "total cycles", "%")?;
+        for subroutine in self.subroutines().iter().take(REPORT_LINES) {
+            let name = subroutine.address.map_or(String::from("main"), |address| format!("{:03X}", address));
+            writeln!(writer, "  {:<7}  {:>8}  {:>12}  {:>12}  {:>5.1}", name, subroutine.calls,
+                subroutine.self_cycles, subroutine.total_cycles, share(subroutine.total_cycles))?;
+        }
+        Ok(())
+    }
+
+    //Stacks are sorted so the output is the same from run to run
+    pub fn write_folded(&self, writer: &mut dyn Write) -> io::Result<()> {
+        let mut lines: Vec<String> = self.stacks.iter().map(|(stack, cycles)| {
+            let mut frames = vec![String::from("main")];
+            frames.extend(stack.iter().map(|address| format!("sub_{:03X}", address)));
+            format!("{} {}", frames.join(";"), cycles)
+        }).collect();
+        lines.sort();
+        for line in lines {
+            writeln!(writer, "{}", line)?;
+        }
+        Ok(())
+    }
+
+    //"-" writes the report to stdout
+    pub fn save_report(&self, path: &str) -> io::Result<()> {
+        match path {
+            "-" => self.write_report(&mut io::stdout()),
+            path => {
+                let mut writer = BufWriter::new(File::create(path)?);
+                self.write_report(&mut writer)?;
+                writer.flush()
+            }
+        }
+    }
+
+    pub fn save_folded(&self, path: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_folded(&mut writer)?;
+        writer.flush()
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Profiler::new()
+    }
+}
+
+#[cfg(test)]
+#[path = "./profiler_test.rs"]
+mod profiler_test;
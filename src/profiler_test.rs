@@ -0,0 +1,55 @@
+use super::Profiler;
+
+//Main calls 300, which calls 400, then both return and main jumps back
+fn profile() -> Profiler {
+    let mut profiler = Profiler::new();
+    profiler.record(0x200, 0x6001, 10, 0x202, 0);
+    profiler.record(0x202, 0x2300, 26, 0x300, 1);
+    profiler.record(0x300, 0x7001, 10, 0x302, 1);
+    profiler.record(0x302, 0x2400, 26, 0x400, 2);
+    profiler.record(0x400, 0x00EE, 10, 0x304, 1);
+    profiler.record(0x304, 0x00EE, 10, 0x204, 0);
+    profiler.record(0x204, 0x1200, 12, 0x200, 0);
+    profiler.count_frame();
+    profiler.count_frame();
+    profiler
+}
+
+#[test]
+fn test_subroutines() {
+    let subroutines: Vec<(Option<u16>, u64, u64, u64)> = profile().subroutines().iter()
+        .map(|subroutine| (subroutine.address, subroutine.calls, subroutine.self_cycles, subroutine.total_cycles))
+        .collect();
+    assert_eq!(subroutines, vec![(None, 0, 48, 104), (Some(0x300), 1, 46, 56), (Some(0x400), 1, 10, 10)]);
+}
+
+#[test]
+fn test_recursion_is_counted_once() {
+    let mut profiler = Profiler::new();
+    profiler.record(0x200, 0x2300, 26, 0x300, 1);
+    profiler.record(0x300, 0x2300, 26, 0x300, 2);
+    profiler.record(0x300, 0x00EE, 10, 0x302, 1);
+    let subroutines: Vec<(Option<u16>, u64, u64, u64)> = profiler.subroutines().iter()
+        .map(|subroutine| (subroutine.address, subroutine.calls, subroutine.self_cycles, subroutine.total_cycles))
+        .collect();
+    assert_eq!(subroutines, vec![(None, 0, 26, 62), (Some(0x300), 2, 36, 36)]);
+}
+
+#[test]
+fn test_folded_stacks() {
+    let mut folded = Vec::new();
+    profile().write_folded(&mut folded).unwrap();
+    assert_eq!(String::from_utf8(folded).unwrap(), "main 48\nmain;sub_300 46\nmain;sub_300;sub_400 10\n");
+}
+
+#[test]
+fn test_report() {
+    let mut report = Vec::new();
+    profile().write_report(&mut report).unwrap();
+    let report = String::from_utf8(report).unwrap();
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines[0], "Profile of 2 frames, 7 instructions, 104 cycles");
+    assert_eq!(lines[1], "Cycles per frame: 52 (2% of the 2598 available on the COSMAC VIP)");
+    assert!(lines[5].starts_with("   202  2300    CALL 0x300                 1            26   25.0"), "{}", lines[5]);
+    assert!(lines.iter().any(|line| line.starts_with("  300             1            46            56   53.8")), "{}", report);
+}
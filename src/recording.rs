@@ -0,0 +1,203 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::{DISPLAY_WIDTH, DISPLAY_HEIGHT, VRAM};
+use crate::palette::Palette;
+use crate::screenshot::rgb_frame;
+use crate::tone::ToneSettings;
+use crate::wav::AudioCapture;
+
+const FRAMES_PER_SECOND: u64 = 60;
+//Most viewers slow down GIF frames shorter than this, in hundredths of a second
+const GIF_MIN_DELAY: u64 = 2;
+//GIF frames are at most u16::MAX pixels across
+pub const MAX_SCALE: usize = u16::MAX as usize / DISPLAY_WIDTH;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RecordFormat {
+    //Animated GIF, frames that repeat the previous one are merged
+    Gif,
+    //Uncompressed YUV4MPEG2 video with the sound in a WAV file next to it
+    Y4m,
+}
+
+impl RecordFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "gif" => Some(RecordFormat::Gif),
+            "y4m" => Some(RecordFormat::Y4m),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            RecordFormat::Gif => "gif",
+            RecordFormat::Y4m => "y4m",
+        }
+    }
+}
+
+//Receives one presented frame per 60 Hz vblank
+pub struct Recorder {
+    encoder: Encoder,
+    scale: usize,
+}
+
+enum Encoder {
+    Gif(GifRecorder),
+    Y4m(Y4mRecorder),
+}
+
+impl Recorder {
+    //The format is chosen by the file extension, the tone is only used for the WAV file next to a .y4m
+    pub fn start(path: &Path, palette: &Palette, scale: usize, tone: ToneSettings) -> io::Result<Self> {
+        let encoder = match RecordFormat::from_path(path) {
+            Some(RecordFormat::Gif) => Encoder::Gif(GifRecorder::new(path, palette, scale)?),
+            Some(RecordFormat::Y4m) => Encoder::Y4m(Y4mRecorder::new(path, scale, tone)?),
+            None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "recordings must be .gif or .y4m")),
+        };
+        Ok(Recorder{encoder, scale})
+    }
+
+    pub fn frame(&mut self, frame: &VRAM, palette: &Palette, beep: bool) -> io::Result<()> {
+        match &mut self.encoder {
+            Encoder::Gif(gif) => gif.frame(scaled_shades(frame, self.scale), gif_palette(palette)),
+            Encoder::Y4m(y4m) => y4m.frame(&rgb_frame(frame, palette, self.scale), beep),
+        }
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        match self.encoder {
+            Encoder::Gif(gif) => gif.finish(),
+            Encoder::Y4m(y4m) => y4m.finish(),
+        }
+    }
+}
+
+struct PendingFrame {
+    pixels: Vec<u8>,
+    palette: Vec<u8>,
+    //Vblank the frame was first shown at
+    shown_at: u64,
+}
+
+struct GifRecorder {
+    encoder: gif::Encoder<BufWriter<File>>,
+    width: u16,
+    height: u16,
+    global_palette: Vec<u8>,
+    pending: Option<PendingFrame>,
+    vblanks: u64,
+    written_centiseconds: u64,
+}
+
+impl GifRecorder {
+    fn new(path: &Path, palette: &Palette, scale: usize) -> io::Result<Self> {
+        let (width, height) = ((DISPLAY_WIDTH * scale) as u16, (DISPLAY_HEIGHT * scale) as u16);
+        let global_palette = gif_palette(palette);
+        let mut encoder = gif::Encoder::new(BufWriter::new(File::create(path)?), width, height, &global_palette).map_err(io::Error::other)?;
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(io::Error::other)?;
+        Ok(GifRecorder{encoder, width, height, global_palette, pending: None, vblanks: 0, written_centiseconds: 0})
+    }
+
+    fn frame(&mut self, pixels: Vec<u8>, palette: Vec<u8>) -> io::Result<()> {
+        let now = self.vblanks;
+        self.vblanks += 1;
+        match self.pending.take() {
+            Some(pending) if pending.pixels == pixels && pending.palette == palette => self.pending = Some(pending),
+            //Too soon after the pending frame, which is dropped in favour of this one
+            Some(pending) if centiseconds(now) - self.written_centiseconds < GIF_MIN_DELAY => {
+                self.pending = Some(PendingFrame{pixels, palette, shown_at: pending.shown_at});
+            }
+            Some(pending) => {
+                self.write(pending, now)?;
+                self.pending = Some(PendingFrame{pixels, palette, shown_at: now});
+            }
+            None => self.pending = Some(PendingFrame{pixels, palette, shown_at: now}),
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, pending: PendingFrame, until: u64) -> io::Result<()> {
+        let delay = (centiseconds(until) - self.written_centiseconds).max(GIF_MIN_DELAY);
+        let mut frame = gif::Frame::from_indexed_pixels(self.width, self.height, pending.pixels, None);
+        frame.delay = delay as u16;
+        if pending.palette != self.global_palette {
+            frame.palette = Some(pending.palette);
+        }
+        self.encoder.write_frame(&frame).map_err(io::Error::other)?;
+        self.written_centiseconds += delay;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        if let Some(pending) = self.pending.take() {
+            let until = self.vblanks.max(pending.shown_at + 1);
+            self.write(pending, until)?;
+        }
+        self.encoder.into_inner()?.flush()
+    }
+}
+
+struct Y4mRecorder {
+    video: BufWriter<File>,
+    audio: AudioCapture,
+}
+
+impl Y4mRecorder {
+    fn new(path: &Path, scale: usize, tone: ToneSettings) -> io::Result<Self> {
+        let mut video = BufWriter::new(File::create(path)?);
+        writeln!(video, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444", DISPLAY_WIDTH * scale, DISPLAY_HEIGHT * scale, FRAMES_PER_SECOND)?;
+        let audio = AudioCapture::create(&path.with_extension("wav"), tone, FRAMES_PER_SECOND)?;
+        Ok(Y4mRecorder{video, audio})
+    }
+
+    fn frame(&mut self, rgb: &[u8], beep: bool) -> io::Result<()> {
+        let pixels: Vec<(i32, i32, i32)> = rgb.chunks(3).map(|c| (c[0] as i32, c[1] as i32, c[2] as i32)).collect();
+        self.video.write_all(b"FRAME\n")?;
+        let planes = [
+            pixels.iter().map(|&(r, g, b)| (((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8).collect::<Vec<u8>>(),
+            pixels.iter().map(|&(r, g, b)| (((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8).collect(),
+            pixels.iter().map(|&(r, g, b)| (((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8).collect(),
+        ];
+        for plane in planes.iter() {
+            self.video.write_all(plane)?;
+        }
+        self.audio.tick(beep)
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        self.video.flush()?;
+        self.audio.finish()
+    }
+}
+
+//GIF frames index a 256 colour palette with the shade of each pixel
+fn gif_palette(palette: &Palette) -> Vec<u8> {
+    (0..=255).flat_map(|shade| {
+        let color = palette.shade(shade);
+        [color.0, color.1, color.2]
+    }).collect()
+}
+
+fn scaled_shades(frame: &VRAM, scale: usize) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(DISPLAY_WIDTH * DISPLAY_HEIGHT * scale * scale);
+    for row in frame.iter() {
+        for _ in 0..scale {
+            for &shade in row.iter() {
+                pixels.extend(std::iter::repeat_n(shade, scale));
+            }
+        }
+    }
+    pixels
+}
+
+fn centiseconds(vblanks: u64) -> u64 {
+    (vblanks * 100 + FRAMES_PER_SECOND / 2) / FRAMES_PER_SECOND
+}
+
+#[cfg(test)]
+#[path = "./recording_test.rs"]
+mod recording_test;
@@ -0,0 +1,66 @@
+use std::env;
+use std::fs::{self, File};
+
+use crate::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use crate::palette::Palette;
+use crate::persistence::FULL_SHADE;
+use crate::tone::ToneSettings;
+use super::{RecordFormat, Recorder};
+
+#[test]
+fn test_record_format_from_path() {
+    assert_eq!(RecordFormat::from_path("clip.gif".as_ref()), Some(RecordFormat::Gif));
+    assert_eq!(RecordFormat::from_path("clip.y4m".as_ref()), Some(RecordFormat::Y4m));
+    assert_eq!(RecordFormat::from_path("clip.mp4".as_ref()), None);
+    assert!(Recorder::start("clip.mp4".as_ref(), &Palette::builtin()[0], 1, ToneSettings::new()).is_err());
+}
+
+#[test]
+fn test_gif_merges_repeated_frames() {
+    let palette = &Palette::builtin()[0];
+    let path = env::temp_dir().join("chip8_recording_test.gif");
+    let mut recorder = Recorder::start(&path, palette, 2, ToneSettings::new()).unwrap();
+    let mut frame = [[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+    for _ in 0..30 {
+        recorder.frame(&frame, palette, false).unwrap();
+    }
+    frame[0][0] = FULL_SHADE;
+    for _ in 0..30 {
+        recorder.frame(&frame, palette, false).unwrap();
+    }
+    recorder.finish().unwrap();
+
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(File::open(&path).unwrap()).unwrap();
+    let mut delays = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        assert_eq!((frame.width, frame.height), (DISPLAY_WIDTH as u16 * 2, DISPLAY_HEIGHT as u16 * 2));
+        delays.push(frame.delay);
+    }
+    fs::remove_file(&path).unwrap();
+    assert_eq!(delays, vec![50, 50]);
+}
+
+#[test]
+fn test_y4m_with_wav() {
+    let palette = &Palette::builtin()[0];
+    let path = env::temp_dir().join("chip8_recording_test.y4m");
+    let mut recorder = Recorder::start(&path, palette, 1, ToneSettings{sample_rate: 22050, ..ToneSettings::new()}).unwrap();
+    let frame = [[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+    recorder.frame(&frame, palette, true).unwrap();
+    recorder.frame(&frame, palette, false).unwrap();
+    recorder.frame(&frame, palette, false).unwrap();
+    recorder.finish().unwrap();
+
+    let video = fs::read(&path).unwrap();
+    let audio = fs::read(path.with_extension("wav")).unwrap();
+    fs::remove_file(&path).unwrap();
+    fs::remove_file(path.with_extension("wav")).unwrap();
+
+    let header = b"YUV4MPEG2 W64 H32 F60:1 Ip A1:1 C444\n";
+    assert!(video.starts_with(header));
+    assert_eq!(video.len(), header.len() + 3 * (6 + DISPLAY_WIDTH * DISPLAY_HEIGHT * 3));
+    //22050 / 60 = 367.5 samples a frame
+    assert_eq!(audio.len(), 44 + (367 + 368 + 367) * 2);
+}
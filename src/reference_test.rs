@@ -0,0 +1,158 @@
+//A deliberately simple CHIP-8 interpreter written straight from Cowgod's technical reference,
+//kept apart from CPU so differential tests can check one against the other.
+//Where the reference leaves a choice open it follows the behaviour CPU is meant to have:
+//  8XY6 and 8XYE shift Vx and ignore Vy
+//  FX55 and FX65 leave I unchanged
+//  BNNN jumps to NNN + V0
+//  sprites wrap around the edges of the display
+//  8XY4 to 8XYE leave the flag in VF even when VF is the destination
+//  FX1E sets VF when I overflows past 0xFFF
+//Anything the reference does not define, such as running off the end of memory,
+//stops the program instead of guessing.
+pub const MEMORY_SIZE: usize = 4096;
+pub const WIDTH: usize = 64;
+pub const HEIGHT: usize = 32;
+
+//Why a program cannot go on
+#[derive(Debug, PartialEq)]
+pub enum Stop {
+    PcOutOfMemory,
+    Undefined(u16),
+    StackOverflow,
+    StackUnderflow,
+    MemoryOutOfRange,
+    InvalidKey,
+    WaitsForKey,
+}
+
+pub struct Reference {
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: u16,
+    pub stack: Vec<u16>,
+    pub dt: u8,
+    pub st: u8,
+    pub memory: Vec<u8>,
+    pub display: [[bool; WIDTH]; HEIGHT],
+    pub keypad: [bool; 16],
+}
+
+impl Reference {
+    //Returns the next opcode, or why it cannot be executed
+    pub fn next(&self) -> Result<u16, Stop> {
+        let pc = self.pc as usize;
+        //Leaves room for the opcode and a skip over the next one
+        if pc + 4 >= MEMORY_SIZE {
+            return Err(Stop::PcOutOfMemory);
+        }
+        let opcode = u16::from_be_bytes([self.memory[pc], self.memory[pc + 1]]);
+        let x = (opcode >> 8 & 0xF) as usize;
+        let n = (opcode & 0xF) as usize;
+        let i = self.i as usize;
+        let within = |length: usize| if i + length <= MEMORY_SIZE { Ok(opcode) } else { Err(Stop::MemoryOutOfRange) };
+        match opcode >> 12 {
+            0x0 if opcode == 0x00E0 => Ok(opcode),
+            0x0 if opcode == 0x00EE => if self.stack.is_empty() { Err(Stop::StackUnderflow) } else { Ok(opcode) },
+            0x2 => if self.stack.len() == 16 { Err(Stop::StackOverflow) } else { Ok(opcode) },
+            0xB => if (opcode & 0xFFF) as usize + (self.v[0] as usize) < MEMORY_SIZE { Ok(opcode) } else { Err(Stop::PcOutOfMemory) },
+            0x1 | 0x3 | 0x4 | 0x6 | 0x7 | 0xA | 0xC => Ok(opcode),
+            0x5 | 0x9 if n == 0 => Ok(opcode),
+            0x8 if matches!(n, 0x0..=0x7 | 0xE) => Ok(opcode),
+            0xD => within(n),
+            0xE if matches!(opcode & 0xFF, 0x9E | 0xA1) => if self.v[x] < 16 { Ok(opcode) } else { Err(Stop::InvalidKey) },
+            0xF => match opcode & 0xFF {
+                0x0A => if self.keypad.contains(&true) { Ok(opcode) } else { Err(Stop::WaitsForKey) },
+                0x07 | 0x15 | 0x18 | 0x1E | 0x29 => Ok(opcode),
+                0x33 => within(3),
+                0x55 | 0x65 => within(x + 1),
+                _ => Err(Stop::Undefined(opcode)),
+            },
+            _ => Err(Stop::Undefined(opcode)),
+        }
+    }
+
+    //Executes an opcode accepted by next, random stands in for the byte CXKK draws
+    pub fn execute(&mut self, opcode: u16, random: u8) {
+        let x = (opcode >> 8 & 0xF) as usize;
+        let y = (opcode >> 4 & 0xF) as usize;
+        let n = (opcode & 0xF) as usize;
+        let nnn = opcode & 0xFFF;
+        let kk = (opcode & 0xFF) as u8;
+        let (vx, vy) = (self.v[x], self.v[y]);
+        let mut next = self.pc + 2;
+        match (opcode >> 12, n) {
+            (0x0, _) if kk == 0xE0 => self.display = [[false; WIDTH]; HEIGHT],
+            (0x0, _) => next = self.stack.pop().unwrap(),
+            (0x1, _) => next = nnn,
+            (0x2, _) => {
+                self.stack.push(next);
+                next = nnn;
+            }
+            (0x3, _) => if vx == kk { next += 2 },
+            (0x4, _) => if vx != kk { next += 2 },
+            (0x5, _) => if vx == vy { next += 2 },
+            (0x6, _) => self.v[x] = kk,
+            (0x7, _) => self.v[x] = vx.wrapping_add(kk),
+            (0x8, 0x0) => self.v[x] = vy,
+            (0x8, 0x1) => self.v[x] = vx | vy,
+            (0x8, 0x2) => self.v[x] = vx & vy,
+            (0x8, 0x3) => self.v[x] = vx ^ vy,
+            (0x8, 0x4) => self.set_with_flag(x, vx.wrapping_add(vy), vx as u16 + vy as u16 > 0xFF),
+            (0x8, 0x5) => self.set_with_flag(x, vx.wrapping_sub(vy), vx > vy),
+            (0x8, 0x6) => self.set_with_flag(x, vx / 2, vx % 2 == 1),
+            (0x8, 0x7) => self.set_with_flag(x, vy.wrapping_sub(vx), vy > vx),
+            (0x8, _) => self.set_with_flag(x, vx.wrapping_mul(2), vx >= 0x80),
+            (0x9, _) => if vx != vy { next += 2 },
+            (0xA, _) => self.i = nnn,
+            (0xB, _) => next = nnn + self.v[0] as u16,
+            (0xC, _) => self.v[x] = random & kk,
+            (0xD, _) => {
+                let mut collision = false;
+                for row in 0..n {
+                    let sprite = self.memory[self.i as usize + row];
+                    for column in 0..8 {
+                        if sprite & (0x80 >> column) != 0 {
+                            let pixel = &mut self.display[(vy as usize + row) % HEIGHT][(vx as usize + column) % WIDTH];
+                            collision |= *pixel;
+                            *pixel = !*pixel;
+                        }
+                    }
+                }
+                self.v[0xF] = collision as u8;
+            }
+            (0xE, _) if kk == 0x9E => if self.keypad[vx as usize] { next += 2 },
+            (0xE, _) => if !self.keypad[vx as usize] { next += 2 },
+            (0xF, _) => match kk {
+                0x07 => self.v[x] = self.dt,
+                0x0A => self.v[x] = self.keypad.iter().position(|&pressed| pressed).unwrap() as u8,
+                0x15 => self.dt = vx,
+                0x18 => self.st = vx,
+                0x1E => {
+                    let sum = self.i + vx as u16;
+                    self.i = sum & 0xFFF;
+                    self.v[0xF] = (sum > 0xFFF) as u8;
+                }
+                0x29 => self.i = vx as u16 * 5,
+                0x33 => {
+                    let i = self.i as usize;
+                    self.memory[i..i + 3].copy_from_slice(&[vx / 100, vx / 10 % 10, vx % 10]);
+                }
+                0x55 => {
+                    let i = self.i as usize;
+                    self.memory[i..=i + x].copy_from_slice(&self.v[..=x]);
+                }
+                _ => {
+                    let i = self.i as usize;
+                    self.v[..=x].copy_from_slice(&self.memory[i..=i + x]);
+                }
+            },
+            _ => unreachable!("{:04X} was not accepted by next", opcode),
+        }
+        self.pc = next;
+    }
+
+    fn set_with_flag(&mut self, x: usize, value: u8, flag: bool) {
+        self.v[x] = value;
+        self.v[0xF] = flag as u8;
+    }
+}
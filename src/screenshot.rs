@@ -0,0 +1,43 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+use chrono::Local;
+
+use crate::{DISPLAY_WIDTH, DISPLAY_HEIGHT, VRAM};
+use crate::palette::Palette;
+
+//Colours a presented frame with the palette, each pixel becoming a scale x scale block of RGB bytes
+pub fn rgb_frame(frame: &VRAM, palette: &Palette, scale: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(DISPLAY_WIDTH * DISPLAY_HEIGHT * scale * scale * 3);
+    for row in frame.iter() {
+        for _ in 0..scale {
+            for &shade in row.iter() {
+                let color = palette.shade(shade);
+                for _ in 0..scale {
+                    data.extend_from_slice(&[color.0, color.1, color.2]);
+                }
+            }
+        }
+    }
+    data
+}
+
+pub fn save_png(path: &Path, frame: &VRAM, palette: &Palette, scale: usize) -> io::Result<()> {
+    let writer = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(writer, (DISPLAY_WIDTH * scale) as u32, (DISPLAY_HEIGHT * scale) as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(&rgb_frame(frame, palette, scale)).map_err(io::Error::other)
+}
+
+//"roms/tetris.rom" becomes "tetris-20261018-142530.png" for the png extension
+pub fn capture_name(rom_path: &str, extension: &str) -> String {
+    let rom_name = Path::new(rom_path).file_stem().and_then(|stem| stem.to_str()).unwrap_or("chip8");
+    format!("{}-{}.{}", rom_name, Local::now().format("%Y%m%d-%H%M%S"), extension)
+}
+
+#[cfg(test)]
+#[path = "./screenshot_test.rs"]
+mod screenshot_test;
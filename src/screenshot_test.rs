@@ -0,0 +1,46 @@
+use std::env;
+use std::fs::{self, File};
+
+use crate::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use crate::palette::{Palette, Rgb};
+use crate::persistence::FULL_SHADE;
+use super::{capture_name, rgb_frame, save_png};
+
+#[test]
+fn test_rgb_frame_scales_pixels() {
+    let palette = Palette::new("test", &[Rgb(1, 2, 3), Rgb(4, 5, 6)]);
+    let mut frame = [[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+    frame[0][1] = FULL_SHADE;
+    let data = rgb_frame(&frame, &palette, 2);
+    assert_eq!(data.len(), DISPLAY_WIDTH * DISPLAY_HEIGHT * 4 * 3);
+    assert_eq!(&data[0..12], &[1, 2, 3, 1, 2, 3, 4, 5, 6, 4, 5, 6]);
+    let second_line = DISPLAY_WIDTH * 2 * 3;
+    assert_eq!(&data[second_line + 6..second_line + 9], &[4, 5, 6]);
+}
+
+#[test]
+fn test_save_png() {
+    let palette = Palette::new("test", &[Rgb(0, 0, 0), Rgb(255, 128, 0)]);
+    let mut frame = [[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+    frame[DISPLAY_HEIGHT - 1][DISPLAY_WIDTH - 1] = FULL_SHADE;
+    let path = env::temp_dir().join("chip8_screenshot_test.png");
+    save_png(&path, &frame, &palette, 1).unwrap();
+
+    let decoder = png::Decoder::new(File::open(&path).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!((info.width, info.height), (DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32));
+    assert_eq!(&data[data.len() - 3..], &[255, 128, 0]);
+    assert_eq!(&data[0..3], &[0, 0, 0]);
+}
+
+#[test]
+fn test_capture_name() {
+    let name = capture_name("roms/tetris.rom", "png");
+    assert!(name.starts_with("tetris-"));
+    assert!(name.ends_with(".png"));
+    assert_eq!(name.len(), "tetris-20261018-142530.png".len());
+}
@@ -0,0 +1,95 @@
+//Instruction costs of the original COSMAC VIP interpreter, in 1802 machine cycles
+//(8 clock periods of the 1.7609 MHz crystal, roughly 4.54 microseconds each)
+
+type OpCode = u16;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Timing {
+    //One instruction per tick of the main loop
+    Instruction,
+    //Each instruction is charged what it cost on the COSMAC VIP
+    CosmacVip,
+}
+
+//Machine cycles between two 60 Hz interrupts
+const VIP_CYCLES_PER_FRAME: u32 = 3668;
+//The CDP1861 steals one cycle per displayed byte: 32 rows, each shown 4 times, 8 bytes wide
+const VIP_DISPLAY_DMA_CYCLES: u32 = 1024;
+//Interrupt routine servicing the timers and setting up the display DMA
+const VIP_INTERRUPT_CYCLES: u32 = 46;
+//What is left for the interpreter each frame
+pub const VIP_CYCLES_AVAILABLE_PER_FRAME: u32 = VIP_CYCLES_PER_FRAME - VIP_DISPLAY_DMA_CYCLES - VIP_INTERRUPT_CYCLES;
+
+//Extra cost of the conditional instructions when the next instruction is skipped
+pub const SKIP_CYCLES: u32 = 4;
+//Extra cost when an address calculation carries into the high byte
+const PAGE_CROSS_CYCLES: u32 = 4;
+
+const DRAW_SETUP_CYCLES: u32 = 26;
+const DRAW_ROW_CYCLES: u32 = 34;
+//A row not aligned to a byte boundary is shifted right one bit at a time
+const DRAW_SHIFT_CYCLES: u32 = 8;
+//and then written to two display bytes instead of one
+const DRAW_SPLIT_ROW_CYCLES: u32 = 16;
+
+//Cost of an instruction on the VIP, not counting the extra cycles of a taken skip. The fixed costs are the
+//published whole instruction times, fetching and decoding included, converted from microseconds.
+//vx is the value of the register selected by the X nibble, v0 and i the current V0 and I.
+pub fn cosmac_vip_cycles(opcode: OpCode, vx: u8, v0: u8, i: usize) -> u32 {
+    let c = (opcode & 0xF000) >> 12;
+    let x = ((opcode & 0x0F00) >> 8) as u32;
+    let n = (opcode & 0x000F) as u32;
+    let nnn = (opcode & 0x0FFF) as usize;
+    let kk = opcode & 0x00FF;
+
+    match (c, kk) {
+        (0x0, 0xE0) => 24,
+        (0x0, 0xEE) => 23,
+        //Machine code routines are not run, so they are charged like a call
+        (0x0, _) | (0x1, _) | (0x2, _) => 23,
+        (0x3, _) | (0x4, _) => 12,
+        (0x5, _) | (0x9, _) => 16,
+        (0x6, _) => 6,
+        (0x7, _) => 10,
+        (0x8, _) => 44,
+        (0xA, _) => 12,
+        (0xB, _) => 23 + page_cross(nnn, nnn + v0 as usize),
+        (0xC, _) => 36,
+        (0xD, _) => sprite_cycles(vx, n),
+        (0xE, _) => 16,
+        (0xF, 0x1E) => 19 + page_cross(i, i + vx as usize),
+        (0xF, 0x29) => 20,
+        (0xF, 0x33) => 80 + 16 * decimal_digit_sum(vx),
+        (0xF, 0x55) | (0xF, 0x65) => 14 + 14 * (x + 1),
+        (0xF, _) => 10,
+        _ => 0,
+    }
+}
+
+//Drawing a sprite blocks the interpreter until the next display interrupt
+pub fn waits_for_vblank(opcode: OpCode) -> bool {
+    opcode & 0xF000 == 0xD000
+}
+
+fn sprite_cycles(x: u8, rows: u32) -> u32 {
+    let shift = (x % 8) as u32;
+    let row = if shift == 0 {
+        DRAW_ROW_CYCLES
+    } else {
+        DRAW_ROW_CYCLES + shift * DRAW_SHIFT_CYCLES + DRAW_SPLIT_ROW_CYCLES
+    };
+    DRAW_SETUP_CYCLES + rows * row
+}
+
+fn page_cross(from: usize, to: usize) -> u32 {
+    if from >> 8 != to >> 8 { PAGE_CROSS_CYCLES } else { 0 }
+}
+
+//FX33 builds each digit by repeated subtraction, so bigger digits take longer
+fn decimal_digit_sum(value: u8) -> u32 {
+    (value / 100 + (value % 100) / 10 + value % 10) as u32
+}
+
+#[cfg(test)]
+#[path = "./timing_test.rs"]
+mod timing_test;
@@ -0,0 +1,37 @@
+use super::{cosmac_vip_cycles, waits_for_vblank, VIP_CYCLES_AVAILABLE_PER_FRAME};
+
+#[test]
+fn test_fixed_cost_instructions() {
+    //Whole instruction times, without a separate fetch on top
+    assert_eq!(cosmac_vip_cycles(0x00E0, 0, 0, 0), 24);
+    assert_eq!(cosmac_vip_cycles(0x6123, 0, 0, 0), 6);
+    assert_eq!(cosmac_vip_cycles(0x8124, 0, 0, 0), 44);
+    assert_eq!(cosmac_vip_cycles(0xA123, 0, 0, 0), 12);
+    assert_eq!(cosmac_vip_cycles(0x1200, 0, 0, 0), 23);
+}
+
+#[test]
+fn test_sprite_cost_depends_on_height_and_position() {
+    let aligned = cosmac_vip_cycles(0xD125, 8, 0, 0);
+    let taller = cosmac_vip_cycles(0xD12A, 8, 0, 0);
+    let unaligned = cosmac_vip_cycles(0xD125, 11, 0, 0);
+    assert!(taller > aligned);
+    assert!(unaligned > aligned);
+    assert!(waits_for_vblank(0xD125));
+    assert!(!waits_for_vblank(0x6123));
+}
+
+#[test]
+fn test_page_crossing_costs_extra() {
+    let same_page = cosmac_vip_cycles(0xB210, 0, 0x10, 0);
+    let next_page = cosmac_vip_cycles(0xB2F0, 0, 0x20, 0);
+    assert_eq!(next_page - same_page, 4);
+    let same_page = cosmac_vip_cycles(0xF11E, 0x10, 0, 0x300);
+    let next_page = cosmac_vip_cycles(0xF11E, 0x10, 0, 0x3F8);
+    assert_eq!(next_page - same_page, 4);
+}
+
+#[test]
+fn test_frame_budget() {
+    assert_eq!(VIP_CYCLES_AVAILABLE_PER_FRAME, 2598);
+}
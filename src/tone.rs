@@ -0,0 +1,122 @@
+//Buzzer tone generator shared by the SDL audio device and the WAV writers
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Waveform {
+    Square,
+    Sine,
+    Triangle,
+    Sawtooth,
+    Noise,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ToneSettings {
+    pub waveform: Waveform,
+    pub frequency: f32,
+    //0.0 to 1.0
+    pub volume: f32,
+    //Fraction of each period a square wave is high
+    pub duty_cycle: f32,
+    pub sample_rate: u32,
+}
+
+impl ToneSettings {
+    pub fn new() -> Self {
+        ToneSettings {
+            waveform: Waveform::Square,
+            frequency: 240.0,
+            volume: 0.25,
+            duty_cycle: 0.5,
+            sample_rate: 44100,
+        }
+    }
+}
+
+impl Default for ToneSettings {
+    fn default() -> Self {
+        ToneSettings::new()
+    }
+}
+
+//Starting and stopping ramps the volume over this long, so the speaker does not click
+const ENVELOPE_SECONDS: f32 = 0.005;
+
+pub struct Tone {
+    settings: ToneSettings,
+    on: bool,
+    muted: bool,
+    phase: f32,
+    envelope: f32,
+    noise: u32,
+    noise_sample: f32,
+}
+
+impl Tone {
+    pub fn new(settings: ToneSettings) -> Self {
+        Tone{settings, on: false, muted: false, phase: 0.0, envelope: 0.0, noise: 0x1234_5678, noise_sample: 0.0}
+    }
+
+    pub fn set_on(&mut self, on: bool) {
+        self.on = on;
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    pub fn fill(&mut self, out: &mut [f32]) {
+        let sample_rate = self.settings.sample_rate as f32;
+        let phase_increment = self.settings.frequency / sample_rate;
+        let envelope_step = 1.0 / (ENVELOPE_SECONDS * sample_rate);
+        let target = if self.on && !self.muted { 1.0 } else { 0.0 };
+
+        for x in out.iter_mut() {
+            self.envelope = if self.envelope < target {
+                (self.envelope + envelope_step).min(target)
+            } else {
+                (self.envelope - envelope_step).max(target)
+            };
+            if self.envelope == 0.0 {
+                *x = 0.0;
+                continue;
+            }
+            *x = self.settings.volume * self.envelope * self.wave();
+            let previous_phase = self.phase;
+            self.phase = (self.phase + phase_increment) % 1.0;
+            //Noise holds each random level for half a period, so it still follows the pitch
+            if (previous_phase < 0.5) != (self.phase < 0.5) {
+                self.noise_sample = self.next_noise();
+            }
+        }
+    }
+
+    //Samples as 16 bit PCM, for WAV files
+    pub fn samples(&mut self, count: usize) -> Vec<i16> {
+        let mut out = vec![0.0; count];
+        self.fill(&mut out);
+        out.iter().map(|sample| (sample * i16::MAX as f32) as i16).collect()
+    }
+
+    fn wave(&self) -> f32 {
+        let phase = self.phase;
+        match self.settings.waveform {
+            Waveform::Square => if phase < self.settings.duty_cycle {1.0} else {-1.0},
+            Waveform::Sine => (phase * std::f32::consts::TAU).sin(),
+            Waveform::Triangle => if phase < 0.5 {4.0 * phase - 1.0} else {3.0 - 4.0 * phase},
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+            Waveform::Noise => self.noise_sample,
+        }
+    }
+
+    //xorshift32
+    fn next_noise(&mut self) -> f32 {
+        self.noise ^= self.noise << 13;
+        self.noise ^= self.noise >> 17;
+        self.noise ^= self.noise << 5;
+        (self.noise as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+}
+
+#[cfg(test)]
+#[path = "./tone_test.rs"]
+mod tone_test;
@@ -0,0 +1,58 @@
+use super::{Tone, ToneSettings, Waveform};
+
+fn settings(waveform: Waveform) -> ToneSettings {
+    ToneSettings{waveform, frequency: 125.0, volume: 1.0, duty_cycle: 0.25, sample_rate: 1000}
+}
+
+#[test]
+fn test_silent_until_started() {
+    let mut tone = Tone::new(settings(Waveform::Square));
+    let mut out = [1.0; 10];
+    tone.fill(&mut out);
+    assert_eq!(out, [0.0; 10]);
+}
+
+#[test]
+fn test_attack_and_release_ramp() {
+    let mut tone = Tone::new(settings(Waveform::Square));
+    tone.set_on(true);
+    let mut out = [0.0; 10];
+    tone.fill(&mut out);
+    //The envelope takes 5 samples at 1000 Hz to open fully
+    assert!(out[0] > 0.0 && out[0] < 1.0);
+    assert_eq!(out[5].abs(), 1.0);
+    tone.set_on(false);
+    tone.fill(&mut out);
+    assert!(out[0].abs() < 1.0);
+    assert_eq!(out[9], 0.0);
+}
+
+#[test]
+fn test_square_duty_cycle() {
+    let mut tone = Tone::new(settings(Waveform::Square));
+    tone.set_on(true);
+    let mut out = [0.0; 16];
+    tone.fill(&mut out);
+    //8 samples per period, high for a quarter of it
+    assert_eq!(&out[8..16], &[1.0, 1.0, -1.0, -1.0, -1.0, -1.0, -1.0, -1.0]);
+}
+
+#[test]
+fn test_waveforms_stay_in_range() {
+    for waveform in [Waveform::Sine, Waveform::Triangle, Waveform::Sawtooth, Waveform::Noise] {
+        let mut tone = Tone::new(settings(waveform));
+        tone.set_on(true);
+        let mut out = [0.0; 100];
+        tone.fill(&mut out);
+        assert!(out.iter().all(|sample| sample.abs() <= 1.0));
+        assert!(out.iter().any(|&sample| sample != 0.0));
+    }
+}
+
+#[test]
+fn test_muted() {
+    let mut tone = Tone::new(settings(Waveform::Square));
+    tone.set_on(true);
+    tone.set_muted(true);
+    assert_eq!(tone.samples(10), vec![0; 10]);
+}
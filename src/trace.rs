@@ -0,0 +1,166 @@
+//Execution trace of every instruction, for diffing runs against other emulators.
+//Text traces have one line per instruction:
+//  cycles PC opcode disassembly I= DT= ST= and the V registers that changed, as Vx:old->new
+//Binary traces start with "C8TR" and a version byte, followed by one record per instruction:
+//  cycles since the previous record u32, PC u16, opcode u16, I u16, DT u8, ST u8,
+//  changed V mask u16 (all little endian), then the old and new value of each changed register
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+
+use crate::cpu::Registers;
+use crate::disassembler::disassemble;
+
+const BINARY_MAGIC: &[u8; 4] = b"C8TR";
+const BINARY_VERSION: u8 = 1;
+const RECORD_SIZE: usize = 14;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TraceFormat {
+    Text,
+    Binary,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFilter {
+    //Inclusive range of PC values to trace
+    pub addresses: Option<(usize, usize)>,
+    //Leading hex digits of the opcodes to trace, all of them when empty
+    pub classes: Vec<u8>,
+}
+
+impl TraceFilter {
+    pub fn new() -> Self {
+        TraceFilter{addresses: None, classes: Vec::new()}
+    }
+
+    fn accepts(&self, pc: usize, opcode: u16) -> bool {
+        let in_range = self.addresses.is_none_or(|(first, last)| (first..=last).contains(&pc));
+        in_range && (self.classes.is_empty() || self.classes.contains(&((opcode >> 12) as u8)))
+    }
+}
+
+impl Default for TraceFilter {
+    fn default() -> Self {
+        TraceFilter::new()
+    }
+}
+
+pub struct Tracer {
+    writer: BufWriter<Box<dyn Write>>,
+    format: TraceFormat,
+    filter: TraceFilter,
+    //Machine cycles since the trace started
+    cycles: u64,
+    //Value of cycles at the last record written
+    recorded: u64,
+}
+
+impl Tracer {
+    //"-" traces to stdout
+    pub fn create(path: &str, format: TraceFormat, filter: TraceFilter) -> io::Result<Self> {
+        let writer: Box<dyn Write> = match path {
+            "-" => Box::new(io::stdout()),
+            path => Box::new(File::create(path)?),
+        };
+        Tracer::new(writer, format, filter)
+    }
+
+    pub fn new(writer: Box<dyn Write>, format: TraceFormat, filter: TraceFilter) -> io::Result<Self> {
+        let mut writer = BufWriter::new(writer);
+        if format == TraceFormat::Binary {
+            writer.write_all(BINARY_MAGIC)?;
+            writer.write_all(&[BINARY_VERSION])?;
+        }
+        Ok(Tracer{writer, format, filter, cycles: 0, recorded: 0})
+    }
+
+    //Called after each instruction with the registers from before and after it
+    pub fn record(&mut self, opcode: u16, cycles: u32, before: &Registers, after: &Registers) -> io::Result<()> {
+        self.cycles += cycles as u64;
+        if !self.filter.accepts(before.pc as usize, opcode) {
+            return Ok(());
+        }
+        let elapsed = self.cycles - self.recorded;
+        self.recorded = self.cycles;
+        match self.format {
+            TraceFormat::Text => writeln!(self.writer, "{}", text_line(self.cycles, opcode, before, after)),
+            TraceFormat::Binary => self.writer.write_all(&binary_record(elapsed, opcode, before, after)),
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn text_line(cycles: u64, opcode: u16, before: &Registers, after: &Registers) -> String {
+    let mut line = format!("{:>10} {:03X} {:04X} {:<16} I={:03X} DT={:02X} ST={:02X}",
+        cycles, before.pc, opcode, disassemble(opcode), after.i, after.dt, after.st);
+    for (x, (old, new)) in before.v.iter().zip(after.v.iter()).enumerate() {
+        if old != new {
+            line.push_str(&format!(" V{:X}:{:02X}->{:02X}", x, old, new));
+        }
+    }
+    line
+}
+
+fn binary_record(cycles: u64, opcode: u16, before: &Registers, after: &Registers) -> Vec<u8> {
+    let mut changed: u16 = 0;
+    let mut values = Vec::new();
+    for (x, (old, new)) in before.v.iter().zip(after.v.iter()).enumerate() {
+        if old != new {
+            changed |= 1 << x;
+            values.extend_from_slice(&[*old, *new]);
+        }
+    }
+    let mut record = Vec::with_capacity(RECORD_SIZE + values.len());
+    record.extend_from_slice(&(cycles.min(u32::MAX as u64) as u32).to_le_bytes());
+    record.extend_from_slice(&before.pc.to_le_bytes());
+    record.extend_from_slice(&opcode.to_le_bytes());
+    record.extend_from_slice(&after.i.to_le_bytes());
+    record.extend_from_slice(&[after.dt, after.st]);
+    record.extend_from_slice(&changed.to_le_bytes());
+    record.extend_from_slice(&values);
+    record
+}
+
+//Turns a binary trace back into the text format
+pub fn binary_to_text(reader: &mut dyn Read, writer: &mut dyn Write) -> io::Result<()> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    if data.len() < BINARY_MAGIC.len() + 1 || &data[..4] != BINARY_MAGIC || data[4] != BINARY_VERSION {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a binary trace"));
+    }
+    let mut cycles: u64 = 0;
+    let mut data = &data[5..];
+    while !data.is_empty() {
+        if data.len() < RECORD_SIZE {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated trace record"));
+        }
+        let word = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+        let changed = word(12);
+        let count = 2 * changed.count_ones() as usize;
+        if data.len() < RECORD_SIZE + count {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated trace record"));
+        }
+        cycles += u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as u64;
+        let mut before = Registers{v: [0; 16], i: 0, pc: word(4), sp: 0, dt: 0, st: 0};
+        let mut after = Registers{i: word(8), dt: data[10], st: data[11], ..before};
+        let mut values = data[RECORD_SIZE..RECORD_SIZE + count].chunks(2);
+        for x in 0..16 {
+            if changed & (1 << x) != 0 {
+                if let Some(&[old, new]) = values.next() {
+                    before.v[x] = old;
+                    after.v[x] = new;
+                }
+            }
+        }
+        writeln!(writer, "{}", text_line(cycles, word(6), &before, &after))?;
+        data = &data[RECORD_SIZE + count..];
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "./trace_test.rs"]
+mod trace_test;
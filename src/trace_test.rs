@@ -0,0 +1,94 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use crate::cpu::Registers;
+use super::{binary_to_text, TraceFilter, TraceFormat, Tracer};
+
+//Keeps what the tracer wrote readable after the tracer takes the writer
+#[derive(Clone)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(data)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn registers(pc: u16) -> Registers {
+    Registers{v: [0; 16], i: 0, pc, sp: 0, dt: 0, st: 0}
+}
+
+fn trace(format: TraceFormat, filter: TraceFilter) -> Vec<u8> {
+    let buffer = SharedBuffer(Rc::new(RefCell::new(Vec::new())));
+    let mut tracer = Tracer::new(Box::new(buffer.clone()), format, filter).unwrap();
+    let mut after = registers(0x202);
+    after.v[1] = 0x05;
+    tracer.record(0x6105, 1, &registers(0x200), &after).unwrap();
+    let before = after;
+    after.pc = 0x204;
+    after.i = 0x2F0;
+    tracer.record(0xA2F0, 1, &before, &after).unwrap();
+    let before = after;
+    after.pc = 0x206;
+    after.v[0xF] = 0x01;
+    after.dt = 0x3C;
+    tracer.record(0xD015, 22, &before, &after).unwrap();
+    tracer.flush().unwrap();
+    let data = buffer.0.borrow().clone();
+    data
+}
+
+#[test]
+fn test_text_trace() {
+    let text = String::from_utf8(trace(TraceFormat::Text, TraceFilter::new())).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines, vec![
+        "         1 200 6105 LD V1, 0x05      I=000 DT=00 ST=00 V1:00->05",
+        "         2 202 A2F0 LD I, 0x2F0      I=2F0 DT=00 ST=00",
+        "        24 204 D015 DRW V0, V1, 5    I=2F0 DT=3C ST=00 VF:00->01",
+    ]);
+}
+
+#[test]
+fn test_filters() {
+    let mut filter = TraceFilter::new();
+    filter.addresses = Some((0x202, 0x2FF));
+    let text = String::from_utf8(trace(TraceFormat::Text, filter)).unwrap();
+    assert_eq!(text.lines().count(), 2);
+    assert!(text.starts_with("         2 202 A2F0"));
+
+    let mut filter = TraceFilter::new();
+    filter.classes = vec![0x6, 0xD];
+    let text = String::from_utf8(trace(TraceFormat::Text, filter)).unwrap();
+    let opcodes: Vec<&str> = text.lines().map(|line| &line[15..19]).collect();
+    assert_eq!(opcodes, vec!["6105", "D015"]);
+}
+
+#[test]
+fn test_binary_trace_decodes_to_text() {
+    let binary = trace(TraceFormat::Binary, TraceFilter::new());
+    assert_eq!(&binary[..5], b"C8TR\x01");
+    let mut text = Vec::new();
+    binary_to_text(&mut &binary[..], &mut text).unwrap();
+    assert_eq!(text, trace(TraceFormat::Text, TraceFilter::new()));
+
+    let mut filter = TraceFilter::new();
+    filter.classes = vec![0xD];
+    let binary = trace(TraceFormat::Binary, filter.clone());
+    let mut text = Vec::new();
+    binary_to_text(&mut &binary[..], &mut text).unwrap();
+    assert_eq!(text, trace(TraceFormat::Text, filter));
+}
+
+#[test]
+fn test_binary_trace_rejects_bad_data() {
+    let mut text = Vec::new();
+    assert!(binary_to_text(&mut &b"NOPE\x01"[..], &mut text).is_err());
+    let binary = trace(TraceFormat::Binary, TraceFilter::new());
+    assert!(binary_to_text(&mut &binary[..binary.len() - 1], &mut text).is_err());
+}
@@ -0,0 +1,69 @@
+//Tells when a file has been saved, through inotify on Linux and the native file events elsewhere.
+//The directory is watched rather than the file, since editors often save by writing a new file and
+//renaming it over the old one, which a watch on the file itself would lose track of.
+use std::ffi::OsString;
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
+
+use notify::event::{AccessKind, AccessMode, EventKind};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+//Quiet time after the last write before the file counts as saved, so a save in several writes is read once, whole
+const SETTLE: Duration = Duration::from_millis(100);
+
+pub struct FileWatcher {
+    //Watching stops when it is dropped. Tests send the events themselves and have none.
+    _watcher: Option<RecommendedWatcher>,
+    events: Receiver<notify::Result<Event>>,
+    name: OsString,
+    //Time of the last write seen and not yet reported
+    changed_at: Option<Instant>,
+}
+
+impl FileWatcher {
+    pub fn new(path: &str) -> notify::Result<Self> {
+        let path = Path::new(path);
+        let name = path.file_name().ok_or_else(notify::Error::path_not_found)?.to_os_string();
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let (sender, events) = channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(FileWatcher{_watcher: Some(watcher), events, name, changed_at: None})
+    }
+
+    //True once for each save, after the writes have settled
+    pub fn changed(&mut self) -> bool {
+        self.changed_by(Instant::now())
+    }
+
+    fn changed_by(&mut self, now: Instant) -> bool {
+        while let Ok(event) = self.events.try_recv() {
+            let Ok(event) = event else {
+                continue;
+            };
+            if is_write(event.kind) && event.paths.iter().any(|path| path.file_name() == Some(self.name.as_os_str())) {
+                self.changed_at = Some(now);
+            }
+        }
+        match self.changed_at {
+            Some(changed_at) if now.duration_since(changed_at) >= SETTLE => {
+                self.changed_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+//Reading the file back on reload must not count as a change
+fn is_write(kind: EventKind) -> bool {
+    matches!(kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Access(AccessKind::Close(AccessMode::Write)))
+}
+
+#[cfg(test)]
+#[path = "./watch_test.rs"]
+mod watch_test;
@@ -0,0 +1,46 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Sender};
+use std::time::Instant;
+
+use notify::event::{AccessKind, AccessMode, CreateKind, DataChange, ModifyKind};
+use notify::{Event, EventKind};
+
+use super::{FileWatcher, SETTLE};
+
+fn watcher() -> (FileWatcher, Sender<notify::Result<Event>>) {
+    let (sender, events) = channel();
+    (FileWatcher{_watcher: None, events, name: OsString::from("game.8o"), changed_at: None}, sender)
+}
+
+fn event(kind: EventKind, name: &str) -> notify::Result<Event> {
+    Ok(Event::new(kind).add_path(PathBuf::from("roms").join(name)))
+}
+
+#[test]
+fn test_reports_saves_of_the_file() {
+    let (mut watcher, sender) = watcher();
+    let start = Instant::now();
+
+    sender.send(event(EventKind::Modify(ModifyKind::Data(DataChange::Content)), "other.8o")).unwrap();
+    assert!(!watcher.changed_by(start + SETTLE));
+    sender.send(event(EventKind::Modify(ModifyKind::Data(DataChange::Content)), "game.8o")).unwrap();
+    assert!(!watcher.changed_by(start));
+    //A second write before the first settles restarts the wait
+    sender.send(event(EventKind::Create(CreateKind::File), "game.8o")).unwrap();
+    assert!(!watcher.changed_by(start + SETTLE / 2));
+    assert!(!watcher.changed_by(start + SETTLE));
+    assert!(watcher.changed_by(start + SETTLE / 2 + SETTLE));
+    //Reported once, and reading the file back is not a change
+    assert!(!watcher.changed_by(start + SETTLE * 4));
+    sender.send(event(EventKind::Access(AccessKind::Close(AccessMode::Read)), "game.8o")).unwrap();
+    sender.send(Err(notify::Error::generic("lost events"))).unwrap();
+    assert!(!watcher.changed_by(start + SETTLE * 8));
+}
+
+#[test]
+fn test_watches_the_directory() {
+    let path = std::env::temp_dir().join("chip8_emulator_watch_test.8o");
+    assert!(FileWatcher::new(path.to_str().unwrap()).is_ok());
+    assert!(FileWatcher::new("").is_err());
+}
@@ -0,0 +1,90 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::tone::{Tone, ToneSettings};
+
+const BITS_PER_SAMPLE: u16 = 16;
+const HEADER_SIZE: u32 = 44;
+
+//16 bit mono PCM, the sizes in the header are filled in by finish
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+    sample_rate: u32,
+    samples: u32,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(mut writer: W, sample_rate: u32) -> io::Result<Self> {
+        writer.write_all(&header(sample_rate, 0))?;
+        Ok(WavWriter{writer, sample_rate, samples: 0})
+    }
+
+    pub fn write_samples(&mut self, samples: &[i16]) -> io::Result<()> {
+        let bytes: Vec<u8> = samples.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+        self.writer.write_all(&bytes)?;
+        self.samples += samples.len() as u32;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(&header(self.sample_rate, self.samples))?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+fn header(sample_rate: u32, samples: u32) -> Vec<u8> {
+    let block_align = BITS_PER_SAMPLE / 8;
+    let data_size = samples * block_align as u32;
+    let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(HEADER_SIZE - 8 + data_size).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes()); //PCM
+    header.extend_from_slice(&1u16.to_le_bytes()); //Mono
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&BITS_PER_SAMPLE.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_size.to_le_bytes());
+    header
+}
+
+//Renders the buzzer into a WAV file as the emulated sound timer runs. Each tick
+//covers 1/ticks_per_second of emulated time, whatever the host speed.
+pub struct AudioCapture {
+    wav: WavWriter<BufWriter<File>>,
+    tone: Tone,
+    sample_rate: u64,
+    ticks_per_second: u64,
+    ticks: u64,
+}
+
+impl AudioCapture {
+    pub fn create(path: &Path, tone: ToneSettings, ticks_per_second: u64) -> io::Result<Self> {
+        let wav = WavWriter::new(BufWriter::new(File::create(path)?), tone.sample_rate)?;
+        Ok(AudioCapture{wav, tone: Tone::new(tone), sample_rate: tone.sample_rate as u64, ticks_per_second, ticks: 0})
+    }
+
+    pub fn tick(&mut self, beep: bool) -> io::Result<()> {
+        //Rates that do not divide evenly get a sample more on some ticks so the sound does not drift
+        let samples = (self.ticks + 1) * self.sample_rate / self.ticks_per_second - self.ticks * self.sample_rate / self.ticks_per_second;
+        self.ticks += 1;
+        self.tone.set_on(beep);
+        self.wav.write_samples(&self.tone.samples(samples as usize))
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        self.wav.finish()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "./wav_test.rs"]
+mod wav_test;
@@ -0,0 +1,40 @@
+use std::env;
+use std::fs;
+use std::io::Cursor;
+
+use crate::tone::ToneSettings;
+use super::{AudioCapture, WavWriter};
+
+#[test]
+fn test_wav_header_sizes() {
+    let mut wav = WavWriter::new(Cursor::new(Vec::new()), 22050).unwrap();
+    wav.write_samples(&[1, -1, 2]).unwrap();
+    let data = wav.finish().unwrap().into_inner();
+    assert_eq!(data.len(), 44 + 6);
+    assert_eq!(&data[0..4], b"RIFF");
+    assert_eq!(u32::from_le_bytes([data[4], data[5], data[6], data[7]]), 36 + 6);
+    assert_eq!(u32::from_le_bytes([data[24], data[25], data[26], data[27]]), 22050);
+    assert_eq!(u32::from_le_bytes([data[28], data[29], data[30], data[31]]), 44100);
+    assert_eq!(u32::from_le_bytes([data[40], data[41], data[42], data[43]]), 6);
+    assert_eq!(&data[44..46], &1i16.to_le_bytes());
+}
+
+#[test]
+fn test_audio_capture_follows_ticks() {
+    let path = env::temp_dir().join("chip8_audio_capture_test.wav");
+    let tone = ToneSettings{sample_rate: 1000, volume: 1.0, ..ToneSettings::new()};
+    //One second at eight ticks a frame
+    let mut capture = AudioCapture::create(&path, tone, 480).unwrap();
+    for tick in 0..480 {
+        capture.tick(tick < 240).unwrap();
+    }
+    capture.finish().unwrap();
+    let data = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let samples: Vec<i16> = data[44..].chunks(2).map(|c| i16::from_le_bytes([c[0], c[1]])).collect();
+    assert_eq!(samples.len(), 1000);
+    assert!(samples[..500].iter().any(|&sample| sample != 0));
+    //The release envelope ends within a few samples of the buzzer stopping
+    assert!(samples[510..].iter().all(|&sample| sample == 0));
+}